@@ -0,0 +1,421 @@
+pub use syntax::AstNode;
+
+use syntax::{SyntaxElement, SyntaxKind, SyntaxNode, SyntaxToken};
+
+/// Implements `AstNode` for a node type that wraps nodes of a single kind.
+macro_rules! ast_node {
+    ($($name:ident),*) => {
+        $(
+            impl AstNode for $name {
+                fn cast(node: SyntaxNode) -> Option<Self> {
+                    (node.kind() == SyntaxKind::$name).then(|| Self(node))
+                }
+
+                fn syntax(&self) -> &SyntaxNode {
+                    &self.0
+                }
+            }
+        )*
+    };
+}
+
+ast_node!(
+    Root,
+    Import,
+    Use,
+    VariableDef,
+    FnDef,
+    ParamList,
+    Param,
+    InfixExpr,
+    Literal,
+    ParenExpr,
+    PrefixExpr,
+    VariableRef,
+    Block,
+    CallExpr
+);
+
+#[derive(Debug)]
+pub struct Root(SyntaxNode);
+
+impl Root {
+    pub fn stmts(&self) -> impl Iterator<Item = Stmt> {
+        self.0.children().filter_map(Stmt::cast)
+    }
+}
+
+#[derive(Debug)]
+pub enum Stmt {
+    Import(Import),
+    Use(Use),
+    VariableDef(VariableDef),
+    FnDef(FnDef),
+    Expr(Expr),
+}
+
+impl AstNode for Stmt {
+    fn cast(node: SyntaxNode) -> Option<Self> {
+        let result = match node.kind() {
+            SyntaxKind::Import => Self::Import(Import(node)),
+            SyntaxKind::Use => Self::Use(Use(node)),
+            SyntaxKind::VariableDef => Self::VariableDef(VariableDef(node)),
+            SyntaxKind::FnDef => Self::FnDef(FnDef(node)),
+            _ => Self::Expr(Expr::cast(node)?),
+        };
+
+        Some(result)
+    }
+
+    fn syntax(&self) -> &SyntaxNode {
+        match self {
+            Self::Import(import) => import.syntax(),
+            Self::Use(use_) => use_.syntax(),
+            Self::VariableDef(variable_def) => variable_def.syntax(),
+            Self::FnDef(fn_def) => fn_def.syntax(),
+            Self::Expr(expr) => expr.syntax(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Import(SyntaxNode);
+
+impl Import {
+    /// The string literal naming the imported file, quotes included.
+    pub fn path(&self) -> Option<SyntaxToken> {
+        first_token(&self.0, SyntaxKind::String)
+    }
+
+    /// The imported file's path, without the quotes.
+    pub fn path_text(&self) -> Option<String> {
+        let path = self.path()?;
+        Some(path.text().trim_matches('"').to_string())
+    }
+
+    pub fn alias(&self) -> Option<SyntaxToken> {
+        first_token(&self.0, SyntaxKind::Ident)
+    }
+}
+
+#[derive(Debug)]
+pub struct Use(SyntaxNode);
+
+impl Use {
+    pub fn module(&self) -> Option<SyntaxToken> {
+        qualified_name(&self.0).0
+    }
+
+    pub fn name(&self) -> Option<SyntaxToken> {
+        qualified_name(&self.0).1
+    }
+}
+
+#[derive(Debug)]
+pub struct VariableDef(SyntaxNode);
+
+impl VariableDef {
+    pub fn name(&self) -> Option<SyntaxToken> {
+        first_token(&self.0, SyntaxKind::Ident)
+    }
+
+    pub fn value(&self) -> Option<Expr> {
+        self.0.children().find_map(Expr::cast)
+    }
+}
+
+#[derive(Debug)]
+pub struct FnDef(SyntaxNode);
+
+impl FnDef {
+    pub fn name(&self) -> Option<SyntaxToken> {
+        first_token(&self.0, SyntaxKind::Ident)
+    }
+
+    pub fn param_list(&self) -> Option<ParamList> {
+        self.0.children().find_map(ParamList::cast)
+    }
+
+    pub fn body(&self) -> Option<Block> {
+        self.0.children().find_map(Block::cast)
+    }
+}
+
+#[derive(Debug)]
+pub struct ParamList(SyntaxNode);
+
+impl ParamList {
+    pub fn params(&self) -> impl Iterator<Item = Param> {
+        self.0
+            .children()
+            .filter(|node| node.kind() == SyntaxKind::Param)
+            .map(Param)
+    }
+}
+
+#[derive(Debug)]
+pub struct Param(SyntaxNode);
+
+impl Param {
+    pub fn name(&self) -> Option<SyntaxToken> {
+        first_token(&self.0, SyntaxKind::Ident)
+    }
+}
+
+#[derive(Debug)]
+pub enum Expr {
+    InfixExpr(InfixExpr),
+    Literal(Literal),
+    ParenExpr(ParenExpr),
+    PrefixExpr(PrefixExpr),
+    VariableRef(VariableRef),
+    Block(Block),
+    CallExpr(CallExpr),
+}
+
+impl AstNode for Expr {
+    fn cast(node: SyntaxNode) -> Option<Self> {
+        let result = match node.kind() {
+            SyntaxKind::InfixExpr => Self::InfixExpr(InfixExpr(node)),
+            SyntaxKind::Literal => Self::Literal(Literal(node)),
+            SyntaxKind::ParenExpr => Self::ParenExpr(ParenExpr(node)),
+            SyntaxKind::PrefixExpr => Self::PrefixExpr(PrefixExpr(node)),
+            SyntaxKind::VariableRef => Self::VariableRef(VariableRef(node)),
+            SyntaxKind::Block => Self::Block(Block(node)),
+            SyntaxKind::CallExpr => Self::CallExpr(CallExpr(node)),
+            _ => return None,
+        };
+
+        Some(result)
+    }
+
+    fn syntax(&self) -> &SyntaxNode {
+        match self {
+            Self::InfixExpr(infix_expr) => infix_expr.syntax(),
+            Self::Literal(literal) => literal.syntax(),
+            Self::ParenExpr(paren_expr) => paren_expr.syntax(),
+            Self::PrefixExpr(prefix_expr) => prefix_expr.syntax(),
+            Self::VariableRef(variable_ref) => variable_ref.syntax(),
+            Self::Block(block) => block.syntax(),
+            Self::CallExpr(call_expr) => call_expr.syntax(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct InfixExpr(SyntaxNode);
+
+impl InfixExpr {
+    pub fn lhs(&self) -> Option<Expr> {
+        self.0.children().find_map(Expr::cast)
+    }
+
+    pub fn rhs(&self) -> Option<Expr> {
+        self.0.children().filter_map(Expr::cast).nth(1)
+    }
+
+    pub fn op(&self) -> Option<SyntaxToken> {
+        self.0
+            .children_with_tokens()
+            .filter_map(SyntaxElement::into_token)
+            .find(|token| {
+                matches!(
+                    token.kind(),
+                    SyntaxKind::Plus | SyntaxKind::Minus | SyntaxKind::Star | SyntaxKind::Slash,
+                )
+            })
+    }
+}
+
+#[derive(Debug)]
+pub struct Literal(SyntaxNode);
+
+impl Literal {
+    pub fn parse(&self) -> Option<u64> {
+        self.0.first_token()?.text().parse().ok()
+    }
+}
+
+#[derive(Debug)]
+pub struct ParenExpr(SyntaxNode);
+
+impl ParenExpr {
+    pub fn expr(&self) -> Option<Expr> {
+        self.0.children().find_map(Expr::cast)
+    }
+}
+
+#[derive(Debug)]
+pub struct PrefixExpr(SyntaxNode);
+
+impl PrefixExpr {
+    pub fn expr(&self) -> Option<Expr> {
+        self.0.children().find_map(Expr::cast)
+    }
+
+    pub fn op(&self) -> Option<SyntaxToken> {
+        first_token(&self.0, SyntaxKind::Minus)
+    }
+}
+
+#[derive(Debug)]
+pub struct VariableRef(SyntaxNode);
+
+impl VariableRef {
+    /// The module the variable comes from, like `m` in `m.value`.
+    pub fn qualifier(&self) -> Option<SyntaxToken> {
+        qualified_name(&self.0).0
+    }
+
+    pub fn name(&self) -> Option<SyntaxToken> {
+        qualified_name(&self.0).1
+    }
+}
+
+#[derive(Debug)]
+pub struct Block(SyntaxNode);
+
+impl Block {
+    pub fn stmts(&self) -> impl Iterator<Item = Stmt> {
+        self.0.children().filter_map(Stmt::cast)
+    }
+}
+
+#[derive(Debug)]
+pub struct CallExpr(SyntaxNode);
+
+impl CallExpr {
+    /// The module the function comes from, like `m` in `m.f()`.
+    pub fn qualifier(&self) -> Option<SyntaxToken> {
+        qualified_name(&self.0).0
+    }
+
+    pub fn name(&self) -> Option<SyntaxToken> {
+        qualified_name(&self.0).1
+    }
+
+    pub fn args(&self) -> impl Iterator<Item = Expr> {
+        self.0
+            .children()
+            .filter(|node| node.kind() == SyntaxKind::ArgList)
+            .flat_map(|arg_list| arg_list.children())
+            .filter_map(Expr::cast)
+    }
+}
+
+fn first_token(node: &SyntaxNode, kind: SyntaxKind) -> Option<SyntaxToken> {
+    node.children_with_tokens()
+        .filter_map(SyntaxElement::into_token)
+        .find(|token| token.kind() == kind)
+}
+
+/// The module and name in `m.name`, or just the name if there's no dot.
+fn qualified_name(node: &SyntaxNode) -> (Option<SyntaxToken>, Option<SyntaxToken>) {
+    let tokens: Vec<_> = node
+        .children_with_tokens()
+        .filter_map(SyntaxElement::into_token)
+        .filter(|token| !token.kind().is_trivia())
+        .collect();
+    let ident =
+        |token: Option<&SyntaxToken>| token.filter(|t| t.kind() == SyntaxKind::Ident).cloned();
+
+    match tokens
+        .iter()
+        .position(|token| token.kind() == SyntaxKind::Dot)
+    {
+        Some(dot) => (ident(tokens[..dot].last()), ident(tokens.get(dot + 1))),
+        None => (None, first_token(node, SyntaxKind::Ident)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn root(input: &str) -> Root {
+        Root::cast(parser::parse(input).syntax()).unwrap()
+    }
+
+    #[test]
+    fn variable_def_name_and_value() {
+        let stmt = root("let foo = 1").stmts().next().unwrap();
+
+        let Stmt::VariableDef(variable_def) = stmt else {
+            panic!("expected a variable definition");
+        };
+
+        assert_eq!(variable_def.name().unwrap().text(), "foo");
+        assert!(matches!(variable_def.value(), Some(Expr::Literal(_))));
+    }
+
+    #[test]
+    fn infix_expr_operands() {
+        let stmt = root("a - 10").stmts().next().unwrap();
+
+        let Stmt::Expr(Expr::InfixExpr(infix_expr)) = stmt else {
+            panic!("expected an infix expression");
+        };
+
+        assert!(matches!(infix_expr.lhs(), Some(Expr::VariableRef(_))));
+        assert_eq!(infix_expr.op().unwrap().kind(), SyntaxKind::Minus);
+        assert!(matches!(infix_expr.rhs(), Some(Expr::Literal(_))));
+    }
+
+    #[test]
+    fn fn_def_params_and_body() {
+        let stmt = root("fn add(x, y) { x + y }").stmts().next().unwrap();
+
+        let Stmt::FnDef(fn_def) = stmt else {
+            panic!("expected a function definition");
+        };
+
+        let params: Vec<_> = fn_def
+            .param_list()
+            .unwrap()
+            .params()
+            .map(|param| param.name().unwrap().text().to_string())
+            .collect();
+
+        assert_eq!(fn_def.name().unwrap().text(), "add");
+        assert_eq!(params, ["x", "y"]);
+        assert_eq!(fn_def.body().unwrap().stmts().count(), 1);
+    }
+
+    #[test]
+    fn call_expr_args() {
+        let stmt = root("add(1, (2))").stmts().next().unwrap();
+
+        let Stmt::Expr(Expr::CallExpr(call_expr)) = stmt else {
+            panic!("expected a call expression");
+        };
+
+        assert_eq!(call_expr.name().unwrap().text(), "add");
+        assert_eq!(call_expr.args().count(), 2);
+    }
+
+    #[test]
+    fn import_use_and_qualified_names() {
+        let mut stmts = root("import \"lib/math.ff\" as math\nuse math.square\nmath.pi")
+            .stmts()
+            .collect::<Vec<_>>()
+            .into_iter();
+
+        let Some(Stmt::Import(import)) = stmts.next() else {
+            panic!("expected an import");
+        };
+        assert_eq!(import.path_text().unwrap(), "lib/math.ff");
+        assert_eq!(import.alias().unwrap().text(), "math");
+
+        let Some(Stmt::Use(use_)) = stmts.next() else {
+            panic!("expected a use");
+        };
+        assert_eq!(use_.module().unwrap().text(), "math");
+        assert_eq!(use_.name().unwrap().text(), "square");
+
+        let Some(Stmt::Expr(Expr::VariableRef(variable_ref))) = stmts.next() else {
+            panic!("expected a variable reference");
+        };
+        assert_eq!(variable_ref.qualifier().unwrap().text(), "math");
+        assert_eq!(variable_ref.name().unwrap().text(), "pi");
+    }
+}
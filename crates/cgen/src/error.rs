@@ -0,0 +1,40 @@
+use std::fmt::Display;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CompileError {
+    MissingExpr,
+    LiteralOutOfRange(u64),
+    UnitValue,
+    UndefinedVariable(String),
+    UndefinedFunction(String),
+    ArityMismatch {
+        name: String,
+        expected: usize,
+        found: usize,
+    },
+}
+
+impl Display for CompileError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::MissingExpr => f.write_str("cannot compile a missing expression"),
+            Self::LiteralOutOfRange(n) => write!(f, "integer literal {} is out of range", n),
+            Self::UnitValue => f.write_str(
+                "the C backend only supports blocks and functions that produce integers",
+            ),
+            Self::UndefinedVariable(name) => write!(f, "undefined variable '{}'", name),
+            Self::UndefinedFunction(name) => write!(f, "undefined function '{}'", name),
+            Self::ArityMismatch {
+                name,
+                expected,
+                found,
+            } => write!(
+                f,
+                "function '{}' takes {} argument(s), but {} were given",
+                name, expected, found,
+            ),
+        }
+    }
+}
+
+impl std::error::Error for CompileError {}
@@ -0,0 +1,535 @@
+//! Compiles programs to portable, dependency-free C99.
+//!
+//! The generated program prints the value of the last statement like `felix-flow FILE` does.
+//! Arithmetic is checked, and runtime errors print the interpreter's message to stderr and exit
+//! with status 1. Every value is an `int64_t`, so blocks and function bodies must end in an
+//! expression.
+
+mod error;
+
+pub use error::CompileError;
+
+use hir::{BinaryOp, Expr, FnDef, ModuleScope, Program, Stmt, StmtKind, UnaryOp};
+use std::fmt::Write;
+
+const PRELUDE: &str = r#"#include <inttypes.h>
+#include <stdint.h>
+#include <stdio.h>
+#include <stdlib.h>
+
+static void ff_fail(const char *message) {
+    fprintf(stderr, "%s\n", message);
+    exit(1);
+}
+
+static int64_t ff_add(int64_t a, int64_t b) {
+    if ((b > 0 && a > INT64_MAX - b) || (b < 0 && a < INT64_MIN - b)) {
+        ff_fail("integer overflow");
+    }
+    return a + b;
+}
+
+static int64_t ff_sub(int64_t a, int64_t b) {
+    if ((b < 0 && a > INT64_MAX + b) || (b > 0 && a < INT64_MIN + b)) {
+        ff_fail("integer overflow");
+    }
+    return a - b;
+}
+
+static int64_t ff_mul(int64_t a, int64_t b) {
+    if (a > 0 ? (b > 0 ? a > INT64_MAX / b : b < INT64_MIN / a)
+              : (b > 0 ? a < INT64_MIN / b : a != 0 && b < INT64_MAX / a)) {
+        ff_fail("integer overflow");
+    }
+    return a * b;
+}
+
+static int64_t ff_div(int64_t a, int64_t b) {
+    if (b == 0) {
+        ff_fail("division by zero");
+    }
+    if (a == INT64_MIN && b == -1) {
+        ff_fail("integer overflow");
+    }
+    return a / b;
+}
+
+static int64_t ff_neg(int64_t a) {
+    if (a == INT64_MIN) {
+        ff_fail("integer overflow");
+    }
+    return -a;
+}
+"#;
+
+pub fn compile(program: &Program) -> Result<String, CompileError> {
+    let scope = ModuleScope::new(program);
+
+    let mut s = String::from(PRELUDE);
+
+    if !scope.globals.is_empty() {
+        s.push('\n');
+    }
+    for global in &scope.globals {
+        writeln!(s, "static int64_t g_{};", global).unwrap();
+        writeln!(s, "static int g_{}_defined;", global).unwrap();
+    }
+
+    if !scope.fns.is_empty() {
+        s.push('\n');
+    }
+    for fn_def in &scope.fns {
+        writeln!(s, "{};", signature(fn_def)).unwrap();
+    }
+
+    for fn_def in &scope.fns {
+        let mut codegen = FnCodegen::new(&scope);
+
+        for (idx, param) in fn_def.params.iter().enumerate() {
+            codegen.locals.push((param.clone(), format!("p{}", idx)));
+        }
+
+        let value = codegen.compile_expr(&fn_def.body)?;
+        codegen.emit(format!("return {};", value));
+
+        write!(s, "\n{} {{\n{}}}\n", signature(fn_def), codegen.body).unwrap();
+    }
+
+    let mut codegen = FnCodegen::new(&scope);
+
+    for (idx, stmt) in program.stmts.iter().enumerate() {
+        match &stmt.kind {
+            StmtKind::VariableDef { name, value } => {
+                let value = codegen.compile_expr(value)?;
+                codegen.emit(format!("g_{} = {};", name, value));
+                codegen.emit(format!("g_{}_defined = 1;", name));
+            }
+            StmtKind::Expr(expr) => {
+                let value = codegen.compile_expr(expr)?;
+
+                if idx == program.stmts.len() - 1 {
+                    codegen.emit(format!("printf(\"%\" PRId64 \"\\n\", {});", value));
+                } else {
+                    codegen.emit(format!("(void){};", value));
+                }
+            }
+        }
+    }
+
+    codegen.emit("return 0;");
+    write!(s, "\nint main(void) {{\n{}}}\n", codegen.body).unwrap();
+
+    Ok(s)
+}
+
+fn signature(fn_def: &FnDef) -> String {
+    let params = if fn_def.params.is_empty() {
+        "void".to_string()
+    } else {
+        (0..fn_def.params.len())
+            .map(|idx| format!("int64_t p{}", idx))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+
+    format!("static int64_t fn_{}({})", fn_def.name, params)
+}
+
+/// Generates the body of one C function. Every intermediate value is stored in its own
+/// temporary, so that operands are evaluated left to right as in the interpreter rather than in
+/// C's unspecified argument order.
+struct FnCodegen<'a> {
+    scope: &'a ModuleScope<'a>,
+    body: String,
+    depth: usize,
+    next_temp: usize,
+    locals: Vec<(String, String)>,
+}
+
+impl<'a> FnCodegen<'a> {
+    fn new(scope: &'a ModuleScope<'a>) -> Self {
+        Self {
+            scope,
+            body: String::new(),
+            depth: 1,
+            next_temp: 0,
+            locals: Vec::new(),
+        }
+    }
+
+    /// Compiles `expr`, returning a C expression for its value that has no side effects.
+    fn compile_expr(&mut self, expr: &Expr) -> Result<String, CompileError> {
+        let value = match expr {
+            Expr::Missing => return Err(CompileError::MissingExpr),
+            Expr::Binary { op, lhs, rhs } => {
+                let lhs = self.compile_expr(lhs)?;
+                let rhs = self.compile_expr(rhs)?;
+
+                let helper = match op {
+                    BinaryOp::Add => "ff_add",
+                    BinaryOp::Sub => "ff_sub",
+                    BinaryOp::Mul => "ff_mul",
+                    BinaryOp::Div => "ff_div",
+                };
+
+                self.temp(format!("{}({}, {})", helper, lhs, rhs))
+            }
+            Expr::Literal { n } => {
+                let n = i64::try_from(*n).map_err(|_| CompileError::LiteralOutOfRange(*n))?;
+                format!("INT64_C({})", n)
+            }
+            Expr::Unary { op, expr } => {
+                let value = self.compile_expr(expr)?;
+
+                match op {
+                    UnaryOp::Neg => self.temp(format!("ff_neg({})", value)),
+                }
+            }
+            Expr::VariableRef { var } => {
+                if let Some((_, local)) = self.locals.iter().rev().find(|(name, _)| name == var) {
+                    local.clone()
+                } else if self.scope.is_global(var) {
+                    self.emit(format!("if (!g_{}_defined) {{", var));
+                    self.depth += 1;
+                    self.emit(format!("ff_fail(\"undefined variable '{}'\");", var));
+                    self.depth -= 1;
+                    self.emit("}");
+
+                    self.temp(format!("g_{}", var))
+                } else {
+                    return Err(CompileError::UndefinedVariable(var.clone()));
+                }
+            }
+            Expr::Block { stmts } => self.compile_block(stmts)?,
+            Expr::Call { callee, args } => {
+                let expected = *self
+                    .scope
+                    .arities
+                    .get(callee.as_str())
+                    .ok_or_else(|| CompileError::UndefinedFunction(callee.clone()))?;
+
+                if expected != args.len() {
+                    return Err(CompileError::ArityMismatch {
+                        name: callee.clone(),
+                        expected,
+                        found: args.len(),
+                    });
+                }
+
+                let args = args
+                    .iter()
+                    .map(|arg| self.compile_expr(arg))
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                self.temp(format!("fn_{}({})", callee, args.join(", ")))
+            }
+        };
+
+        Ok(value)
+    }
+
+    fn compile_block(&mut self, stmts: &[Stmt]) -> Result<String, CompileError> {
+        match stmts.last() {
+            Some(Stmt {
+                kind: StmtKind::Expr(_),
+                ..
+            }) => {}
+            _ => return Err(CompileError::UnitValue),
+        }
+
+        let result = self.fresh_temp();
+        self.emit(format!("int64_t {};", result));
+        self.emit("{");
+        self.depth += 1;
+
+        let scope_start = self.locals.len();
+
+        for (idx, stmt) in stmts.iter().enumerate() {
+            match &stmt.kind {
+                StmtKind::VariableDef { name, value } => {
+                    let value = self.compile_expr(value)?;
+                    let local = self.temp(value);
+                    self.locals.push((name.clone(), local));
+                }
+                StmtKind::Expr(expr) => {
+                    let value = self.compile_expr(expr)?;
+
+                    if idx == stmts.len() - 1 {
+                        self.emit(format!("{} = {};", result, value));
+                    } else {
+                        self.emit(format!("(void){};", value));
+                    }
+                }
+            }
+        }
+
+        self.locals.truncate(scope_start);
+
+        self.depth -= 1;
+        self.emit("}");
+
+        Ok(result)
+    }
+
+    fn temp(&mut self, value: String) -> String {
+        let temp = self.fresh_temp();
+        self.emit(format!("int64_t {} = {};", temp, value));
+        temp
+    }
+
+    fn fresh_temp(&mut self) -> String {
+        self.next_temp += 1;
+        format!("t{}", self.next_temp - 1)
+    }
+
+    fn emit(&mut self, line: impl AsRef<str>) {
+        writeln!(
+            self.body,
+            "{:indent$}{}",
+            "",
+            line.as_ref(),
+            indent = self.depth * 4,
+        )
+        .unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ast::AstNode;
+    use eval::{Interpreter, Value};
+    use expect_test::{expect, Expect};
+    use std::path::PathBuf;
+    use std::process::Command;
+    use std::{env, fs, io, process};
+
+    const PROGRAMS: &[&str] = &[
+        "",
+        "42",
+        "1 + 2 * 3 - -4 / 2",
+        "let a = 1",
+        "let a = 10\nlet b = a * 2\nb - a",
+        "let a = 1\nlet a = a + 1\na",
+        "{ let x = 1 x }",
+        "let x = 1\n{ let x = x + 1 let x = x * 3 x } + x",
+        "{ let a = 1 { let b = a + 1 { let c = b * 10 c + a } } }",
+        "fn add(x, y) { x + y }\nadd(1, 2) * 2",
+        "double(21)\nfn double(x) { x * 2 }",
+        "fn f() { 7 }\nfn g(x) { f() * x }\ng(g(2))",
+        "fn f(a, b, c) { let d = a * b { let e = d - c e * e } }\nf(3, 4, 2) + f(1, 1, 1)",
+        "fn f(x, x) { x }\nf(1, 2)",
+        "fn f() { 1 }\nfn f() { 2 }\nf()",
+        "fn main() { 5 }\nmain() + 1",
+        "fn f() { g }\nf()\nlet g = 1\ng",
+        "fn f() { g }\nlet g = 1\nf()",
+        "1 / (2 - 2)",
+        "(1 / 0) + (9223372036854775807 + 1)",
+        "9223372036854775807 + 1",
+        "-9223372036854775807 - 2",
+        "4611686018427387904 * 2",
+        "-1 * (-9223372036854775807 - 1)",
+        "(-9223372036854775807 - 1) / -1",
+        "-(-9223372036854775807 - 1)",
+        "-9223372036854775807 - 1",
+        "3037000499 * 3037000499",
+        "-3037000500 * 3037000500",
+    ];
+
+    fn lower(input: &str) -> Program {
+        let parse = parser::parse(input);
+        assert!(parse.errors().is_empty(), "{}", parse.debug_tree());
+
+        hir::lower(ast::Root::cast(parse.syntax()).unwrap())
+    }
+
+    fn check(input: &str, expected: Expect) {
+        expected.assert_eq(&compile(&lower(input)).unwrap());
+    }
+
+    /// Builds `source` with the system C compiler, returning the path of the executable, or
+    /// `None` if there is no `cc` to build with.
+    fn build(source: &str, name: &str) -> Option<PathBuf> {
+        let dir = env::temp_dir().join(format!("felix-flow-cgen-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let source_path = dir.join(format!("{}.c", name));
+        let exe_path = dir.join(name);
+        fs::write(&source_path, source).unwrap();
+
+        let output = match Command::new("cc")
+            .args(["-std=c99", "-pedantic", "-Wall", "-O2", "-o"])
+            .arg(&exe_path)
+            .arg(&source_path)
+            .output()
+        {
+            Ok(output) => output,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return None,
+            Err(e) => panic!("failed to run cc: {}", e),
+        };
+
+        assert!(
+            output.status.success(),
+            "cc failed:\n{}\n{}",
+            String::from_utf8_lossy(&output.stderr),
+            source,
+        );
+
+        Some(exe_path)
+    }
+
+    #[test]
+    fn compiled_programs_agree_with_interpreter() {
+        for (idx, input) in PROGRAMS.iter().enumerate() {
+            let program = lower(input);
+            let source = compile(&program).unwrap();
+
+            let Some(exe) = build(&source, &format!("program{}", idx)) else {
+                eprintln!("skipping: no `cc` found");
+                return;
+            };
+            let output = Command::new(exe).output().unwrap();
+            let stdout = String::from_utf8(output.stdout).unwrap();
+            let stderr = String::from_utf8(output.stderr).unwrap();
+
+            match Interpreter::new().run(&program) {
+                Ok(value) => {
+                    let expected = match value {
+                        Value::Unit => String::new(),
+                        value => format!("{}\n", value),
+                    };
+
+                    assert!(output.status.success(), "{:?} failed: {}", input, stderr);
+                    assert_eq!(stdout, expected, "wrong output for {:?}", input);
+                }
+                Err(e) => {
+                    assert_eq!(output.status.code(), Some(1), "{:?} did not fail", input);
+                    assert_eq!(stderr, format!("{}\n", e), "wrong error for {:?}", input);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn emit_program() {
+        check(
+            "let a = 2\nfn f(x) { let y = x * a y }\nf(3) + { let b = 1 b }",
+            expect![[r##"
+                #include <inttypes.h>
+                #include <stdint.h>
+                #include <stdio.h>
+                #include <stdlib.h>
+
+                static void ff_fail(const char *message) {
+                    fprintf(stderr, "%s\n", message);
+                    exit(1);
+                }
+
+                static int64_t ff_add(int64_t a, int64_t b) {
+                    if ((b > 0 && a > INT64_MAX - b) || (b < 0 && a < INT64_MIN - b)) {
+                        ff_fail("integer overflow");
+                    }
+                    return a + b;
+                }
+
+                static int64_t ff_sub(int64_t a, int64_t b) {
+                    if ((b < 0 && a > INT64_MAX + b) || (b > 0 && a < INT64_MIN + b)) {
+                        ff_fail("integer overflow");
+                    }
+                    return a - b;
+                }
+
+                static int64_t ff_mul(int64_t a, int64_t b) {
+                    if (a > 0 ? (b > 0 ? a > INT64_MAX / b : b < INT64_MIN / a)
+                              : (b > 0 ? a < INT64_MIN / b : a != 0 && b < INT64_MAX / a)) {
+                        ff_fail("integer overflow");
+                    }
+                    return a * b;
+                }
+
+                static int64_t ff_div(int64_t a, int64_t b) {
+                    if (b == 0) {
+                        ff_fail("division by zero");
+                    }
+                    if (a == INT64_MIN && b == -1) {
+                        ff_fail("integer overflow");
+                    }
+                    return a / b;
+                }
+
+                static int64_t ff_neg(int64_t a) {
+                    if (a == INT64_MIN) {
+                        ff_fail("integer overflow");
+                    }
+                    return -a;
+                }
+
+                static int64_t g_a;
+                static int g_a_defined;
+
+                static int64_t fn_f(int64_t p0);
+
+                static int64_t fn_f(int64_t p0) {
+                    int64_t t0;
+                    {
+                        if (!g_a_defined) {
+                            ff_fail("undefined variable 'a'");
+                        }
+                        int64_t t1 = g_a;
+                        int64_t t2 = ff_mul(p0, t1);
+                        int64_t t3 = t2;
+                        t0 = t3;
+                    }
+                    return t0;
+                }
+
+                int main(void) {
+                    g_a = INT64_C(2);
+                    g_a_defined = 1;
+                    int64_t t0 = fn_f(INT64_C(3));
+                    int64_t t1;
+                    {
+                        int64_t t2 = INT64_C(1);
+                        t1 = t2;
+                    }
+                    int64_t t3 = ff_add(t0, t1);
+                    printf("%" PRId64 "\n", t3);
+                    return 0;
+                }
+            "##]],
+        );
+    }
+
+    #[test]
+    fn reject_unit_values() {
+        assert_eq!(compile(&lower("{ }")), Err(CompileError::UnitValue));
+        assert_eq!(
+            compile(&lower("fn f() { let a = 1 }\n1")),
+            Err(CompileError::UnitValue),
+        );
+    }
+
+    #[test]
+    fn reject_undefined_names() {
+        assert_eq!(
+            compile(&lower("abc")),
+            Err(CompileError::UndefinedVariable("abc".to_string())),
+        );
+        assert_eq!(
+            compile(&lower("foo()")),
+            Err(CompileError::UndefinedFunction("foo".to_string())),
+        );
+    }
+
+    #[test]
+    fn reject_arity_mismatch() {
+        assert_eq!(
+            compile(&lower("fn f(x) { x }\nf()")),
+            Err(CompileError::ArityMismatch {
+                name: "f".to_string(),
+                expected: 1,
+                found: 0,
+            }),
+        );
+    }
+}
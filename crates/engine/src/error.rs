@@ -0,0 +1,29 @@
+use eval::EvalError;
+use parser::ParseError;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EngineError {
+    Parse(Vec<ParseError>),
+    Eval(EvalError),
+}
+
+impl fmt::Display for EngineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Parse(errors) => {
+                let errors: Vec<_> = errors.iter().map(ToString::to_string).collect();
+                f.write_str(&errors.join("\n"))
+            }
+            Self::Eval(e) => e.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for EngineError {}
+
+impl From<EvalError> for EngineError {
+    fn from(e: EvalError) -> Self {
+        Self::Eval(e)
+    }
+}
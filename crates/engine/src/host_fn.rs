@@ -0,0 +1,42 @@
+use eval::{EvalError, FromValue, IntoValue, Value};
+
+/// Rust functions that can be called from felix-flow, taking arguments that implement
+/// [`FromValue`] and returning something that implements [`IntoValue`]. `Args` is the tuple of
+/// the argument types, which tells apart the implementations for each number of arguments.
+pub trait HostFn<Args> {
+    fn arity(&self) -> usize;
+
+    /// Calls the function with `args`, which there are [`HostFn::arity`] of.
+    fn call(&self, args: &[Value]) -> Result<Value, EvalError>;
+}
+
+macro_rules! host_fn {
+    ($($arg:ident),*) => {
+        impl<F, R, $($arg),*> HostFn<($($arg,)*)> for F
+        where
+            F: Fn($($arg),*) -> R,
+            R: IntoValue,
+            $($arg: FromValue,)*
+        {
+            fn arity(&self) -> usize {
+                0 $(+ { stringify!($arg); 1 })*
+            }
+
+            #[allow(non_snake_case)]
+            fn call(&self, args: &[Value]) -> Result<Value, EvalError> {
+                let &[$($arg),*] = args else {
+                    unreachable!("called with the wrong number of arguments");
+                };
+
+                self($(<$arg>::from_value($arg)?),*).into_value()
+            }
+        }
+    };
+}
+
+host_fn!();
+host_fn!(A);
+host_fn!(A, B);
+host_fn!(A, B, C);
+host_fn!(A, B, C, D);
+host_fn!(A, B, C, D, E);
@@ -0,0 +1,238 @@
+mod error;
+mod host_fn;
+
+pub use error::EngineError;
+pub use eval::{EvalError, FromValue, IntoValue, Limit, Limits, Value, DEFAULT_MAX_DEPTH};
+pub use host_fn::HostFn;
+
+use ast::AstNode;
+use eval::Interpreter;
+
+/// Runs felix-flow code from inside a Rust program, with functions of the host's own available
+/// to it.
+///
+/// Globals and functions defined by the code passed to [`Engine::eval`] are remembered, so that
+/// later code can use them. Imports aren't loaded.
+///
+/// Calls can nest [`DEFAULT_MAX_DEPTH`] deep, but nothing else is limited unless [`Limits`] are
+/// set, which code from untrusted sources should be run with.
+#[derive(Debug, Default)]
+pub struct Engine {
+    interpreter: Interpreter,
+}
+
+impl Engine {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Makes `f` callable as `name`, unless the code defines a function with the same name.
+    pub fn register_fn<Args>(&mut self, name: &str, f: impl HostFn<Args> + 'static) -> &mut Self {
+        let arity = f.arity();
+        self.interpreter
+            .register_native(name, arity, move |args| f.call(args));
+
+        self
+    }
+
+    /// Bounds each later call to [`Engine::eval`] by `limits`.
+    pub fn set_limits(&mut self, limits: Limits) -> &mut Self {
+        self.interpreter.set_limits(limits);
+
+        self
+    }
+
+    /// Runs `source`, converting the value of its last statement to `T`.
+    pub fn eval<T: FromValue>(&mut self, source: &str) -> Result<T, EngineError> {
+        let parse = parser::parse(source);
+
+        if !parse.errors().is_empty() {
+            return Err(EngineError::Parse(parse.errors().to_vec()));
+        }
+
+        let program = hir::lower(ast::Root::cast(parse.syntax()).unwrap());
+        let value = self.interpreter.run(&program)?;
+
+        Ok(T::from_value(value)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use expect_test::{expect, Expect};
+
+    fn check_error<T: FromValue + std::fmt::Debug>(
+        engine: &mut Engine,
+        source: &str,
+        expected: Expect,
+    ) {
+        expected.assert_eq(&engine.eval::<T>(source).unwrap_err().to_string());
+    }
+
+    #[test]
+    fn call_registered_functions() {
+        let result = Engine::new()
+            .register_fn("isqrt", |x: i64| (x as f64).sqrt() as i64)
+            .eval::<i64>("let a = 2\na * isqrt(9)");
+
+        assert_eq!(result, Ok(6));
+    }
+
+    #[test]
+    fn register_functions_of_any_arity() {
+        let mut engine = Engine::new();
+        engine
+            .register_fn("answer", || 42)
+            .register_fn("clamp", |x: i64, lo: i64, hi: i64| x.clamp(lo, hi))
+            .register_fn("ignore", |_: i64| ());
+
+        assert_eq!(engine.eval("clamp(answer(), 0, 10)"), Ok(10));
+        assert_eq!(engine.eval("ignore(1)"), Ok(()));
+        assert_eq!(engine.eval("answer()"), Ok(Value::Int(42)));
+        check_error::<i64>(
+            &mut engine,
+            "clamp(1, 2)",
+            expect!["function 'clamp' takes 3 argument(s), but 2 were given"],
+        );
+    }
+
+    #[test]
+    fn remember_definitions_across_evals() {
+        let mut engine = Engine::new();
+        engine.register_fn("double", |x: i64| x * 2);
+
+        assert_eq!(engine.eval("let a = 5\nfn double(x) { x + x + x }"), Ok(()));
+        assert_eq!(engine.eval("double(a)"), Ok(15));
+    }
+
+    #[test]
+    fn convert_values_checking_their_range() {
+        let mut engine = Engine::new();
+        engine.register_fn("byte", |x: u8| x);
+
+        assert_eq!(engine.eval::<u16>("byte(255)"), Ok(255));
+        check_error::<u8>(&mut engine, "byte(256)", expect!["integer overflow"]);
+        check_error::<u8>(&mut engine, "-1", expect!["integer overflow"]);
+        check_error::<i64>(
+            &mut engine,
+            "{ }",
+            expect!["expected an integer, but found ()"],
+        );
+        check_error::<()>(&mut engine, "1", expect!["expected (), but found 1"]);
+    }
+
+    #[test]
+    fn convert_floats_only_when_exact() {
+        let mut engine = Engine::new();
+        engine
+            .register_fn("sqrt", |x: f64| x.sqrt())
+            .register_fn("half", |x: f32| x / 2.0);
+
+        assert_eq!(engine.eval("sqrt(16)"), Ok(4));
+        assert_eq!(engine.eval("half(-8)"), Ok(-4));
+        assert_eq!(
+            engine.eval::<f64>("-9223372036854775807 - 1"),
+            Ok(-9.223372036854776e18)
+        );
+        check_error::<i64>(
+            &mut engine,
+            "sqrt(2)",
+            expect!["cannot convert 1.4142135623730951 exactly"],
+        );
+        check_error::<i64>(
+            &mut engine,
+            "sqrt(-1)",
+            expect!["cannot convert NaN exactly"],
+        );
+        check_error::<i64>(
+            &mut engine,
+            "half(16777217)",
+            expect!["cannot convert 16777217 exactly"],
+        );
+        check_error::<f64>(
+            &mut engine,
+            "9223372036854775807",
+            expect!["cannot convert 9223372036854775807 exactly"],
+        );
+        check_error::<f64>(
+            &mut engine,
+            "{ }",
+            expect!["expected an integer, but found ()"],
+        );
+    }
+
+    #[test]
+    fn report_errors_from_functions() {
+        let mut engine = Engine::new();
+        engine.register_fn("checkedSqrt", |x: i64| {
+            if x < 0 {
+                Err(format!("cannot take the square root of {}", x))
+            } else {
+                Ok((x as f64).sqrt() as i64)
+            }
+        });
+
+        assert_eq!(engine.eval("checkedSqrt(16)"), Ok(4));
+        check_error::<i64>(
+            &mut engine,
+            "checkedSqrt(-4)",
+            expect!["cannot take the square root of -4"],
+        );
+    }
+
+    #[test]
+    fn stop_untrusted_code_at_its_limits() {
+        let mut engine = Engine::new();
+        engine.set_limits(Limits {
+            fuel: Some(1000),
+            max_depth: Some(50),
+            ..Limits::default()
+        });
+
+        check_error::<i64>(
+            &mut engine,
+            "fn f(n) { f(n + 1) }\nf(0)",
+            expect!["recursion depth limit exceeded"],
+        );
+        check_error::<i64>(
+            &mut engine,
+            "fn a() { 1 + 1 }\nfn b() { a() + a() + a() + a() }\nfn c() { b() + b() + b() + b() }\nfn d() { c() + c() + c() + c() }\nfn e() { d() + d() + d() + d() }\ne()",
+            expect!["fuel limit exceeded"],
+        );
+        assert_eq!(engine.eval("fn f(n) { n * 2 }\nf(21)"), Ok(42));
+    }
+
+    #[test]
+    fn stop_recursion_through_nested_expressions() {
+        let source = format!("fn f(x) {{ {}f(x) }}\nf(1)", "-".repeat(250));
+        let depth_exceeded = Err(EngineError::Eval(EvalError::LimitExceeded(Limit::Depth)));
+
+        assert_eq!(Engine::new().eval::<i64>(&source), depth_exceeded);
+
+        let mut engine = Engine::new();
+        engine.set_limits(Limits {
+            max_depth: None,
+            ..Limits::default()
+        });
+        assert_eq!(engine.eval::<i64>(&source), depth_exceeded);
+
+        engine.set_limits(Limits {
+            max_depth: Some(1000),
+            ..Limits::default()
+        });
+        assert_eq!(engine.eval::<i64>(&source), depth_exceeded);
+    }
+
+    #[test]
+    fn report_parse_errors() {
+        check_error::<i64>(
+            &mut Engine::new(),
+            "let = 1",
+            expect![[r#"
+            error at 4..5: expected identifier, but found '='
+            error at 6..7: expected '=', but found number
+            error at 6..7: expected number, identifier, '-', '(' or '{'"#]],
+        );
+    }
+}
@@ -0,0 +1,51 @@
+use crate::{Limit, Value};
+use std::fmt::Display;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EvalError {
+    UndefinedVariable(String),
+    UndefinedFunction(String),
+    ArityMismatch {
+        name: String,
+        expected: usize,
+        found: usize,
+    },
+    ExpectedInt(Value),
+    ExpectedUnit(Value),
+    DivisionByZero,
+    Overflow,
+    /// A number that can't be converted between an integer and a float without changing it.
+    Inexact(String),
+    MissingExpr,
+    /// An error reported by a native function.
+    Native(String),
+    LimitExceeded(Limit),
+}
+
+impl Display for EvalError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UndefinedVariable(name) => write!(f, "undefined variable '{}'", name),
+            Self::UndefinedFunction(name) => write!(f, "undefined function '{}'", name),
+            Self::ArityMismatch {
+                name,
+                expected,
+                found,
+            } => write!(
+                f,
+                "function '{}' takes {} argument(s), but {} were given",
+                name, expected, found,
+            ),
+            Self::ExpectedInt(found) => write!(f, "expected an integer, but found {}", found),
+            Self::ExpectedUnit(found) => write!(f, "expected (), but found {}", found),
+            Self::DivisionByZero => f.write_str("division by zero"),
+            Self::Overflow => f.write_str("integer overflow"),
+            Self::Inexact(number) => write!(f, "cannot convert {} exactly", number),
+            Self::MissingExpr => f.write_str("cannot evaluate a missing expression"),
+            Self::Native(message) => f.write_str(message),
+            Self::LimitExceeded(limit) => write!(f, "{} limit exceeded", limit),
+        }
+    }
+}
+
+impl std::error::Error for EvalError {}
@@ -0,0 +1,500 @@
+mod error;
+mod limits;
+mod value;
+
+pub use error::EvalError;
+pub use limits::{Budget, Limit, Limits, DEFAULT_MAX_DEPTH, MAX_NESTING};
+pub use value::{FromValue, IntoValue, Value};
+
+use hir::{BinaryOp, Expr, FnDef, Program, Stmt, StmtKind, UnaryOp};
+use std::collections::HashMap;
+use std::fmt;
+use std::rc::Rc;
+
+/// A tree-walking interpreter over the HIR.
+///
+/// Top-level `let`s define globals, which persist across calls to [`Interpreter::run`] so that
+/// the REPL can build up state line by line. `let`s inside blocks are local to the block, and
+/// function bodies only see their own parameters and locals plus the globals.
+///
+/// Native functions registered by the host can be called like any other, but functions defined
+/// by the program take precedence over them.
+#[derive(Debug, Default)]
+pub struct Interpreter {
+    globals: HashMap<String, Value>,
+    fns: HashMap<String, Rc<FnDef>>,
+    natives: HashMap<String, Native>,
+    limits: Limits,
+    budget: Budget,
+}
+
+#[derive(Clone)]
+struct Native {
+    arity: usize,
+    f: Rc<NativeFn>,
+}
+
+impl fmt::Debug for Native {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Native")
+            .field("arity", &self.arity)
+            .finish_non_exhaustive()
+    }
+}
+
+type Locals = Vec<(String, Value)>;
+
+type NativeFn = dyn Fn(&[Value]) -> Result<Value, EvalError>;
+
+impl Interpreter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Makes `f` callable as `name` with `arity` arguments.
+    pub fn register_native(
+        &mut self,
+        name: impl Into<String>,
+        arity: usize,
+        f: impl Fn(&[Value]) -> Result<Value, EvalError> + 'static,
+    ) {
+        let native = Native {
+            arity,
+            f: Rc::new(f),
+        };
+        self.natives.insert(name.into(), native);
+    }
+
+    /// Bounds every later run by `limits`.
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.limits = limits;
+    }
+
+    /// Runs a program, returning the value of its last statement.
+    pub fn run(&mut self, program: &Program) -> Result<Value, EvalError> {
+        self.budget = Budget::new(self.limits);
+
+        for fn_def in &program.fns {
+            self.fns
+                .insert(fn_def.name.clone(), Rc::new(fn_def.clone()));
+        }
+
+        let mut value = Value::Unit;
+
+        for stmt in &program.stmts {
+            value = match &stmt.kind {
+                StmtKind::VariableDef { name, value } => {
+                    let value = self.eval_expr(value, &mut Locals::new())?;
+                    self.globals.insert(name.clone(), value);
+                    Value::Unit
+                }
+                StmtKind::Expr(expr) => self.eval_expr(expr, &mut Locals::new())?,
+            };
+        }
+
+        Ok(value)
+    }
+
+    fn eval_expr(&mut self, expr: &Expr, locals: &mut Locals) -> Result<Value, EvalError> {
+        self.budget.step()?;
+        self.budget.nest()?;
+
+        let value = match expr {
+            Expr::Missing => Err(EvalError::MissingExpr),
+            Expr::Binary { op, lhs, rhs } => self.eval_binary(*op, lhs, rhs, locals),
+            Expr::Literal { n } => i64::try_from(*n)
+                .map(Value::Int)
+                .map_err(|_| EvalError::Overflow),
+            Expr::Unary { op, expr } => {
+                let value = self.eval_int(expr, locals)?;
+
+                unary(*op, value).map(Value::Int)
+            }
+            Expr::VariableRef { var } => locals
+                .iter()
+                .rev()
+                .find(|(name, _)| name == var)
+                .map(|(_, value)| *value)
+                .or_else(|| self.globals.get(var).copied())
+                .ok_or_else(|| EvalError::UndefinedVariable(var.clone())),
+            Expr::Block { stmts } => self.eval_block(stmts, locals),
+            Expr::Call { callee, args } => {
+                let args = args
+                    .iter()
+                    .map(|arg| self.eval_expr(arg, locals))
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                self.call(callee, args)
+            }
+        };
+
+        self.budget.unnest();
+        value
+    }
+
+    fn eval_binary(
+        &mut self,
+        op: BinaryOp,
+        lhs: &Expr,
+        rhs: &Expr,
+        locals: &mut Locals,
+    ) -> Result<Value, EvalError> {
+        // Like the VM, evaluate both operands before checking either.
+        let lhs = self.eval_expr(lhs, locals)?;
+        let rhs = self.eval_expr(rhs, locals)?;
+
+        binary(op, int(lhs)?, int(rhs)?).map(Value::Int)
+    }
+
+    fn eval_int(&mut self, expr: &Expr, locals: &mut Locals) -> Result<i64, EvalError> {
+        int(self.eval_expr(expr, locals)?)
+    }
+
+    fn eval_block(&mut self, stmts: &[Stmt], locals: &mut Locals) -> Result<Value, EvalError> {
+        let scope_start = locals.len();
+        let mut value = Value::Unit;
+
+        for stmt in stmts {
+            value = match &stmt.kind {
+                StmtKind::VariableDef { name, value } => {
+                    let value = self.eval_expr(value, locals)?;
+                    self.budget.hold(self.budget.held() + 1)?;
+                    locals.push((name.clone(), value));
+                    Value::Unit
+                }
+                StmtKind::Expr(expr) => self.eval_expr(expr, locals)?,
+            };
+        }
+
+        self.budget
+            .hold(self.budget.held() - (locals.len() - scope_start))?;
+        locals.truncate(scope_start);
+
+        Ok(value)
+    }
+
+    fn call(&mut self, callee: &str, args: Vec<Value>) -> Result<Value, EvalError> {
+        let check_arity = |expected: usize| {
+            if expected == args.len() {
+                Ok(())
+            } else {
+                Err(EvalError::ArityMismatch {
+                    name: callee.to_string(),
+                    expected,
+                    found: args.len(),
+                })
+            }
+        };
+
+        if let Some(fn_def) = self.fns.get(callee).cloned() {
+            check_arity(fn_def.params.len())?;
+
+            self.budget.enter()?;
+            self.budget.hold(self.budget.held() + args.len())?;
+
+            let num_params = args.len();
+            let mut locals = fn_def.params.iter().cloned().zip(args).collect();
+            let value = self.eval_expr(&fn_def.body, &mut locals)?;
+
+            self.budget.hold(self.budget.held() - num_params)?;
+            self.budget.exit();
+
+            return Ok(value);
+        }
+
+        let native = self
+            .natives
+            .get(callee)
+            .cloned()
+            .ok_or_else(|| EvalError::UndefinedFunction(callee.to_string()))?;
+        check_arity(native.arity)?;
+
+        (native.f)(&args)
+    }
+}
+
+fn int(value: Value) -> Result<i64, EvalError> {
+    match value {
+        Value::Int(n) => Ok(n),
+        value => Err(EvalError::ExpectedInt(value)),
+    }
+}
+
+pub fn binary(op: BinaryOp, lhs: i64, rhs: i64) -> Result<i64, EvalError> {
+    let result = match op {
+        BinaryOp::Add => lhs.checked_add(rhs),
+        BinaryOp::Sub => lhs.checked_sub(rhs),
+        BinaryOp::Mul => lhs.checked_mul(rhs),
+        BinaryOp::Div if rhs == 0 => return Err(EvalError::DivisionByZero),
+        BinaryOp::Div => lhs.checked_div(rhs),
+    };
+
+    result.ok_or(EvalError::Overflow)
+}
+
+pub fn unary(op: UnaryOp, value: i64) -> Result<i64, EvalError> {
+    match op {
+        UnaryOp::Neg => value.checked_neg().ok_or(EvalError::Overflow),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ast::AstNode;
+
+    fn run(interpreter: &mut Interpreter, input: &str) -> Result<Value, EvalError> {
+        let parse = parser::parse(input);
+        assert!(parse.errors().is_empty(), "{}", parse.debug_tree());
+
+        let program = hir::lower(ast::Root::cast(parse.syntax()).unwrap());
+        interpreter.run(&program)
+    }
+
+    fn check(input: &str, expected: Result<Value, EvalError>) {
+        assert_eq!(run(&mut Interpreter::new(), input), expected);
+    }
+
+    #[test]
+    fn evaluate_arithmetic_with_precedence() {
+        check("1 + 2 * 3 - -4 / 2", Ok(Value::Int(9)));
+    }
+
+    #[test]
+    fn evaluate_empty_program() {
+        check("", Ok(Value::Unit));
+    }
+
+    #[test]
+    fn evaluate_variable_def_to_unit() {
+        check("let a = 1", Ok(Value::Unit));
+    }
+
+    #[test]
+    fn evaluate_global_reference() {
+        check("let a = 10\nlet b = a * 2\nb - a", Ok(Value::Int(10)));
+    }
+
+    #[test]
+    fn block_locals_do_not_escape() {
+        check(
+            "{ let x = 1 x }\nx",
+            Err(EvalError::UndefinedVariable("x".to_string())),
+        );
+    }
+
+    #[test]
+    fn block_local_shadows_global() {
+        check("let x = 1\n{ let x = x + 1 x } + x", Ok(Value::Int(3)));
+    }
+
+    #[test]
+    fn call_function() {
+        check("fn add(x, y) { x + y }\nadd(1, 2) * 2", Ok(Value::Int(6)));
+    }
+
+    #[test]
+    fn call_function_defined_later() {
+        check("double(21)\nfn double(x) { x * 2 }", Ok(Value::Int(42)));
+    }
+
+    #[test]
+    fn function_sees_globals_but_not_caller_locals() {
+        check("let g = 1\nfn f() { g }\nf()", Ok(Value::Int(1)));
+        check(
+            "fn f() { x }\n{ let x = 1 f() }",
+            Err(EvalError::UndefinedVariable("x".to_string())),
+        );
+    }
+
+    #[test]
+    fn call_with_wrong_number_of_arguments() {
+        check(
+            "fn f(x) { x }\nf(1, 2)",
+            Err(EvalError::ArityMismatch {
+                name: "f".to_string(),
+                expected: 1,
+                found: 2,
+            }),
+        );
+    }
+
+    #[test]
+    fn call_undefined_function() {
+        check("f()", Err(EvalError::UndefinedFunction("f".to_string())));
+    }
+
+    #[test]
+    fn division_by_zero() {
+        check("1 / (2 - 2)", Err(EvalError::DivisionByZero));
+    }
+
+    #[test]
+    fn arithmetic_overflow() {
+        check("9223372036854775807 + 1", Err(EvalError::Overflow));
+        check("(-9223372036854775807 - 1) / -1", Err(EvalError::Overflow));
+    }
+
+    #[test]
+    fn unit_in_arithmetic() {
+        check("{ } + 1", Err(EvalError::ExpectedInt(Value::Unit)));
+    }
+
+    #[test]
+    fn call_native_function() {
+        let mut interpreter = Interpreter::new();
+        interpreter.register_native("max", 2, |args| {
+            let (a, b) = (i64::from_value(args[0])?, i64::from_value(args[1])?);
+            Ok(Value::Int(a.max(b)))
+        });
+
+        assert_eq!(run(&mut interpreter, "max(3, 4) + 1"), Ok(Value::Int(5)));
+        assert_eq!(
+            run(&mut interpreter, "max(1)"),
+            Err(EvalError::ArityMismatch {
+                name: "max".to_string(),
+                expected: 2,
+                found: 1,
+            }),
+        );
+        assert_eq!(
+            run(&mut interpreter, "fn max(a, b) { a }\nmax(3, 4)"),
+            Ok(Value::Int(3)),
+        );
+    }
+
+    /// A program whose functions each call the previous one twice, so that calling the last
+    /// takes about `2^n` steps.
+    fn doubling_calls(n: usize) -> String {
+        let mut input = "fn f0() { 1 }\n".to_string();
+        for i in 1..=n {
+            input += &format!("fn f{}() {{ f{}() + f{}() }}\n", i, i - 1, i - 1);
+        }
+        input + &format!("f{}()", n)
+    }
+
+    fn run_limited(limits: Limits, input: &str) -> Result<Value, EvalError> {
+        let mut interpreter = Interpreter::new();
+        interpreter.set_limits(limits);
+        run(&mut interpreter, input)
+    }
+
+    #[test]
+    fn stop_infinite_recursion() {
+        let limits = Limits {
+            max_depth: Some(100),
+            ..Limits::default()
+        };
+
+        assert_eq!(
+            run_limited(limits, "fn f(n) { f(n + 1) }\nf(0)"),
+            Err(EvalError::LimitExceeded(Limit::Depth)),
+        );
+        assert_eq!(
+            run_limited(limits, "fn f(n) { n }\nfn g(n) { f(n) + f(n) }\ng(2)"),
+            Ok(Value::Int(4)),
+        );
+    }
+
+    #[test]
+    fn stop_infinite_recursion_by_default() {
+        check(
+            "fn f(n) { 1 + (2 * -{ let a = f(n + 1) a }) }\nf(0)",
+            Err(EvalError::LimitExceeded(Limit::Depth)),
+        );
+    }
+
+    #[test]
+    fn stop_recursion_through_deeply_nested_expressions() {
+        let unlimited = Limits {
+            max_depth: None,
+            ..Limits::default()
+        };
+
+        for (open, close) in [("-", ""), ("g(", ")"), ("{ ", " }"), ("1 + (", ")")] {
+            let input = format!(
+                "fn g(x) {{ x }}\nfn f(x) {{ {}f(x){} }}\nf(1)",
+                open.repeat(120),
+                close.repeat(120),
+            );
+
+            check(&input, Err(EvalError::LimitExceeded(Limit::Depth)));
+            assert_eq!(
+                run_limited(unlimited, &input),
+                Err(EvalError::LimitExceeded(Limit::Depth)),
+            );
+        }
+        check(
+            &format!(
+                "fn f(x) {{ {}x }}\nf({}1)",
+                "-".repeat(250),
+                "-".repeat(250)
+            ),
+            Ok(Value::Int(1)),
+        );
+    }
+
+    #[test]
+    fn stop_runaway_computations() {
+        let fuel = Limits {
+            fuel: Some(10_000),
+            ..Limits::default()
+        };
+        assert_eq!(
+            run_limited(fuel, &doubling_calls(40)),
+            Err(EvalError::LimitExceeded(Limit::Fuel)),
+        );
+        assert_eq!(run_limited(fuel, &doubling_calls(5)), Ok(Value::Int(32)));
+
+        let time = Limits {
+            timeout: Some(std::time::Duration::from_millis(10)),
+            ..Limits::default()
+        };
+        assert_eq!(
+            run_limited(time, &doubling_calls(60)),
+            Err(EvalError::LimitExceeded(Limit::Time)),
+        );
+    }
+
+    #[test]
+    fn limit_memory_held_in_locals() {
+        let limits = Limits {
+            max_memory: Some(1024),
+            ..Limits::default()
+        };
+
+        assert_eq!(
+            run_limited(limits, "fn f(n) { let a = n let b = n f(a + b) }\nf(1)"),
+            Err(EvalError::LimitExceeded(Limit::Memory)),
+        );
+        assert_eq!(
+            run_limited(limits, "{ let a = 1 let b = 2 { let c = a + b c } }"),
+            Ok(Value::Int(3)),
+        );
+    }
+
+    #[test]
+    fn limits_apply_to_each_run() {
+        let mut interpreter = Interpreter::new();
+        interpreter.set_limits(Limits {
+            fuel: Some(100),
+            ..Limits::default()
+        });
+
+        for _ in 0..3 {
+            assert_eq!(run(&mut interpreter, &doubling_calls(3)), Ok(Value::Int(8)));
+        }
+    }
+
+    #[test]
+    fn globals_persist_across_runs() {
+        let mut interpreter = Interpreter::new();
+
+        assert_eq!(run(&mut interpreter, "let a = 5"), Ok(Value::Unit));
+        assert_eq!(
+            run(&mut interpreter, "fn inc(x) { x + 1 }"),
+            Ok(Value::Unit)
+        );
+        assert_eq!(run(&mut interpreter, "inc(a)"), Ok(Value::Int(6)));
+    }
+}
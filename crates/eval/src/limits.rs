@@ -0,0 +1,149 @@
+use crate::{EvalError, Value};
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// Bounds on the work a run can do, so that untrusted code can't hang or exhaust its host. Only
+/// the depth of calls is limited by default, to [`DEFAULT_MAX_DEPTH`].
+///
+/// The language has no strings or collections, so the memory a run uses is that of the values
+/// held in local variables and, in the VM, on the operand stack.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    /// How many steps a run can take: an expression evaluated by the interpreter, or an
+    /// instruction executed by the VM.
+    pub fuel: Option<u64>,
+    /// How deeply calls can nest. The interpreter also stops at this limit when calls and the
+    /// expressions within them together nest deeper than [`MAX_NESTING`], whatever it's set to.
+    pub max_depth: Option<usize>,
+    /// How many bytes of values a run can hold at once.
+    pub max_memory: Option<usize>,
+    /// How long a run can take. The clock is checked every few steps, so a run can go on for
+    /// slightly longer.
+    pub timeout: Option<Duration>,
+}
+
+/// How deeply calls can nest unless set otherwise, so that runaway recursion stops quickly in
+/// either backend. The language can't stop recursing, so a run that finishes never nests calls
+/// deeper than it has functions.
+pub const DEFAULT_MAX_DEPTH: usize = 128;
+
+/// How deeply the interpreter can recurse, counting each nested expression as well as each
+/// call. It takes up to a few kilobytes of native stack for each level in debug builds, so this
+/// keeps it within the 2 MiB a spawned thread gets.
+pub const MAX_NESTING: usize = 512;
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            fuel: None,
+            max_depth: Some(DEFAULT_MAX_DEPTH),
+            max_memory: None,
+            timeout: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Limit {
+    Fuel,
+    Depth,
+    Memory,
+    Time,
+}
+
+impl fmt::Display for Limit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Fuel => "fuel",
+            Self::Depth => "recursion depth",
+            Self::Memory => "memory",
+            Self::Time => "time",
+        })
+    }
+}
+
+/// How many steps are taken between looking at the clock.
+const STEPS_PER_CLOCK_CHECK: u64 = 1024;
+
+/// What a single run has used so far, checked against its [`Limits`].
+#[derive(Debug, Default)]
+pub struct Budget {
+    limits: Limits,
+    steps: u64,
+    depth: usize,
+    nesting: usize,
+    values: usize,
+    deadline: Option<Instant>,
+}
+
+impl Budget {
+    pub fn new(limits: Limits) -> Self {
+        Self {
+            limits,
+            deadline: limits.timeout.map(|timeout| Instant::now() + timeout),
+            ..Self::default()
+        }
+    }
+
+    pub fn step(&mut self) -> Result<(), EvalError> {
+        self.steps += 1;
+
+        if self.limits.fuel.is_some_and(|fuel| self.steps > fuel) {
+            return Err(EvalError::LimitExceeded(Limit::Fuel));
+        }
+
+        if let Some(deadline) = self.deadline {
+            if self.steps.is_multiple_of(STEPS_PER_CLOCK_CHECK) && Instant::now() >= deadline {
+                return Err(EvalError::LimitExceeded(Limit::Time));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Enters a call, which [`Budget::exit`] must be called after if this succeeds.
+    pub fn enter(&mut self) -> Result<(), EvalError> {
+        if self.limits.max_depth.is_some_and(|max| self.depth >= max) {
+            return Err(EvalError::LimitExceeded(Limit::Depth));
+        }
+
+        self.depth += 1;
+        Ok(())
+    }
+
+    pub fn exit(&mut self) {
+        self.depth -= 1;
+    }
+
+    /// Enters a nested expression, which [`Budget::unnest`] must be called after if this
+    /// succeeds. Only the interpreter, which recurses to evaluate expressions, needs this.
+    pub fn nest(&mut self) -> Result<(), EvalError> {
+        if self.nesting >= MAX_NESTING {
+            return Err(EvalError::LimitExceeded(Limit::Depth));
+        }
+
+        self.nesting += 1;
+        Ok(())
+    }
+
+    pub fn unnest(&mut self) {
+        self.nesting -= 1;
+    }
+
+    /// Sets how many values are held, failing if they would take up more memory than allowed.
+    pub fn hold(&mut self, values: usize) -> Result<(), EvalError> {
+        self.values = values;
+
+        let bytes = values.saturating_mul(std::mem::size_of::<Value>());
+        if self.limits.max_memory.is_some_and(|max| bytes > max) {
+            return Err(EvalError::LimitExceeded(Limit::Memory));
+        }
+
+        Ok(())
+    }
+
+    /// How many values are held.
+    pub fn held(&self) -> usize {
+        self.values
+    }
+}
@@ -0,0 +1,119 @@
+use crate::EvalError;
+use std::fmt::Display;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Value {
+    Int(i64),
+    Unit,
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Int(n) => write!(f, "{}", n),
+            Self::Unit => f.write_str("()"),
+        }
+    }
+}
+
+/// Rust types that felix-flow values can be converted to.
+pub trait FromValue: Sized {
+    fn from_value(value: Value) -> Result<Self, EvalError>;
+}
+
+/// Rust types that can be converted to felix-flow values. Converting can fail, since not every
+/// Rust number fits in a felix-flow integer.
+pub trait IntoValue {
+    fn into_value(self) -> Result<Value, EvalError>;
+}
+
+impl FromValue for Value {
+    fn from_value(value: Value) -> Result<Self, EvalError> {
+        Ok(value)
+    }
+}
+
+impl IntoValue for Value {
+    fn into_value(self) -> Result<Value, EvalError> {
+        Ok(self)
+    }
+}
+
+impl FromValue for () {
+    fn from_value(value: Value) -> Result<Self, EvalError> {
+        match value {
+            Value::Unit => Ok(()),
+            value => Err(EvalError::ExpectedUnit(value)),
+        }
+    }
+}
+
+impl IntoValue for () {
+    fn into_value(self) -> Result<Value, EvalError> {
+        Ok(Value::Unit)
+    }
+}
+
+macro_rules! int_conversions {
+    ($($ty:ty),*) => {$(
+        impl FromValue for $ty {
+            fn from_value(value: Value) -> Result<Self, EvalError> {
+                match value {
+                    Value::Int(n) => <$ty>::try_from(n).map_err(|_| EvalError::Overflow),
+                    value => Err(EvalError::ExpectedInt(value)),
+                }
+            }
+        }
+
+        impl IntoValue for $ty {
+            fn into_value(self) -> Result<Value, EvalError> {
+                i64::try_from(self)
+                    .map(Value::Int)
+                    .map_err(|_| EvalError::Overflow)
+            }
+        }
+    )*};
+}
+
+int_conversions!(i8, i16, i32, i64, isize, u8, u16, u32, u64, usize);
+
+/// Floats convert to and from integers only when no rounding is needed, since there's no
+/// float value to hold anything else.
+macro_rules! float_conversions {
+    ($($ty:ty),*) => {$(
+        impl FromValue for $ty {
+            fn from_value(value: Value) -> Result<Self, EvalError> {
+                match value {
+                    Value::Int(n) if (n as $ty) as i128 == i128::from(n) => Ok(n as $ty),
+                    Value::Int(n) => Err(EvalError::Inexact(n.to_string())),
+                    value => Err(EvalError::ExpectedInt(value)),
+                }
+            }
+        }
+
+        impl IntoValue for $ty {
+            fn into_value(self) -> Result<Value, EvalError> {
+                // -2^63 is exactly an i64, but 2^63 is one past the largest.
+                let in_range = (-9_223_372_036_854_775_808.0..9_223_372_036_854_775_808.0)
+                    .contains(&f64::from(self));
+                if in_range && self.fract() == 0.0 {
+                    Ok(Value::Int(self as i64))
+                } else {
+                    Err(EvalError::Inexact(self.to_string()))
+                }
+            }
+        }
+    )*};
+}
+
+float_conversions!(f32, f64);
+
+/// Lets native functions fail with a message of their own.
+impl<T: IntoValue, E: Display> IntoValue for Result<T, E> {
+    fn into_value(self) -> Result<Value, EvalError> {
+        match self {
+            Ok(value) => value.into_value(),
+            Err(e) => Err(EvalError::Native(e.to_string())),
+        }
+    }
+}
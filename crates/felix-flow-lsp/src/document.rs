@@ -0,0 +1,181 @@
+use ide::{FileId, RootDatabase, SourceDatabase};
+use lsp_types::{Position, Range, TextDocumentContentChangeEvent};
+use std::sync::Arc;
+use syntax::{LineCol, LineIndex, TextEdit};
+use text_size::{TextRange, TextSize};
+
+/// An open file, along with what's needed to convert between its positions and offsets. What's
+/// known about its contents is kept in the database.
+///
+/// LSP positions count columns in UTF-16 code units, while the rest of the compiler uses byte
+/// offsets, so every position is converted through the line's text.
+pub(crate) struct Document {
+    pub(crate) file: FileId,
+    text: String,
+    line_index: LineIndex,
+}
+
+impl Document {
+    pub(crate) fn new(file: FileId, text: String) -> Self {
+        Self {
+            file,
+            line_index: LineIndex::new(&text),
+            text,
+        }
+    }
+
+    /// Applies a change from the client to the document and `db`, only reparsing what it touched
+    /// if it comes with a range. A range outside the document is logged and the change dropped,
+    /// since there's nowhere to put its text.
+    pub(crate) fn change(&mut self, db: &mut RootDatabase, change: TextDocumentContentChangeEvent) {
+        match change.range {
+            Some(range) => {
+                let Some(edit) = self.edit(range, change.text) else {
+                    eprintln!(
+                        "dropping change to {}:{}-{}:{}, which is outside the document",
+                        range.start.line,
+                        range.start.character,
+                        range.end.line,
+                        range.end.character
+                    );
+                    return;
+                };
+                edit.apply(&mut self.text);
+                db.apply_edit(self.file, edit);
+            }
+            None => {
+                self.text = change.text;
+                db.set_file_text(self.file, Arc::new(self.text.clone()));
+            }
+        }
+
+        self.line_index = LineIndex::new(&self.text);
+    }
+
+    fn edit(&self, range: Range, text: String) -> Option<TextEdit> {
+        let start = self.offset(range.start)?;
+        let end = self.offset(range.end)?;
+
+        (start <= end).then(|| TextEdit::replace(TextRange::new(start, end), text))
+    }
+
+    pub(crate) fn offset(&self, position: Position) -> Option<TextSize> {
+        let line_start = self.line_index.offset(LineCol {
+            line: position.line,
+            col: 0,
+        })?;
+        let line = self.text[usize::from(line_start)..]
+            .split('\n')
+            .next()
+            .unwrap_or("");
+
+        let mut utf16_col = 0;
+        let mut col = 0;
+
+        for c in line.chars() {
+            if utf16_col >= position.character {
+                break;
+            }
+            utf16_col += c.len_utf16() as u32;
+            col += c.len_utf8() as u32;
+        }
+
+        Some(line_start + TextSize::from(col))
+    }
+
+    pub(crate) fn position(&self, offset: TextSize) -> Position {
+        let line_col = self.line_index.line_col(offset);
+        let line_start = offset - TextSize::from(line_col.col);
+        let character = self.text[TextRange::new(line_start, offset)]
+            .encode_utf16()
+            .count();
+
+        Position::new(line_col.line, character as u32)
+    }
+
+    pub(crate) fn range(&self, range: TextRange) -> Range {
+        Range::new(self.position(range.start()), self.position(range.end()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    #[test]
+    fn convert_positions_through_utf16() {
+        let text = "# ünï😀\nlet a = 1".to_string();
+        let document = Document::new(FileId(0), text);
+
+        let emoji = TextSize::from(7);
+        assert_eq!(document.position(emoji), Position::new(0, 5));
+        assert_eq!(document.offset(Position::new(0, 5)), Some(emoji));
+
+        let after_emoji = TextSize::from(11);
+        assert_eq!(document.position(after_emoji), Position::new(0, 7));
+        assert_eq!(document.offset(Position::new(0, 7)), Some(after_emoji));
+
+        assert_eq!(document.position(16.into()), Position::new(1, 4));
+        assert_eq!(document.offset(Position::new(1, 4)), Some(16.into()));
+        assert_eq!(document.offset(Position::new(2, 0)), None);
+    }
+
+    #[test]
+    fn apply_ranged_and_full_changes() {
+        let mut db = RootDatabase::new();
+        let text = "let a = { 1 }\na".to_string();
+        let file = db.set_file(Path::new("main.ff"), text.clone());
+        let mut document = Document::new(file, text);
+
+        document.change(
+            &mut db,
+            TextDocumentContentChangeEvent {
+                range: Some(Range::new(Position::new(0, 10), Position::new(0, 11))),
+                range_length: None,
+                text: "2 + 3".to_string(),
+            },
+        );
+        assert_eq!(document.text, "let a = { 2 + 3 }\na");
+        assert_eq!(
+            db.analysis(file).syntax().to_string(),
+            ide::Analysis::new(&document.text).syntax().to_string(),
+        );
+        assert_eq!(document.position(18.into()), Position::new(1, 0));
+
+        document.change(
+            &mut db,
+            TextDocumentContentChangeEvent {
+                range: None,
+                range_length: None,
+                text: "b".to_string(),
+            },
+        );
+        assert_eq!(document.text, "b");
+        assert_eq!(*db.file_text(file), "b");
+    }
+
+    #[test]
+    fn drop_changes_outside_the_document() {
+        let mut db = RootDatabase::new();
+        let text = "let a = 1\na".to_string();
+        let file = db.set_file(Path::new("main.ff"), text.clone());
+        let mut document = Document::new(file, text.clone());
+
+        for range in [
+            Range::new(Position::new(5, 0), Position::new(5, 1)),
+            Range::new(Position::new(1, 1), Position::new(0, 0)),
+        ] {
+            document.change(
+                &mut db,
+                TextDocumentContentChangeEvent {
+                    range: Some(range),
+                    range_length: None,
+                    text: "b".to_string(),
+                },
+            );
+            assert_eq!(document.text, text);
+            assert_eq!(*db.file_text(file), text);
+        }
+    }
+}
@@ -0,0 +1,15 @@
+mod document;
+mod server;
+
+use lsp_server::Connection;
+
+fn main() -> server::Result<()> {
+    let (connection, io_threads) = Connection::stdio();
+
+    server::run(&connection)?;
+
+    drop(connection);
+    io_threads.join()?;
+
+    Ok(())
+}
@@ -0,0 +1,746 @@
+use crate::document::Document;
+use ide::{FileId, RootDatabase, SourceDatabase};
+use lsp_server::{Connection, ErrorCode, Message, Notification, Request, Response};
+use lsp_types::notification::{
+    DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument, Notification as _,
+    PublishDiagnostics,
+};
+use lsp_types::request::{
+    Completion, DocumentSymbolRequest, GotoDefinition, HoverRequest, References, Rename,
+    Request as _,
+};
+use lsp_types::{
+    CompletionItem, CompletionItemKind, CompletionOptions, CompletionResponse, CompletionTextEdit,
+    Diagnostic, DiagnosticSeverity, DocumentSymbol, DocumentSymbolResponse, GotoDefinitionResponse,
+    Hover, HoverContents, HoverProviderCapability, InsertTextFormat, Location, MarkupContent,
+    MarkupKind, OneOf, PublishDiagnosticsParams, ServerCapabilities, TextDocumentPositionParams,
+    TextDocumentSyncCapability, TextDocumentSyncKind, TextEdit, Uri, WorkspaceEdit,
+};
+use serde::de::DeserializeOwned;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+
+pub(crate) type Result<T> = std::result::Result<T, Box<dyn Error + Send + Sync>>;
+
+/// Answers requests from the client on `connection` until it shuts the server down.
+pub(crate) fn run(connection: &Connection) -> Result<()> {
+    connection.initialize(serde_json::to_value(capabilities())?)?;
+
+    let mut server = Server {
+        connection,
+        db: RootDatabase::new(),
+        documents: HashMap::new(),
+    };
+
+    for message in &connection.receiver {
+        match message {
+            Message::Request(request) => {
+                if connection.handle_shutdown(&request)? {
+                    return Ok(());
+                }
+
+                let response = server.request(request);
+                connection.sender.send(response.into())?;
+            }
+            Message::Notification(notification) => server.notification(notification)?,
+            Message::Response(_) => {}
+        }
+    }
+
+    Ok(())
+}
+
+fn capabilities() -> ServerCapabilities {
+    ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncCapability::Kind(
+            TextDocumentSyncKind::INCREMENTAL,
+        )),
+        document_symbol_provider: Some(OneOf::Left(true)),
+        definition_provider: Some(OneOf::Left(true)),
+        references_provider: Some(OneOf::Left(true)),
+        hover_provider: Some(HoverProviderCapability::Simple(true)),
+        rename_provider: Some(OneOf::Left(true)),
+        completion_provider: Some(CompletionOptions::default()),
+        ..ServerCapabilities::default()
+    }
+}
+
+/// Open documents are kept in the database with the text the client sent, and the files they
+/// import with the text on disk.
+struct Server<'a> {
+    connection: &'a Connection,
+    db: RootDatabase,
+    documents: HashMap<Uri, Document>,
+}
+
+impl Server<'_> {
+    fn request(&self, request: Request) -> Response {
+        let id = request.id.clone();
+
+        let result = match request.method.as_str() {
+            DocumentSymbolRequest::METHOD => {
+                params(request).and_then(|params: lsp_types::DocumentSymbolParams| {
+                    to_json(self.document_symbols(&params.text_document.uri))
+                })
+            }
+            GotoDefinition::METHOD => {
+                params(request).and_then(|params: lsp_types::GotoDefinitionParams| {
+                    to_json(self.definition(params.text_document_position_params))
+                })
+            }
+            References::METHOD => params(request).and_then(|params: lsp_types::ReferenceParams| {
+                to_json(self.references(
+                    params.text_document_position,
+                    params.context.include_declaration,
+                ))
+            }),
+            HoverRequest::METHOD => params(request).and_then(|params: lsp_types::HoverParams| {
+                to_json(self.hover(params.text_document_position_params))
+            }),
+            Completion::METHOD => {
+                params(request).and_then(|params: lsp_types::CompletionParams| {
+                    to_json(self.completion(params.text_document_position))
+                })
+            }
+            Rename::METHOD => match params(request).map(|params| self.rename(params)) {
+                Ok(Ok(edit)) => to_json(edit),
+                Ok(Err(e)) => {
+                    return Response::new_err(id, ErrorCode::RequestFailed as i32, e.to_string())
+                }
+                Err(e) => Err(e),
+            },
+            method => {
+                return Response::new_err(
+                    id,
+                    ErrorCode::MethodNotFound as i32,
+                    format!("unknown request '{}'", method),
+                )
+            }
+        };
+
+        match result {
+            Ok(result) => Response {
+                id,
+                result: Some(result),
+                error: None,
+            },
+            Err(e) => Response::new_err(id, ErrorCode::InvalidParams as i32, e.to_string()),
+        }
+    }
+
+    fn notification(&mut self, notification: Notification) -> Result<()> {
+        match notification.method.as_str() {
+            DidOpenTextDocument::METHOD => {
+                let Some(params) =
+                    notification_params::<lsp_types::DidOpenTextDocumentParams>(notification)
+                else {
+                    return Ok(());
+                };
+                self.open(params.text_document.uri, params.text_document.text)
+            }
+            DidChangeTextDocument::METHOD => {
+                let Some(params) =
+                    notification_params::<lsp_types::DidChangeTextDocumentParams>(notification)
+                else {
+                    return Ok(());
+                };
+
+                let uri = params.text_document.uri;
+                let Some(document) = self.documents.get_mut(&uri) else {
+                    return Ok(());
+                };
+
+                for change in params.content_changes {
+                    document.change(&mut self.db, change);
+                }
+
+                let file = document.file;
+                self.load_imports(file);
+                self.publish_all(Some(uri))
+            }
+            DidCloseTextDocument::METHOD => {
+                let Some(params) =
+                    notification_params::<lsp_types::DidCloseTextDocumentParams>(notification)
+                else {
+                    return Ok(());
+                };
+                let uri = params.text_document.uri;
+
+                if self.documents.remove(&uri).is_some() {
+                    // Files importing it see what's saved from now on.
+                    let path = path(&uri);
+                    if let Ok(text) = fs::read_to_string(&path) {
+                        self.db.set_file(&path, text);
+                    }
+                }
+
+                self.publish_diagnostics(uri, Vec::new())?;
+                self.publish_all(None)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn open(&mut self, uri: Uri, text: String) -> Result<()> {
+        let file = self.db.set_file(&path(&uri), text.clone());
+        self.documents
+            .insert(uri.clone(), Document::new(file, text));
+        self.load_imports(file);
+
+        self.publish_all(Some(uri))
+    }
+
+    /// Reads the files imported by `file` that aren't in the database yet from disk.
+    fn load_imports(&mut self, file: FileId) {
+        for import in self.db.imports(file) {
+            if self.db.files().file(&import).is_some() {
+                continue;
+            }
+            if let Ok(text) = fs::read_to_string(&import) {
+                self.db.set_file(&import, text);
+            }
+        }
+    }
+
+    /// Publishes the diagnostics of every open document, starting with `changed`. Only those
+    /// depending on what changed are computed again.
+    fn publish_all(&self, changed: Option<Uri>) -> Result<()> {
+        let mut uris: Vec<_> = self
+            .documents
+            .keys()
+            .filter(|uri| Some(*uri) != changed.as_ref())
+            .cloned()
+            .collect();
+        uris.sort();
+
+        for uri in changed.into_iter().chain(uris) {
+            self.publish(uri)?;
+        }
+
+        Ok(())
+    }
+
+    fn publish(&self, uri: Uri) -> Result<()> {
+        let document = &self.documents[&uri];
+        let diagnostics = self
+            .db
+            .diagnostics(document.file)
+            .iter()
+            .cloned()
+            .map(|diagnostic| Diagnostic {
+                range: document.range(diagnostic.range),
+                severity: Some(DiagnosticSeverity::ERROR),
+                source: Some("felix-flow".to_string()),
+                message: diagnostic.message,
+                ..Diagnostic::default()
+            })
+            .collect();
+
+        self.publish_diagnostics(uri, diagnostics)
+    }
+
+    fn publish_diagnostics(&self, uri: Uri, diagnostics: Vec<Diagnostic>) -> Result<()> {
+        let params = PublishDiagnosticsParams::new(uri, diagnostics, None);
+        let notification = Notification::new(PublishDiagnostics::METHOD.to_string(), params);
+        self.connection.sender.send(notification.into())?;
+
+        Ok(())
+    }
+
+    fn document_symbols(&self, uri: &Uri) -> Option<DocumentSymbolResponse> {
+        let document = self.documents.get(uri)?;
+
+        #[allow(deprecated)]
+        fn convert(document: &Document, symbol: ide::Symbol) -> DocumentSymbol {
+            DocumentSymbol {
+                name: symbol.name,
+                detail: None,
+                kind: match symbol.kind {
+                    ide::SymbolKind::Variable => lsp_types::SymbolKind::VARIABLE,
+                    ide::SymbolKind::Function => lsp_types::SymbolKind::FUNCTION,
+                },
+                tags: None,
+                deprecated: None,
+                range: document.range(symbol.range),
+                selection_range: document.range(symbol.name_range),
+                children: Some(
+                    symbol
+                        .children
+                        .into_iter()
+                        .map(|child| convert(document, child))
+                        .collect(),
+                ),
+            }
+        }
+
+        let symbols = self.db.analysis(document.file).document_symbols();
+
+        Some(DocumentSymbolResponse::Nested(
+            symbols
+                .into_iter()
+                .map(|symbol| convert(document, symbol))
+                .collect(),
+        ))
+    }
+
+    fn definition(&self, position: TextDocumentPositionParams) -> Option<GotoDefinitionResponse> {
+        let uri = position.text_document.uri;
+        let document = self.documents.get(&uri)?;
+
+        let offset = document.offset(position.position)?;
+        let analysis = self.db.analysis(document.file);
+        let definition = analysis.definition(offset)?;
+
+        Some(GotoDefinitionResponse::Scalar(Location::new(
+            uri.clone(),
+            document.range(definition.name_range),
+        )))
+    }
+
+    fn references(
+        &self,
+        position: TextDocumentPositionParams,
+        include_declaration: bool,
+    ) -> Option<Vec<Location>> {
+        let uri = position.text_document.uri;
+        let document = self.documents.get(&uri)?;
+
+        let offset = document.offset(position.position)?;
+        let references = self
+            .db
+            .analysis(document.file)
+            .references(offset, include_declaration)
+            .into_iter()
+            .map(|range| Location::new(uri.clone(), document.range(range)))
+            .collect();
+
+        Some(references)
+    }
+
+    fn hover(&self, position: TextDocumentPositionParams) -> Option<Hover> {
+        let document = self.documents.get(&position.text_document.uri)?;
+
+        let offset = document.offset(position.position)?;
+        let hover = self.db.analysis(document.file).hover(offset)?;
+
+        Some(Hover {
+            contents: HoverContents::Markup(MarkupContent {
+                kind: MarkupKind::Markdown,
+                value: format!("```felix-flow\n{}\n```", hover.text),
+            }),
+            range: Some(document.range(hover.range)),
+        })
+    }
+
+    fn completion(&self, position: TextDocumentPositionParams) -> Option<CompletionResponse> {
+        let document = self.documents.get(&position.text_document.uri)?;
+
+        let offset = document.offset(position.position)?;
+        let items = self
+            .db
+            .analysis(document.file)
+            .completions(offset)
+            .into_iter()
+            .map(|item| CompletionItem {
+                label: item.label,
+                kind: Some(match item.kind {
+                    ide::CompletionKind::Variable => CompletionItemKind::VARIABLE,
+                    ide::CompletionKind::Function => CompletionItemKind::FUNCTION,
+                    ide::CompletionKind::Module => CompletionItemKind::MODULE,
+                    ide::CompletionKind::Keyword => CompletionItemKind::KEYWORD,
+                }),
+                detail: item.detail,
+                insert_text_format: Some(if item.is_snippet {
+                    InsertTextFormat::SNIPPET
+                } else {
+                    InsertTextFormat::PLAIN_TEXT
+                }),
+                text_edit: Some(CompletionTextEdit::Edit(TextEdit::new(
+                    document.range(item.range),
+                    item.insert_text,
+                ))),
+                ..CompletionItem::default()
+            })
+            .collect();
+
+        Some(CompletionResponse::Array(items))
+    }
+
+    fn rename(
+        &self,
+        params: lsp_types::RenameParams,
+    ) -> std::result::Result<Option<WorkspaceEdit>, ide::RenameError> {
+        let uri = params.text_document_position.text_document.uri;
+        let Some(document) = self.documents.get(&uri) else {
+            return Ok(None);
+        };
+        let Some(offset) = document.offset(params.text_document_position.position) else {
+            return Ok(None);
+        };
+
+        let edits = self
+            .db
+            .analysis(document.file)
+            .rename(offset, &params.new_name)?
+            .into_iter()
+            .map(|edit| TextEdit::new(document.range(edit.delete), edit.insert))
+            .collect();
+
+        Ok(Some(WorkspaceEdit::new(HashMap::from([(uri, edits)]))))
+    }
+}
+
+/// The path of the file `uri` refers to, which imports are found relative to.
+fn path(uri: &Uri) -> PathBuf {
+    PathBuf::from(&*uri.path().as_estr().decode().into_string_lossy())
+}
+
+fn params<P: DeserializeOwned>(request: Request) -> Result<P> {
+    Ok(serde_json::from_value(request.params)?)
+}
+
+/// Notifications have no response to carry an error, so malformed ones are logged and dropped.
+fn notification_params<P: DeserializeOwned>(notification: Notification) -> Option<P> {
+    match serde_json::from_value(notification.params) {
+        Ok(params) => Some(params),
+        Err(e) => {
+            eprintln!(
+                "dropping malformed '{}' notification: {}",
+                notification.method, e
+            );
+            None
+        }
+    }
+}
+
+fn to_json(result: impl serde::Serialize) -> Result<serde_json::Value> {
+    Ok(serde_json::to_value(result)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use expect_test::{expect, Expect};
+    use lsp_server::RequestId;
+    use serde_json::{json, Value};
+    use std::thread::{self, JoinHandle};
+
+    const URI: &str = "file:///main.ff";
+
+    /// Drives a server running on another thread through an in-memory connection.
+    struct Client {
+        connection: Connection,
+        server: Option<JoinHandle<()>>,
+        next_id: i32,
+    }
+
+    impl Client {
+        fn new() -> Self {
+            let (server, connection) = Connection::memory();
+            let server = thread::spawn(move || run(&server).unwrap());
+
+            let mut client = Self {
+                connection,
+                server: Some(server),
+                next_id: 0,
+            };
+            client.request("initialize", json!({ "capabilities": {} }));
+            client.notify("initialized", json!({}));
+
+            client
+        }
+
+        fn request(&mut self, method: &str, params: Value) -> Value {
+            self.next_id += 1;
+            let id = RequestId::from(self.next_id);
+
+            let request = Request::new(id.clone(), method.to_string(), params);
+            self.connection.sender.send(request.into()).unwrap();
+
+            match self.connection.receiver.recv().unwrap() {
+                Message::Response(response) if response.id == id => match response.error {
+                    Some(error) => json!({ "error": error.message }),
+                    None => response.result.unwrap_or(Value::Null),
+                },
+                message => panic!("expected a response, but got {:?}", message),
+            }
+        }
+
+        fn notify(&self, method: &str, params: Value) {
+            let notification = Notification::new(method.to_string(), params);
+            self.connection.sender.send(notification.into()).unwrap();
+        }
+
+        /// Opens the test document, returning the published diagnostics.
+        fn open(&self, text: &str) -> Value {
+            self.open_uri(URI, text);
+            self.diagnostics()
+        }
+
+        fn open_uri(&self, uri: &str, text: &str) {
+            self.notify(
+                "textDocument/didOpen",
+                json!({
+                    "textDocument": { "uri": uri, "languageId": "felix-flow", "version": 0, "text": text },
+                }),
+            );
+        }
+
+        /// Changes the test document, returning the published diagnostics.
+        fn change(&self, change: Value) -> Value {
+            self.change_uri(URI, change);
+            self.diagnostics()
+        }
+
+        fn change_uri(&self, uri: &str, change: Value) {
+            self.notify(
+                "textDocument/didChange",
+                json!({
+                    "textDocument": { "uri": uri, "version": 1 },
+                    "contentChanges": [change],
+                }),
+            );
+        }
+
+        fn diagnostics(&self) -> Value {
+            self.publication()["diagnostics"].clone()
+        }
+
+        fn publication(&self) -> Value {
+            match self.connection.receiver.recv().unwrap() {
+                Message::Notification(notification)
+                    if notification.method == PublishDiagnostics::METHOD =>
+                {
+                    notification.params
+                }
+                message => panic!("expected diagnostics, but got {:?}", message),
+            }
+        }
+
+        /// The messages published for the next `count` documents, one document per line.
+        fn published(&self, count: usize) -> String {
+            (0..count)
+                .map(|_| {
+                    let publication = self.publication();
+                    let messages: Vec<_> = publication["diagnostics"]
+                        .as_array()
+                        .unwrap()
+                        .iter()
+                        .map(|diagnostic| diagnostic["message"].as_str().unwrap().to_string())
+                        .collect();
+
+                    format!("{} {:?}\n", publication["uri"].as_str().unwrap(), messages)
+                })
+                .collect()
+        }
+
+        fn at(&mut self, method: &str, line: u32, character: u32) -> Value {
+            self.request(
+                method,
+                json!({
+                    "textDocument": { "uri": URI },
+                    "position": { "line": line, "character": character },
+                    "context": { "includeDeclaration": true },
+                }),
+            )
+        }
+    }
+
+    impl Drop for Client {
+        fn drop(&mut self) {
+            self.request("shutdown", Value::Null);
+            self.notify("exit", Value::Null);
+            self.server.take().unwrap().join().unwrap();
+        }
+    }
+
+    fn check(actual: Value, expected: Expect) {
+        expected.assert_eq(&actual.to_string());
+    }
+
+    #[test]
+    fn publish_parse_errors_on_every_change() {
+        let client = Client::new();
+
+        check(
+            client.open("let a =\nlet b = 1"),
+            expect![[
+                r#"[{"message":"expected number, identifier, '-', '(' or '{', but found 'let'","range":{"end":{"character":3,"line":1},"start":{"character":0,"line":1}},"severity":1,"source":"felix-flow"}]"#
+            ]],
+        );
+        check(
+            client.change(json!({
+                "range": { "start": { "line": 0, "character": 7 }, "end": { "line": 0, "character": 7 } },
+                "text": " 1",
+            })),
+            expect!["[]"],
+        );
+        check(
+            client.change(json!({ "text": "let" })),
+            expect![[
+                r#"[{"message":"expected identifier","range":{"end":{"character":3,"line":0},"start":{"character":0,"line":0}},"severity":1,"source":"felix-flow"},{"message":"expected '='","range":{"end":{"character":3,"line":0},"start":{"character":0,"line":0}},"severity":1,"source":"felix-flow"},{"message":"expected number, identifier, '-', '(' or '{'","range":{"end":{"character":3,"line":0},"start":{"character":0,"line":0}},"severity":1,"source":"felix-flow"}]"#
+            ]],
+        );
+    }
+
+    #[test]
+    fn recheck_open_documents_importing_a_changed_one() {
+        let client = Client::new();
+
+        client.open_uri("file:///lib.ff", "let x = 1");
+        client.published(1);
+
+        client.open_uri(URI, "import \"lib.ff\" as lib\nlib.x + lib.y");
+        expect![[r#"
+            file:///main.ff ["module 'lib' has no 'y'"]
+            file:///lib.ff []
+        "#]]
+        .assert_eq(&client.published(2));
+
+        client.change_uri("file:///lib.ff", json!({ "text": "let x = 1\nlet y = 2" }));
+        expect![[r#"
+            file:///lib.ff []
+            file:///main.ff []
+        "#]]
+        .assert_eq(&client.published(2));
+    }
+
+    #[test]
+    fn load_imported_files_from_disk() {
+        let dir = std::env::temp_dir().join(format!("felix-flow-lsp-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("saved.ff"), "fn f() { 1 }").unwrap();
+
+        let client = Client::new();
+        client.open_uri(
+            &format!("file://{}/main.ff", dir.display()),
+            "import \"saved.ff\" as s\ns.f() + s.g()",
+        );
+        let published = client.published(1);
+        fs::remove_dir_all(&dir).unwrap();
+
+        expect![[r#"
+            file://$DIR/main.ff ["module 's' has no 'g'"]
+        "#]]
+        .assert_eq(&published.replace(&dir.display().to_string(), "$DIR"));
+    }
+
+    #[test]
+    fn document_symbols() {
+        let mut client = Client::new();
+        client.open("fn f(x) {\n  let y = x\n  y\n}");
+
+        check(
+            client.request(
+                "textDocument/documentSymbol",
+                json!({ "textDocument": { "uri": URI } }),
+            ),
+            expect![[
+                r#"[{"children":[{"children":[],"kind":13,"name":"y","range":{"end":{"character":11,"line":1},"start":{"character":2,"line":1}},"selectionRange":{"end":{"character":7,"line":1},"start":{"character":6,"line":1}}}],"kind":12,"name":"f","range":{"end":{"character":1,"line":3},"start":{"character":0,"line":0}},"selectionRange":{"end":{"character":4,"line":0},"start":{"character":3,"line":0}}}]"#
+            ]],
+        );
+    }
+
+    #[test]
+    fn definition_and_references() {
+        let mut client = Client::new();
+        client.open("let a = 1\nfn f() { a }\na + f()");
+
+        check(
+            client.at("textDocument/definition", 2, 0),
+            expect![[
+                r#"{"range":{"end":{"character":5,"line":0},"start":{"character":4,"line":0}},"uri":"file:///main.ff"}"#
+            ]],
+        );
+        check(
+            client.at("textDocument/references", 0, 4),
+            expect![[
+                r#"[{"range":{"end":{"character":5,"line":0},"start":{"character":4,"line":0}},"uri":"file:///main.ff"},{"range":{"end":{"character":10,"line":1},"start":{"character":9,"line":1}},"uri":"file:///main.ff"},{"range":{"end":{"character":1,"line":2},"start":{"character":0,"line":2}},"uri":"file:///main.ff"}]"#
+            ]],
+        );
+        check(client.at("textDocument/definition", 2, 2), expect!["null"]);
+    }
+
+    #[test]
+    fn hover() {
+        let mut client = Client::new();
+        client.open("fn add(x, y) { x + y }\nadd(1, 2)");
+
+        check(
+            client.at("textDocument/hover", 0, 4),
+            expect![[
+                r#"{"contents":{"kind":"markdown","value":"```felix-flow\nfn add(x, y): int\n```"},"range":{"end":{"character":6,"line":0},"start":{"character":3,"line":0}}}"#
+            ]],
+        );
+    }
+
+    #[test]
+    fn rename() {
+        let mut client = Client::new();
+        client.open("let a = 1\nlet b = a");
+
+        let mut rename = |character: u32, new_name: &str| {
+            client.request(
+                "textDocument/rename",
+                json!({
+                    "textDocument": { "uri": URI },
+                    "position": { "line": 1, "character": character },
+                    "newName": new_name,
+                }),
+            )
+        };
+
+        check(
+            rename(8, "c"),
+            expect![[
+                r#"{"changes":{"file:///main.ff":[{"newText":"c","range":{"end":{"character":5,"line":0},"start":{"character":4,"line":0}}},{"newText":"c","range":{"end":{"character":9,"line":1},"start":{"character":8,"line":1}}}]}}"#
+            ]],
+        );
+        check(
+            rename(4, "fn"),
+            expect![[r#"{"error":"'fn' is a keyword"}"#]],
+        );
+    }
+
+    #[test]
+    fn completion() {
+        let mut client = Client::new();
+        client.open("fn add(x, y) { x + y }\nlet a = ad");
+
+        check(
+            client.request(
+                "textDocument/completion",
+                json!({
+                    "textDocument": { "uri": URI },
+                    "position": { "line": 1, "character": 10 },
+                }),
+            ),
+            expect![[
+                r#"[{"detail":"fn add(x, y)","insertTextFormat":2,"kind":3,"label":"add","textEdit":{"newText":"add(${1:x}, ${2:y})","range":{"end":{"character":10,"line":1},"start":{"character":8,"line":1}}}}]"#
+            ]],
+        );
+    }
+
+    #[test]
+    fn drop_malformed_notifications() {
+        let client = Client::new();
+
+        client.notify("textDocument/didOpen", json!({ "textDocument": 1 }));
+        client.notify("textDocument/didChange", Value::Null);
+        client.notify("textDocument/didClose", json!({}));
+
+        check(client.open("let a = 1"), expect!["[]"]);
+    }
+
+    #[test]
+    fn reject_unknown_requests() {
+        let mut client = Client::new();
+
+        check(
+            client.request("textDocument/formatting", json!({})),
+            expect![[r#"{"error":"unknown request 'textDocument/formatting'"}"#]],
+        );
+    }
+}
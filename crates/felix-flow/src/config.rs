@@ -0,0 +1,261 @@
+use crate::session::Backend;
+use formatter::FormatOptions;
+use ide::Severity;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::{env, fs};
+use syntax::{LineCol, LineIndex};
+use toml_edit::{Item, Key, TableLike};
+
+const FILE_NAME: &str = "felix-flow.toml";
+
+/// Settings from a `felix-flow.toml`, which apply to the directory it's in and everything below.
+#[derive(Debug, Default, PartialEq)]
+pub(crate) struct Config {
+    pub(crate) backend: Option<Backend>,
+    /// The file to use when none is given.
+    pub(crate) entry: Option<PathBuf>,
+    pub(crate) format: FormatOptions,
+    pub(crate) lint_levels: HashMap<String, Severity>,
+}
+
+#[derive(Debug)]
+pub(crate) struct ConfigError {
+    path: PathBuf,
+    line_col: LineCol,
+    message: String,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}:{}: {}",
+            self.path.display(),
+            self.line_col.line + 1,
+            self.line_col.col + 1,
+            self.message
+        )
+    }
+}
+
+impl Error for ConfigError {}
+
+impl Config {
+    /// The config that applies to `path`, found by looking in the directory it's in and then in
+    /// each directory above.
+    pub(crate) fn for_file(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let path = env::current_dir()?.join(path);
+        Self::discover(path.parent().unwrap_or(&path))
+    }
+
+    /// The config that applies to the current directory.
+    pub(crate) fn for_current_dir() -> Result<Self, Box<dyn Error>> {
+        Self::discover(&env::current_dir()?)
+    }
+
+    fn discover(dir: &Path) -> Result<Self, Box<dyn Error>> {
+        for dir in dir.ancestors() {
+            let path = dir.join(FILE_NAME);
+            if !path.is_file() {
+                continue;
+            }
+
+            let text = fs::read_to_string(&path)?;
+            return Self::parse(&text, dir).map_err(|(span, message)| {
+                let offset = u32::try_from(span.start).unwrap().into();
+                ConfigError {
+                    line_col: LineIndex::new(&text).line_col(offset),
+                    path,
+                    message,
+                }
+                .into()
+            });
+        }
+
+        Ok(Self::default())
+    }
+
+    /// Reads a config from `text`, with paths in it relative to `dir`, or returns the span of
+    /// what's wrong with it.
+    fn parse(text: &str, dir: &Path) -> Result<Self, (Range<usize>, String)> {
+        let document = toml_edit::Document::parse(text)
+            .map_err(|e| (e.span().unwrap_or(0..0), e.message().trim().to_string()))?;
+
+        let mut config = Self::default();
+
+        for (key, item) in entries(document.as_table()) {
+            match key.get() {
+                "backend" => {
+                    let backend = string(key, item)?;
+                    config.backend = Some(backend.parse().map_err(|e| error(key, e))?);
+                }
+                "entry" => config.entry = Some(dir.join(string(key, item)?)),
+                "format" => {
+                    for (option, item) in entries(table(key, item)?) {
+                        let value = match option.get() {
+                            "width" => &mut config.format.width,
+                            "indent" => &mut config.format.indent,
+                            _ => return Err(unknown(option, Some(key))),
+                        };
+
+                        *value = item
+                            .as_integer()
+                            .and_then(|n| usize::try_from(n).ok())
+                            .filter(|&n| (1..=1000).contains(&n))
+                            .ok_or_else(|| {
+                                error(
+                                    option,
+                                    format!(
+                                        "'format.{}' must be a whole number from 1 to 1000",
+                                        option.get()
+                                    ),
+                                )
+                            })?;
+                    }
+                }
+                "lint" => {
+                    for (rule, item) in entries(table(key, item)?) {
+                        if !ide::lint_rules()
+                            .any(|(code, name)| rule.get() == code || rule.get() == name)
+                        {
+                            return Err(error(rule, format!("unknown lint rule '{}'", rule.get())));
+                        }
+
+                        let severity = match string(rule, item)? {
+                            "allow" => Severity::Allow,
+                            "warn" => Severity::Warning,
+                            "deny" => Severity::Error,
+                            level => {
+                                return Err(error(
+                                    rule,
+                                    format!(
+                                        "unknown level '{}' for 'lint.{}', expected 'allow', 'warn' or 'deny'",
+                                        level,
+                                        rule.get()
+                                    ),
+                                ))
+                            }
+                        };
+                        config.lint_levels.insert(rule.get().to_string(), severity);
+                    }
+                }
+                _ => return Err(unknown(key, None)),
+            }
+        }
+
+        Ok(config)
+    }
+}
+
+fn entries(table: &dyn TableLike) -> impl Iterator<Item = (&Key, &Item)> {
+    table.iter().filter_map(|(key, _)| table.get_key_value(key))
+}
+
+fn string<'a>(key: &Key, item: &'a Item) -> Result<&'a str, (Range<usize>, String)> {
+    item.as_str().ok_or_else(|| {
+        error(
+            key,
+            format!("'{}' must be a string, not {}", key.get(), item.type_name()),
+        )
+    })
+}
+
+fn table<'a>(key: &Key, item: &'a Item) -> Result<&'a dyn TableLike, (Range<usize>, String)> {
+    item.as_table_like().ok_or_else(|| {
+        error(
+            key,
+            format!("'{}' must be a table, not {}", key.get(), item.type_name()),
+        )
+    })
+}
+
+fn unknown(key: &Key, table: Option<&Key>) -> (Range<usize>, String) {
+    let name = match table {
+        Some(table) => format!("{}.{}", table.get(), key.get()),
+        None => key.get().to_string(),
+    };
+    error(key, format!("unknown key '{}'", name))
+}
+
+fn error(key: &Key, message: impl Into<String>) -> (Range<usize>, String) {
+    (key.span().unwrap_or(0..0), message.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use expect_test::{expect, Expect};
+
+    fn check_error(text: &str, expected: Expect) {
+        let actual = match Config::parse(text, Path::new("/project")) {
+            Ok(config) => format!("{:?}", config),
+            Err((span, message)) => format!("{:?}: {}", &text[span], message),
+        };
+
+        expected.assert_eq(&actual);
+    }
+
+    #[test]
+    fn read_every_setting() {
+        let config = Config::parse(
+            "backend = \"vm\"\nentry = \"src/main.ff\"\n\n[format]\nwidth = 80\nindent = 2\n\n[lint]\nunused-variable = \"deny\"\nL003 = \"allow\"\n",
+            Path::new("/project"),
+        )
+        .unwrap();
+
+        assert_eq!(
+            config,
+            Config {
+                backend: Some(Backend::Vm),
+                entry: Some(PathBuf::from("/project/src/main.ff")),
+                format: FormatOptions {
+                    width: 80,
+                    indent: 2,
+                },
+                lint_levels: HashMap::from([
+                    ("unused-variable".to_string(), Severity::Error),
+                    ("L003".to_string(), Severity::Allow),
+                ]),
+            }
+        );
+    }
+
+    #[test]
+    fn point_at_the_offending_key() {
+        check_error(
+            "backend = \"jit\"",
+            expect![[r#""backend": unknown backend 'jit', expected 'vm' or 'tree'"#]],
+        );
+        check_error(
+            "format = { width = 0 }",
+            expect![[r#""width": 'format.width' must be a whole number from 1 to 1000"#]],
+        );
+        check_error(
+            "[format]\ntabs = true",
+            expect![[r#""tabs": unknown key 'format.tabs'"#]],
+        );
+        check_error(
+            "[lint]\nunused = \"deny\"",
+            expect![[r#""unused": unknown lint rule 'unused'"#]],
+        );
+        check_error(
+            "[lint]\nL001 = \"forbid\"",
+            expect![[
+                r#""L001": unknown level 'forbid' for 'lint.L001', expected 'allow', 'warn' or 'deny'"#
+            ]],
+        );
+        check_error(
+            "entry = 1",
+            expect![[r#""entry": 'entry' must be a string, not integer"#]],
+        );
+        check_error("[run]", expect![[r#""run": unknown key 'run'"#]]);
+        check_error(
+            "backend = ",
+            expect![[r#""": string values must be quoted, expected literal string"#]],
+        );
+    }
+}
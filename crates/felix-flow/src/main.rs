@@ -1,7 +1,464 @@
-use parser::parse;
-use std::io::{self, Write};
+mod config;
+mod session;
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
+use config::Config;
+use eval::{Limits, Value};
+use ide::{RootDatabase, SourceDatabase};
+use session::{Backend, Session};
+use std::error::Error;
+use std::io::{self, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+use std::{env, fs, process};
+use syntax::{LineCol, LineIndex};
+
+const USAGE: &str = "\
+usage: felix-flow [--backend=vm|tree] [LIMIT...] [FILE]
+       felix-flow --emit=optimized [FILE]
+       felix-flow compile [FILE] [-o OUTPUT]
+       felix-flow build --target=wat|c [FILE] [-o OUTPUT]
+       felix-flow disasm [FILE]
+       felix-flow fmt [--check] [FILE...]
+       felix-flow highlight [--html] [FILE]
+       felix-flow lint [--fix] [FILE...]
+       felix-flow parse [--format=json|sexp] [FILE]
+       felix-flow rename [FILE] LINE:COLUMN NEW_NAME
+
+Running code, or the REPL, stops at the limits --fuel=STEPS, --max-depth=CALLS,
+--max-memory=BYTES and --timeout=MILLISECONDS. Calls nest at most 128 deep unless set otherwise.
+
+Settings are read from the closest felix-flow.toml above the file, and FILE defaults to the
+entry file set there.";
+
+/// Where REPL input is taken to come from, so that imports are found in the current directory.
+const REPL_PATH: &str = "<repl>";
+
+/// The extension of compiled modules written by `felix-flow compile`.
+const COMPILED_EXTENSION: &str = "ffc";
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    let result = match args.first().map(String::as_str) {
+        Some("compile") => compile(&args[1..]),
+        Some("disasm") => disasm(&args[1..]),
+        Some("build") => build(&args[1..]),
+        Some("fmt") => fmt(&args[1..]),
+        Some("highlight") => highlight(&args[1..]),
+        Some("lint") => lint(&args[1..]),
+        Some("parse") => parse(&args[1..]),
+        Some("rename") => rename(&args[1..]),
+        _ => run(&args),
+    };
+
+    if let Err(e) = result {
+        fail(e);
+    }
+}
+
+fn run(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let mut backend = None;
+    let mut emit_optimized = false;
+    let mut limits = Limits::default();
+    let mut path = None;
+
+    for arg in args {
+        if set_limit(&mut limits, arg)? {
+            continue;
+        }
+
+        if let Some(name) = arg.strip_prefix("--backend=") {
+            backend = Some(name.parse()?);
+        } else if arg == "--emit=optimized" {
+            emit_optimized = true;
+        } else if path.is_none() && !arg.starts_with('-') {
+            path = Some(PathBuf::from(arg));
+        } else {
+            return Err(USAGE.into());
+        }
+    }
+
+    let config = match &path {
+        Some(path) => Config::for_file(path)?,
+        None => Config::for_current_dir()?,
+    };
+    let backend = backend.or(config.backend).unwrap_or(Backend::Tree);
+
+    let Some(path) = path.or(config.entry) else {
+        if emit_optimized {
+            return Err(USAGE.into());
+        }
+        return repl(Session::new(backend, limits));
+    };
+
+    if emit_optimized {
+        print!("{}", session::lower(&path, &fs::read_to_string(&path)?)?);
+        return Ok(());
+    }
+
+    let value = if is_compiled(&path) {
+        let executable = load(&path)?;
+        let mut vm = vm::Vm::new();
+        vm.set_limits(limits);
+        vm.run(&executable.module, &executable.main)?
+    } else {
+        Session::new(backend, limits).run(&path, &fs::read_to_string(&path)?)?
+    };
+
+    print_value(value);
+
+    Ok(())
+}
+
+/// Sets the limit that `arg` is a flag for, returning whether it was one.
+fn set_limit(limits: &mut Limits, arg: &str) -> Result<bool, Box<dyn Error>> {
+    let Some((flag, value)) = arg.split_once('=') else {
+        return Ok(false);
+    };
+    let invalid = |_| format!("invalid value '{}' for {}", value, flag);
+
+    match flag {
+        "--fuel" => limits.fuel = Some(value.parse().map_err(invalid)?),
+        "--max-depth" => limits.max_depth = Some(value.parse().map_err(invalid)?),
+        "--max-memory" => limits.max_memory = Some(value.parse().map_err(invalid)?),
+        "--timeout" => {
+            limits.timeout = Some(Duration::from_millis(value.parse().map_err(invalid)?))
+        }
+        _ => return Ok(false),
+    }
+
+    Ok(true)
+}
+
+fn compile(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let mut input = None;
+    let mut output = None;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if arg == "-o" {
+            output = Some(PathBuf::from(args.next().ok_or(USAGE)?));
+        } else if input.is_none() && !arg.starts_with('-') {
+            input = Some(PathBuf::from(arg));
+        } else {
+            return Err(USAGE.into());
+        }
+    }
+
+    let (input, _) = input_and_config(input)?;
+    let output = output.unwrap_or_else(|| input.with_extension(COMPILED_EXTENSION));
+
+    let executable = session::compile(&input, &fs::read_to_string(&input)?)?;
+    fs::write(output, executable.to_bytes())?;
+
+    Ok(())
+}
+
+fn disasm(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let path = match args {
+        [] => None,
+        [path] if !path.starts_with('-') => Some(PathBuf::from(path)),
+        _ => return Err(USAGE.into()),
+    };
+    let (path, _) = input_and_config(path)?;
+
+    let executable = if is_compiled(&path) {
+        load(&path)?
+    } else {
+        session::compile(&path, &fs::read_to_string(&path)?)?
+    };
+
+    print!("{}", vm::disassemble(&executable.module, &executable.main));
+
+    Ok(())
+}
+
+fn build(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let mut target = None;
+    let mut input = None;
+    let mut output = None;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if let Some(name) = arg.strip_prefix("--target=") {
+            target = Some(name.parse::<Target>()?);
+        } else if arg == "-o" {
+            output = Some(PathBuf::from(args.next().ok_or(USAGE)?));
+        } else if input.is_none() && !arg.starts_with('-') {
+            input = Some(PathBuf::from(arg));
+        } else {
+            return Err(USAGE.into());
+        }
+    }
+
+    let Some(target) = target else {
+        return Err(USAGE.into());
+    };
+    let (input, _) = input_and_config(input)?;
+    let output = output.unwrap_or_else(|| input.with_extension(target.extension()));
+
+    let program = session::lower(&input, &fs::read_to_string(&input)?)?;
+
+    let code = match target {
+        Target::Wat => wasm::compile(&program)?,
+        Target::C => cgen::compile(&program)?,
+    };
+    fs::write(output, code)?;
+
+    Ok(())
+}
+
+/// Formats files in place, or with `--check` lists the ones that aren't formatted and fails if
+/// there are any.
+fn fmt(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let mut check = false;
+    let mut paths = Vec::new();
+
+    for arg in args {
+        if arg == "--check" {
+            check = true;
+        } else if !arg.starts_with('-') {
+            paths.push(PathBuf::from(arg));
+        } else {
+            return Err(USAGE.into());
+        }
+    }
+
+    let mut db = RootDatabase::new();
+    let mut unformatted = Vec::new();
+
+    for (path, config) in inputs_and_configs(paths)? {
+        let input = fs::read_to_string(&path)?;
+        let file = db.set_file(&path, input.clone());
+        let parse = db.parse(file);
+
+        if !parse.errors().is_empty() {
+            let errors: Vec<_> = parse
+                .errors()
+                .iter()
+                .map(|error| format!("{}: {}", path.display(), error))
+                .collect();
+            return Err(errors.join("\n").into());
+        }
+
+        let output = formatter::format_with(&parse.syntax(), &config.format);
+        if output == input {
+            continue;
+        }
+
+        if check {
+            unformatted.push(path.display().to_string());
+        } else {
+            fs::write(&path, output)?;
+        }
+    }
+
+    if !unformatted.is_empty() {
+        return Err(unformatted.join("\n").into());
+    }
+
+    Ok(())
+}
+
+fn highlight(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let (path, html) = match args {
+        [] => (None, false),
+        [flag] if flag == "--html" => (None, true),
+        [path] if !path.starts_with('-') => (Some(path), false),
+        [flag, path] | [path, flag] if flag == "--html" => (Some(path), true),
+        _ => return Err(USAGE.into()),
+    };
+    let (path, _) = input_and_config(path.map(PathBuf::from))?;
+
+    let text = fs::read_to_string(path)?;
+    let highlights = ide::Analysis::new(&text).highlight();
+
+    if html {
+        print!("{}", ide::render_html(&text, &highlights));
+    } else {
+        print!("{}", ide::render_ansi(&text, &highlights));
+    }
+
+    Ok(())
+}
+
+/// Prints the lints in files, failing if any of them are errors. With `--fix`, the lints that can
+/// be fixed automatically are fixed in place first. The files are analyzed in one database, like
+/// the language server's.
+fn lint(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let mut fix = false;
+    let mut paths = Vec::new();
+
+    for arg in args {
+        if arg == "--fix" {
+            fix = true;
+        } else if !arg.starts_with('-') {
+            paths.push(PathBuf::from(arg));
+        } else {
+            return Err(USAGE.into());
+        }
+    }
+
+    let mut db = RootDatabase::new();
+    let mut errors = 0;
+
+    for (path, config) in inputs_and_configs(paths)? {
+        let mut text = fs::read_to_string(&path)?;
+        let file = db.set_file(&path, text.clone());
+        let mut lints = db.analysis(file).lints_with_levels(&config.lint_levels);
+
+        if fix {
+            // Fixes can overlap, like those for `a - - -b`, so only the first of those is applied.
+            let mut edits: Vec<syntax::TextEdit> = Vec::new();
+            for fix in lints.iter().filter_map(|lint| lint.fix.as_ref()) {
+                let overlaps = fix.iter().any(|edit| {
+                    edits.iter().any(|other| {
+                        edit.delete.start() < other.delete.end()
+                            && other.delete.start() < edit.delete.end()
+                    })
+                });
+                if !overlaps {
+                    edits.extend(fix.iter().cloned());
+                }
+            }
+
+            if !edits.is_empty() {
+                edits.sort_by_key(|edit| edit.delete.start());
+                for edit in edits.iter().rev() {
+                    edit.apply(&mut text);
+                }
+                fs::write(&path, &text)?;
+
+                db.set_file(&path, text.clone());
+                lints = db.analysis(file).lints_with_levels(&config.lint_levels);
+            }
+        }
+
+        let line_index = LineIndex::new(&text);
+        for lint in lints {
+            let LineCol { line, col } = line_index.line_col(lint.range.start());
+            println!(
+                "{}:{}:{}: {}: {} [{} {}]",
+                path.display(),
+                line + 1,
+                col + 1,
+                lint.severity,
+                lint.message,
+                lint.code,
+                lint.name
+            );
+
+            if lint.severity == ide::Severity::Error {
+                errors += 1;
+            }
+        }
+    }
+
+    if errors > 0 {
+        return Err(format!("{} lint error(s)", errors).into());
+    }
+
+    Ok(())
+}
+
+/// Prints the syntax tree and parse errors of a file, as an S-expression unless asked for JSON.
+fn parse(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let mut json = false;
+    let mut path = None;
+
+    for arg in args {
+        match arg.strip_prefix("--format=") {
+            Some("json") => json = true,
+            Some("sexp") => json = false,
+            Some(format) => {
+                return Err(
+                    format!("unknown format '{}', expected 'json' or 'sexp'", format).into(),
+                )
+            }
+            None if path.is_none() && !arg.starts_with('-') => path = Some(arg),
+            None => return Err(USAGE.into()),
+        }
+    }
+
+    let (path, _) = input_and_config(path.map(PathBuf::from))?;
+
+    let parse = parser::parse(&fs::read_to_string(path)?);
+
+    if json {
+        println!("{}", parse.to_json());
+    } else {
+        print!("{}", parse.to_sexp());
+    }
+
+    Ok(())
+}
+
+/// Renames the variable or function at a position, given with lines and columns counting from 1,
+/// rewriting the file in place.
+fn rename(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let (path, position, new_name) = match args {
+        [position, new_name] => (None, position, new_name),
+        [path, position, new_name] => (Some(path), position, new_name),
+        _ => return Err(USAGE.into()),
+    };
+    let (path, _) = input_and_config(path.map(PathBuf::from))?;
+
+    let mut text = fs::read_to_string(&path)?;
+
+    let invalid_position = || format!("invalid position '{}', expected LINE:COLUMN", position);
+    let count_from_1 = |n: &str| {
+        n.parse::<u32>()
+            .ok()
+            .and_then(|n| n.checked_sub(1))
+            .ok_or_else(invalid_position)
+    };
+    let (line, col) = position.split_once(':').ok_or_else(invalid_position)?;
+    let line_col = LineCol {
+        line: count_from_1(line)?,
+        col: count_from_1(col)?,
+    };
+    let offset = LineIndex::new(&text)
+        .offset(line_col)
+        .ok_or_else(|| format!("{} is past the end of {}", position, path.display()))?;
+
+    let edits = ide::Analysis::new(&text).rename(offset, new_name)?;
+    for edit in edits.iter().rev() {
+        edit.apply(&mut text);
+    }
+    fs::write(path, text)?;
+
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Target {
+    Wat,
+    C,
+}
+
+impl Target {
+    fn extension(self) -> &'static str {
+        match self {
+            Self::Wat => "wat",
+            Self::C => "c",
+        }
+    }
+}
+
+impl FromStr for Target {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "wat" => Ok(Self::Wat),
+            "c" => Ok(Self::C),
+            _ => Err(format!("unknown target '{}', expected 'wat' or 'c'", s)),
+        }
+    }
+}
+
+fn repl(mut session: Session) -> Result<(), Box<dyn Error>> {
     let stdin = io::stdin();
     let mut stdout = io::stdout();
 
@@ -11,11 +468,70 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         write!(stdout, ">>> ")?;
         stdout.flush()?;
 
-        stdin.read_line(&mut input)?;
+        if stdin.read_line(&mut input)? == 0 {
+            return Ok(());
+        }
 
-        let parse = parse(&input);
-        println!("{}", parse.debug_tree());
+        match session.run(Path::new(REPL_PATH), &input) {
+            Ok(value) => print_value(value),
+            Err(e) => {
+                // Point out what failed to parse by echoing the line with errors highlighted.
+                let analysis = ide::Analysis::new(&input);
+                if !analysis.diagnostics().is_empty() && io::stderr().is_terminal() {
+                    eprint!("{}", ide::render_ansi(&input, &analysis.highlight()));
+                }
+                eprintln!("{}", e)
+            }
+        }
 
         input.clear()
     }
 }
+
+/// The file to work on, which is the entry file from the config if none was given, along with
+/// the config that applies to it.
+fn input_and_config(path: Option<PathBuf>) -> Result<(PathBuf, Config), Box<dyn Error>> {
+    match path {
+        Some(path) => {
+            let config = Config::for_file(&path)?;
+            Ok((path, config))
+        }
+        None => {
+            let mut config = Config::for_current_dir()?;
+            let entry = config.entry.take().ok_or(USAGE)?;
+            Ok((entry, config))
+        }
+    }
+}
+
+/// Like [`input_and_config`], but for commands that take several files.
+fn inputs_and_configs(paths: Vec<PathBuf>) -> Result<Vec<(PathBuf, Config)>, Box<dyn Error>> {
+    if paths.is_empty() {
+        return Ok(vec![input_and_config(None)?]);
+    }
+
+    paths
+        .into_iter()
+        .map(|path| input_and_config(Some(path)))
+        .collect()
+}
+
+fn is_compiled(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext == COMPILED_EXTENSION)
+}
+
+fn load(path: &Path) -> Result<vm::Executable, Box<dyn Error>> {
+    Ok(vm::Executable::from_bytes(&fs::read(path)?)?)
+}
+
+fn print_value(value: Value) {
+    if value != Value::Unit {
+        println!("{}", value);
+    }
+}
+
+fn fail(message: impl std::fmt::Display) -> ! {
+    eprintln!("{}", message);
+    process::exit(1)
+}
@@ -0,0 +1,115 @@
+use ast::AstNode;
+use eval::{Interpreter, Limits, Value};
+use modules::Loader;
+use std::error::Error;
+use std::path::Path;
+use std::str::FromStr;
+use syntax::LineIndex;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Backend {
+    Tree,
+    Vm,
+}
+
+impl FromStr for Backend {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "tree" => Ok(Self::Tree),
+            "vm" => Ok(Self::Vm),
+            _ => Err(format!("unknown backend '{}', expected 'vm' or 'tree'", s)),
+        }
+    }
+}
+
+/// Evaluation state that persists across inputs, so that REPL lines can refer to the
+/// globals and functions defined, and the modules imported, by earlier ones.
+pub(crate) struct Session {
+    loader: Loader,
+    state: State,
+}
+
+enum State {
+    Tree(Interpreter),
+    Vm { module: vm::Module, vm: vm::Vm },
+}
+
+impl Session {
+    /// A session that bounds each run by `limits`.
+    pub(crate) fn new(backend: Backend, limits: Limits) -> Self {
+        let state = match backend {
+            Backend::Tree => {
+                let mut interpreter = Interpreter::new();
+                interpreter.set_limits(limits);
+                State::Tree(interpreter)
+            }
+            Backend::Vm => {
+                let mut vm = vm::Vm::new();
+                vm.set_limits(limits);
+                State::Vm {
+                    module: vm::Module::new(),
+                    vm,
+                }
+            }
+        };
+
+        Self {
+            loader: Loader::new(),
+            state,
+        }
+    }
+
+    /// Runs `input`, which is the text of the file at `path`. Imports are found relative to
+    /// the directory `path` is in.
+    pub(crate) fn run(&mut self, path: &Path, input: &str) -> Result<Value, Box<dyn Error>> {
+        let (program, line_index) = lower_with(&mut self.loader, path, input)?;
+
+        let value = match &mut self.state {
+            State::Tree(interpreter) => interpreter.run(&program)?,
+            State::Vm { module, vm } => {
+                let main = vm::compile(module, &program, &line_index)?;
+                vm.run(module, &main)?
+            }
+        };
+
+        Ok(value)
+    }
+}
+
+/// Parses and lowers `input`, the text of the file at `path`, links in the modules it imports
+/// and optimizes the result, failing with every parse error if there are any.
+pub(crate) fn lower(path: &Path, input: &str) -> Result<hir::Program, Box<dyn Error>> {
+    Ok(lower_with(&mut Loader::new(), path, input)?.0)
+}
+
+/// Like [`lower`], but also returns the line index for the linked program.
+fn lower_with(
+    loader: &mut Loader,
+    path: &Path,
+    input: &str,
+) -> Result<(hir::Program, LineIndex), Box<dyn Error>> {
+    let parse = parser::parse(input);
+
+    if !parse.errors().is_empty() {
+        let errors: Vec<_> = parse.errors().iter().map(ToString::to_string).collect();
+        return Err(errors.join("\n").into());
+    }
+
+    let program = hir::lower(ast::Root::cast(parse.syntax()).unwrap());
+    let mut line_index = LineIndex::new(input);
+    let program = loader.link(&program, path, &mut line_index)?;
+
+    Ok((opt::optimize(&program)?, line_index))
+}
+
+/// Compiles `input`, the text of the file at `path`, into a standalone executable for the VM.
+pub(crate) fn compile(path: &Path, input: &str) -> Result<vm::Executable, Box<dyn Error>> {
+    let (program, line_index) = lower_with(&mut Loader::new(), path, input)?;
+
+    let mut module = vm::Module::new();
+    let main = vm::compile(&mut module, &program, &line_index)?;
+
+    Ok(vm::Executable { module, main })
+}
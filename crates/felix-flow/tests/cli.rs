@@ -0,0 +1,244 @@
+use expect_test::{expect, Expect};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::{env, fs, process};
+
+/// Writes `input` to `main.ff` in a fresh directory named `name`, so that no felix-flow.toml
+/// applies to it.
+fn write_input(name: &str, input: &str) -> PathBuf {
+    write_files(name, &[("main.ff", input)])
+}
+
+/// Like [`write_input`], but writes every file in `files`, returning the path of the first.
+fn write_files(name: &str, files: &[(&str, &str)]) -> PathBuf {
+    let dir = env::temp_dir()
+        .join(format!("felix-flow-cli-{}", process::id()))
+        .join(name);
+    fs::create_dir_all(&dir).unwrap();
+
+    for (file, text) in files {
+        fs::write(dir.join(file), text).unwrap();
+    }
+    dir.join(files[0].0)
+}
+
+/// Runs felix-flow on `path` with `args` before it, checking the exit code and what it printed
+/// to stderr. Crashes like stack overflows don't exit with a code at all.
+fn check(args: &[&str], path: &PathBuf, code: i32, expected_stderr: Expect) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_felix-flow"))
+        .args(args)
+        .arg(path)
+        .output()
+        .unwrap();
+
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert_eq!(output.status.code(), Some(code), "{:?}: {}", args, stderr);
+    expected_stderr.assert_eq(&stderr);
+
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn reject_long_operator_chains() {
+    let path = write_input("chain", &format!("1{}\n", " + 1".repeat(100_000)));
+
+    check(
+        &[],
+        &path,
+        1,
+        expect![[r#"
+            error at 1030..1031: nesting too deep
+        "#]],
+    );
+    check(
+        &["--backend=vm"],
+        &path,
+        1,
+        expect![[r#"
+            error at 1030..1031: nesting too deep
+        "#]],
+    );
+    check(&["lint"], &path, 0, expect![""]);
+
+    let sexp = check(&["parse"], &path, 0, expect![""]);
+    assert!(sexp.ends_with("(error 1030..1031 \"nesting too deep\")\n"));
+}
+
+#[test]
+fn show_lines_of_imported_code_in_their_own_files() {
+    let path = write_files(
+        "import",
+        &[
+            ("main.ff", "import \"m.ff\" as m\n\n\n\nm.f(1)"),
+            ("m.ff", "fn f(x) {\n    x\n}\nlet y = 2"),
+        ],
+    );
+
+    let disasm = check(&["disasm"], &path, 0, expect![""]);
+    expect![[r#"
+        constants: 0=2 1=1
+        globals: 0=m_y
+
+        fn m_f/1 (1 locals)
+        0000    2 GetLocal 0
+        0003    1 Return
+
+        fn <main>/0 (0 locals)
+        0000    4 Constant 0 (2)
+        0003    | SetGlobal 0 (m_y)
+        0006    5 Constant 1 (1)
+        0009    | Call 0 (m_f) 1
+        0013    | Return
+    "#]]
+    .assert_eq(&disasm);
+}
+
+/// Runs the REPL in the directory `path` is in, feeding it `input`, and returns what it printed
+/// to stdout and stderr.
+fn run_repl(args: &[&str], path: &Path, input: &str) -> (String, String) {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_felix-flow"))
+        .args(args)
+        .current_dir(path.parent().unwrap())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .unwrap();
+
+    let output = child.wait_with_output().unwrap();
+    assert_eq!(output.status.code(), Some(0));
+    (
+        String::from_utf8(output.stdout).unwrap(),
+        String::from_utf8(output.stderr).unwrap(),
+    )
+}
+
+#[test]
+fn stop_infinite_recursion() {
+    let path = write_input("recursion", "fn f(n) { f(n) }\nf(0)\n");
+
+    for backend in ["--backend=tree", "--backend=vm"] {
+        check(
+            &[backend],
+            &path,
+            1,
+            expect![[r#"
+                recursion depth limit exceeded
+            "#]],
+        );
+    }
+    let deep = write_input(
+        "deep-recursion",
+        &format!("fn f(x) {{ {}f(x) }}\nf(1)\n", "-".repeat(250)),
+    );
+    for backend in ["--backend=tree", "--backend=vm"] {
+        check(
+            &[backend],
+            &deep,
+            1,
+            expect![[r#"
+                recursion depth limit exceeded
+            "#]],
+        );
+    }
+    check(
+        &["--max-depth=x"],
+        &path,
+        1,
+        expect![[r#"
+            invalid value 'x' for --max-depth
+        "#]],
+    );
+
+    let (stdout, stderr) = run_repl(
+        &["--max-depth=10"],
+        &path,
+        "fn f(n) { f(n) }\nfn g(n) { n }\nf(0)\ng(1)\n",
+    );
+    expect![">>> >>> >>> >>> 1\n>>> "].assert_eq(&stdout);
+    expect![[r#"
+        recursion depth limit exceeded
+    "#]]
+    .assert_eq(&stderr);
+}
+
+#[test]
+fn stop_runaway_computations() {
+    let mut input = "fn f0() { 1 }\n".to_string();
+    for i in 1..=60 {
+        input += &format!("fn f{}() {{ f{}() + f{}() }}\n", i, i - 1, i - 1);
+    }
+    input += "f60()\n";
+    let path = write_input("runaway", &input);
+
+    for backend in ["--backend=tree", "--backend=vm"] {
+        check(
+            &[backend, "--fuel=100000"],
+            &path,
+            1,
+            expect![[r#"
+                fuel limit exceeded
+            "#]],
+        );
+        check(
+            &[backend, "--timeout=50"],
+            &path,
+            1,
+            expect![[r#"
+                time limit exceeded
+            "#]],
+        );
+    }
+
+    let (stdout, stderr) = run_repl(&["--fuel=100000"], &path, &input);
+    assert!(stdout.ends_with(">>> >>> "), "{}", stdout);
+    expect![[r#"
+        fuel limit exceeded
+    "#]]
+    .assert_eq(&stderr);
+}
+
+#[test]
+fn rename_in_the_entry_file() {
+    let path = write_files(
+        "rename",
+        &[
+            ("main.ff", "let a = 1\na + a\n"),
+            ("felix-flow.toml", "entry = \"main.ff\"\n"),
+        ],
+    );
+    let dir = path.parent().unwrap();
+    let rename = |args: &[&str]| {
+        let output = Command::new(env!("CARGO_BIN_EXE_felix-flow"))
+            .arg("rename")
+            .args(args)
+            .current_dir(dir)
+            .output()
+            .unwrap();
+        (
+            output.status.code(),
+            String::from_utf8(output.stderr).unwrap(),
+        )
+    };
+
+    assert_eq!(rename(&["2:1", "b"]), (Some(0), String::new()));
+    assert_eq!(fs::read_to_string(&path).unwrap(), "let b = 1\nb + b\n");
+
+    for position in ["a:3", "2:x", "0:1", "2"] {
+        let expected = format!("invalid position '{}', expected LINE:COLUMN\n", position);
+        assert_eq!(rename(&[position, "c"]), (Some(1), expected));
+    }
+
+    fs::write(dir.join("felix-flow.toml"), "entry = 1\n").unwrap();
+    let (code, stderr) = rename(&["main.ff", "2:1", "c"]);
+    assert_eq!(code, Some(1));
+    assert!(stderr.contains("'entry' must be a string"), "{}", stderr);
+    assert_eq!(fs::read_to_string(&path).unwrap(), "let b = 1\nb + b\n");
+}
@@ -0,0 +1,504 @@
+mod parens;
+
+pub use parens::is_redundant;
+
+use syntax::{SyntaxKind, SyntaxNode, SyntaxToken};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FormatOptions {
+    /// The column that lines are wrapped before where possible.
+    pub width: usize,
+    /// The number of spaces per level of indentation.
+    pub indent: usize,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        Self {
+            width: 100,
+            indent: 4,
+        }
+    }
+}
+
+/// Prints the tree rooted at `root` in the canonical style, with the default options.
+pub fn format(root: &SyntaxNode) -> String {
+    format_with(root, &FormatOptions::default())
+}
+
+/// Prints the tree rooted at `root` in the canonical style.
+///
+/// Every statement goes on its own line, blocks are indented, and infix operators and `=` are
+/// surrounded by single spaces. Lines that would be too wide are broken after an operator, `=`
+/// or `,`, with the rest of the statement indented one level further. At most one blank line is kept between
+/// statements. Comments stay where they are, either at the end of a line or on a line of their
+/// own. Parentheses are removed wherever the expression parses the same without them.
+///
+/// The tree is printed token by token, so text that failed to parse is kept as well, but it's
+/// only spaced out roughly.
+pub fn format_with(root: &SyntaxNode, options: &FormatOptions) -> String {
+    let mut formatter = Formatter {
+        options: *options,
+        out: String::new(),
+        pending: Sep::None,
+        last: None,
+    };
+
+    let tokens: Vec<_> = root
+        .descendants_with_tokens()
+        .filter_map(|element| element.into_token())
+        .collect();
+
+    for (idx, token) in tokens.iter().enumerate() {
+        let whitespace = idx
+            .checked_sub(1)
+            .map(|prev| &tokens[prev])
+            .filter(|prev| prev.kind() == SyntaxKind::Whitespace)
+            .map_or("", |prev| prev.text());
+
+        match token.kind() {
+            SyntaxKind::Whitespace => {}
+            SyntaxKind::Comment => {
+                let next = tokens[idx..].iter().find(|token| !token.kind().is_trivia());
+                formatter.comment(token, whitespace, next);
+            }
+            _ if parens::is_removed(token) => {
+                formatter.pending = formatter
+                    .pending
+                    .max(formatter.sep_before(token, whitespace));
+            }
+            _ => formatter.token(token, whitespace, &tokens[idx + 1..]),
+        }
+    }
+
+    if !formatter.out.is_empty() {
+        formatter.out.push('\n');
+    }
+
+    formatter.out
+}
+
+/// What goes between two tokens, ordered so that the larger of two requests wins.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Sep {
+    None,
+    Space,
+    Line,
+    BlankLine,
+}
+
+struct Formatter {
+    options: FormatOptions,
+    out: String,
+    /// The separator requested since the last token was printed.
+    pending: Sep,
+    /// The last token printed other than a comment.
+    last: Option<SyntaxToken>,
+}
+
+impl Formatter {
+    fn token(&mut self, token: &SyntaxToken, whitespace: &str, rest: &[SyntaxToken]) {
+        let mut sep = self.pending.max(self.sep_before(token, whitespace));
+        let mut indent = indent_level(token);
+
+        // Words only get separated by the rules above in code that parsed, so make sure that
+        // they don't run together anywhere else.
+        if self.last.as_ref().is_some_and(|last| is_word(last.kind())) && is_word(token.kind()) {
+            sep = sep.max(Sep::Space);
+        }
+
+        if sep == Sep::Space
+            && self.last.as_ref().is_some_and(is_break_point)
+            && self.column() + 1 + chunk_width(token, rest) > self.options.width
+        {
+            sep = Sep::Line;
+            indent += 1;
+        }
+
+        self.push(sep, indent, token.text());
+        self.pending = sep_after(token);
+        self.last = Some(token.clone());
+    }
+
+    /// Prints a comment either on its own line, indented like the code after it, or at the end
+    /// of the line it was on.
+    fn comment(&mut self, comment: &SyntaxToken, whitespace: &str, next: Option<&SyntaxToken>) {
+        let own_line = self.out.is_empty() || whitespace.contains('\n');
+
+        let sep = if own_line {
+            self.pending.max(self.line_before(whitespace))
+        } else {
+            Sep::Space
+        };
+
+        let indent = next.map_or(0, |next| {
+            indent_level(next) + usize::from(is_block_brace(next, SyntaxKind::RBrace))
+        });
+
+        self.push(sep, indent, comment.text());
+        self.pending = Sep::Line;
+    }
+
+    fn sep_before(&self, token: &SyntaxToken, whitespace: &str) -> Sep {
+        let parent = token.parent().map(|parent| parent.kind());
+
+        if starts_stmt(token) {
+            return self.line_before(whitespace);
+        }
+
+        match token.kind() {
+            SyntaxKind::Plus | SyntaxKind::Minus | SyntaxKind::Star | SyntaxKind::Slash
+                if parent == Some(SyntaxKind::InfixExpr) =>
+            {
+                Sep::Space
+            }
+            SyntaxKind::Equals | SyntaxKind::AsKw => Sep::Space,
+            SyntaxKind::RBrace if is_block_brace(token, SyntaxKind::RBrace) => {
+                let is_empty = token
+                    .parent()
+                    .unwrap()
+                    .children_with_tokens()
+                    .all(|element| {
+                        element.kind().is_trivia()
+                            || matches!(element.kind(), SyntaxKind::LBrace | SyntaxKind::RBrace)
+                    });
+
+                if is_empty {
+                    Sep::Space
+                } else {
+                    Sep::Line
+                }
+            }
+            SyntaxKind::LBrace
+                if is_block_brace(token, SyntaxKind::LBrace)
+                    && token
+                        .parent()
+                        .and_then(|block| block.parent())
+                        .map(|node| node.kind())
+                        == Some(SyntaxKind::FnDef) =>
+            {
+                Sep::Space
+            }
+            _ if parent == Some(SyntaxKind::Error) && !whitespace.is_empty() => Sep::Space,
+            _ => Sep::None,
+        }
+    }
+
+    /// A line break, keeping a blank line from the original text unless it would open or start
+    /// a block.
+    fn line_before(&self, whitespace: &str) -> Sep {
+        if whitespace.matches('\n').count() >= 2 && !self.out.ends_with('{') {
+            Sep::BlankLine
+        } else {
+            Sep::Line
+        }
+    }
+
+    fn push(&mut self, sep: Sep, indent: usize, text: &str) {
+        if !self.out.is_empty() {
+            match sep {
+                Sep::None => {}
+                Sep::Space => self.out.push(' '),
+                Sep::Line | Sep::BlankLine => {
+                    if sep == Sep::BlankLine {
+                        self.out.push('\n');
+                    }
+                    self.out.push('\n');
+                    self.out.push_str(&" ".repeat(indent * self.options.indent));
+                }
+            }
+        }
+
+        self.out.push_str(text);
+    }
+
+    fn column(&self) -> usize {
+        let line_start = self.out.rfind('\n').map_or(0, |idx| idx + 1);
+        self.out[line_start..].chars().count()
+    }
+}
+
+/// Whether a line can be broken after `token`.
+fn is_break_point(token: &SyntaxToken) -> bool {
+    match token.kind() {
+        SyntaxKind::Comma | SyntaxKind::Equals => true,
+        SyntaxKind::Plus | SyntaxKind::Minus | SyntaxKind::Star | SyntaxKind::Slash => {
+            token.parent().map(|parent| parent.kind()) == Some(SyntaxKind::InfixExpr)
+        }
+        _ => false,
+    }
+}
+
+/// Roughly how wide the text from `token` up to the next place a line can be broken is.
+fn chunk_width(token: &SyntaxToken, rest: &[SyntaxToken]) -> usize {
+    let mut width = token.text().len();
+    if is_break_point(token) {
+        return width;
+    }
+
+    let mut prev = token;
+    for next in rest
+        .iter()
+        .filter(|next| next.kind() != SyntaxKind::Whitespace)
+    {
+        if next.kind() == SyntaxKind::Comment
+            || starts_stmt(next)
+            || is_block_brace(next, SyntaxKind::LBrace)
+            || is_block_brace(next, SyntaxKind::RBrace)
+        {
+            break;
+        }
+
+        if parens::is_removed(next) {
+            continue;
+        }
+
+        if sep_after(prev) == Sep::Space || is_break_point(next) {
+            width += 1;
+        }
+        width += next.text().len();
+
+        if is_break_point(next) {
+            break;
+        }
+        prev = next;
+    }
+
+    width
+}
+
+fn sep_after(token: &SyntaxToken) -> Sep {
+    let parent = token.parent().map(|parent| parent.kind());
+
+    match token.kind() {
+        SyntaxKind::LetKw
+        | SyntaxKind::FnKw
+        | SyntaxKind::ImportKw
+        | SyntaxKind::AsKw
+        | SyntaxKind::UseKw
+        | SyntaxKind::Comma
+        | SyntaxKind::Equals => Sep::Space,
+        SyntaxKind::Plus | SyntaxKind::Minus | SyntaxKind::Star | SyntaxKind::Slash
+            if parent == Some(SyntaxKind::InfixExpr) =>
+        {
+            Sep::Space
+        }
+        _ => Sep::None,
+    }
+}
+
+/// Whether `token` is the first token of a statement, in which case it goes on a new line.
+fn starts_stmt(token: &SyntaxToken) -> bool {
+    for node in token.parent_ancestors() {
+        if first_token(&node).as_ref() != Some(token) {
+            return false;
+        }
+
+        if node
+            .parent()
+            .is_some_and(|parent| matches!(parent.kind(), SyntaxKind::Root | SyntaxKind::Block))
+        {
+            return true;
+        }
+    }
+
+    false
+}
+
+fn first_token(node: &SyntaxNode) -> Option<SyntaxToken> {
+    node.descendants_with_tokens()
+        .filter_map(|element| element.into_token())
+        .find(|token| !token.kind().is_trivia())
+}
+
+/// The number of blocks `token` is nested in, not counting the block it delimits.
+fn indent_level(token: &SyntaxToken) -> usize {
+    let blocks = token
+        .parent_ancestors()
+        .filter(|node| node.kind() == SyntaxKind::Block)
+        .count();
+
+    if is_block_brace(token, SyntaxKind::LBrace) || is_block_brace(token, SyntaxKind::RBrace) {
+        blocks - 1
+    } else {
+        blocks
+    }
+}
+
+fn is_block_brace(token: &SyntaxToken, kind: SyntaxKind) -> bool {
+    token.kind() == kind && token.parent().map(|parent| parent.kind()) == Some(SyntaxKind::Block)
+}
+
+fn is_word(kind: SyntaxKind) -> bool {
+    matches!(
+        kind,
+        SyntaxKind::Ident
+            | SyntaxKind::Number
+            | SyntaxKind::String
+            | SyntaxKind::LetKw
+            | SyntaxKind::FnKw
+            | SyntaxKind::ImportKw
+            | SyntaxKind::AsKw
+            | SyntaxKind::UseKw
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use expect_test::{expect, Expect};
+
+    fn check(input: &str, expected: Expect) {
+        let formatted = format(&parser::parse(input).syntax());
+        expected.assert_eq(&formatted);
+
+        assert_eq!(format(&parser::parse(&formatted).syntax()), formatted);
+    }
+
+    #[test]
+    fn space_operators_and_definitions() {
+        check(
+            "let   a=1+2*  -3\nfn add( x ,y ){x+y}\nadd(a,4)",
+            expect![[r#"
+                let a = 1 + 2 * -3
+                fn add(x, y) {
+                    x + y
+                }
+                add(a, 4)
+            "#]],
+        );
+    }
+
+    #[test]
+    fn space_imports_and_qualified_names() {
+        check(
+            "import   \"lib/m.ff\"as   m\nuse m . f\nf( m .x)",
+            expect![[r#"
+                import "lib/m.ff" as m
+                use m.f
+                f(m.x)
+            "#]],
+        );
+    }
+
+    #[test]
+    fn indent_nested_blocks() {
+        check(
+            "let a = { let b = {1} {} b }",
+            expect![[r#"
+                let a = {
+                    let b = {
+                        1
+                    }
+                    { }
+                    b
+                }
+            "#]],
+        );
+    }
+
+    #[test]
+    fn wrap_long_lines_and_indent_with_options() {
+        let options = FormatOptions {
+            width: 24,
+            indent: 2,
+        };
+        let check = |input: &str, expected: Expect| {
+            let formatted = format_with(&parser::parse(input).syntax(), &options);
+            expected.assert_eq(&formatted);
+
+            assert_eq!(
+                format_with(&parser::parse(&formatted).syntax(), &options),
+                formatted
+            );
+        };
+
+        check(
+            "fn f(a) { let total = alpha + beta * gamma - delta(one, two, three) total }",
+            expect![[r#"
+                fn f(a) {
+                  let total = alpha +
+                    beta * gamma -
+                    delta(one, two,
+                    three)
+                  total
+                }
+            "#]],
+        );
+    }
+
+    #[test]
+    fn keep_single_blank_lines() {
+        check(
+            "\n\nlet a = 1\n\n\n\nlet b = 2\n{\n\n  a\n\n  b\n\n}",
+            expect![[r#"
+                let a = 1
+
+                let b = 2
+                {
+                    a
+
+                    b
+                }
+            "#]],
+        );
+    }
+
+    #[test]
+    fn keep_comments_in_place() {
+        check(
+            "# header\n\nlet a = 1 # one\n{ # open\n   # inside\n  a\n      # before close\n}\n# end",
+            expect![[r#"
+                # header
+
+                let a = 1 # one
+                { # open
+                    # inside
+                    a
+                    # before close
+                }
+                # end
+            "#]],
+        );
+    }
+
+    #[test]
+    fn remove_redundant_parens() {
+        check(
+            "let a = (1 + 2)\n(a - 1) - (2 * a)\n(a * 2) * (a / 2)\nlet b = -(-a)\nf((a), (a + 1))\n((a))",
+            expect![[r#"
+                let a = 1 + 2
+                a - 1 - 2 * a
+                a * 2 * (a / 2)
+                let b = --a
+                f(a, a + 1)
+                a
+            "#]],
+        );
+    }
+
+    #[test]
+    fn keep_parens_that_matter() {
+        check(
+            "(a + 1) * 2\na - (b - c)\nlet d = -(a + 1)\nfn f() {}\n(-1)\n(# why\n1)",
+            expect![[r#"
+                (a + 1) * 2
+                a - (b - c)
+                let d = -(a + 1)
+                fn f() { }
+                (-1)
+                ( # why
+                1)
+            "#]],
+        );
+        check(
+            "let a = (b)\n(-c)\n(d)\n(e)",
+            expect![[r#"
+                let a = (b)
+                (-c)
+                d
+                e
+            "#]],
+        );
+    }
+}
@@ -0,0 +1,108 @@
+use syntax::{SyntaxKind, SyntaxNode, SyntaxToken};
+
+/// Whether `token` is a parenthesis of a `ParenExpr` that isn't needed.
+pub(crate) fn is_removed(token: &SyntaxToken) -> bool {
+    matches!(token.kind(), SyntaxKind::LParen | SyntaxKind::RParen)
+        && token
+            .parent()
+            .is_some_and(|parent| parent.kind() == SyntaxKind::ParenExpr && is_redundant(&parent))
+}
+
+/// Whether the parentheses of `paren_expr` can be removed without changing how the code parses.
+pub fn is_redundant(paren_expr: &SyntaxNode) -> bool {
+    // Both parentheses have to be there, and comments inside them are left alone rather than
+    // moved around.
+    let has_rparen = paren_expr
+        .children_with_tokens()
+        .any(|element| element.kind() == SyntaxKind::RParen);
+    let has_comments = paren_expr
+        .descendants_with_tokens()
+        .any(|element| element.kind() == SyntaxKind::Comment);
+
+    let Some(inner) = paren_expr.children().next() else {
+        return false;
+    };
+
+    if !has_rparen || has_comments || inner.kind() == SyntaxKind::Error {
+        return false;
+    }
+
+    // Without the parentheses, whatever comes before a `(` that follows them would be called.
+    let next = paren_expr
+        .last_token()
+        .and_then(|token| token.next_token())
+        .into_iter()
+        .flat_map(|token| std::iter::successors(Some(token), |token| token.next_token()))
+        .find(|token| !token.kind().is_trivia());
+    if next.is_some_and(|next| next.kind() == SyntaxKind::LParen && !is_removed(&next)) {
+        return false;
+    }
+
+    if matches!(
+        inner.kind(),
+        SyntaxKind::Literal
+            | SyntaxKind::VariableRef
+            | SyntaxKind::CallExpr
+            | SyntaxKind::ParenExpr
+            | SyntaxKind::Block
+    ) {
+        return true;
+    }
+
+    // Parentheses around these parentheses go away too, so it's their context that matters.
+    let mut node = paren_expr.clone();
+    let parent = loop {
+        let Some(parent) = node.parent() else {
+            return false;
+        };
+
+        if parent.kind() == SyntaxKind::ParenExpr && is_redundant(&parent) {
+            node = parent;
+        } else {
+            break parent;
+        }
+    };
+
+    match parent.kind() {
+        // A statement can't start with `-`, as it would continue the statement before it.
+        SyntaxKind::Root | SyntaxKind::Block => {
+            first_printed_token(&inner).is_some_and(|token| token.kind() != SyntaxKind::Minus)
+        }
+        SyntaxKind::ParenExpr | SyntaxKind::VariableDef | SyntaxKind::ArgList => true,
+        SyntaxKind::PrefixExpr => inner.kind() == SyntaxKind::PrefixExpr,
+        SyntaxKind::InfixExpr => match inner.kind() {
+            SyntaxKind::PrefixExpr => true,
+            SyntaxKind::InfixExpr => {
+                let (Some(inner_power), Some(outer_power)) =
+                    (binding_power(&inner), binding_power(&parent))
+                else {
+                    return false;
+                };
+                let is_lhs = parent.children().next().as_ref() == Some(&node);
+
+                inner_power > outer_power || (inner_power == outer_power && is_lhs)
+            }
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+fn first_printed_token(node: &SyntaxNode) -> Option<SyntaxToken> {
+    node.descendants_with_tokens()
+        .filter_map(|element| element.into_token())
+        .find(|token| !token.kind().is_trivia() && !is_removed(token))
+}
+
+/// How tightly the operator of an infix expression binds.
+fn binding_power(infix_expr: &SyntaxNode) -> Option<u8> {
+    let op = infix_expr
+        .children_with_tokens()
+        .find(|element| !element.kind().is_trivia() && element.as_token().is_some())?;
+
+    match op.kind() {
+        SyntaxKind::Plus | SyntaxKind::Minus => Some(1),
+        SyntaxKind::Star | SyntaxKind::Slash => Some(3),
+        _ => None,
+    }
+}
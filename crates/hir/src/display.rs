@@ -0,0 +1,200 @@
+use crate::{BinaryOp, Expr, FnDef, Program, Stmt, StmtKind, UnaryOp};
+use std::fmt::{self, Display, Write};
+
+/// Prints a program back as felix-flow source, with function definitions first and blocks
+/// spread over several lines. Parentheses are only added where precedence requires them.
+impl Display for Program {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut printer = Printer { f, indent: 0 };
+
+        for import in &self.imports {
+            writeln!(printer.f, "import \"{}\" as {}", import.path, import.alias)?;
+        }
+
+        for use_ in &self.uses {
+            writeln!(printer.f, "use {}.{}", use_.module, use_.name)?;
+        }
+
+        for fn_def in &self.fns {
+            printer.fn_def(fn_def)?;
+        }
+
+        for stmt in &self.stmts {
+            printer.stmt(stmt)?;
+        }
+
+        Ok(())
+    }
+}
+
+struct Printer<'a, 'b> {
+    f: &'a mut fmt::Formatter<'b>,
+    indent: usize,
+}
+
+impl Printer<'_, '_> {
+    fn fn_def(&mut self, fn_def: &FnDef) -> fmt::Result {
+        self.start_line()?;
+        write!(self.f, "fn {}({}) ", fn_def.name, fn_def.params.join(", "))?;
+        self.expr(&fn_def.body, 0)?;
+        self.f.write_char('\n')
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) -> fmt::Result {
+        self.start_line()?;
+
+        match &stmt.kind {
+            StmtKind::VariableDef { name, value } => {
+                write!(self.f, "let {} = ", name)?;
+                self.expr(value, 0)?;
+            }
+            StmtKind::Expr(expr) => self.expr(expr, 0)?,
+        }
+
+        self.f.write_char('\n')
+    }
+
+    /// Prints `expr`, parenthesizing it if it binds less tightly than `min_binding_power`.
+    fn expr(&mut self, expr: &Expr, min_binding_power: u8) -> fmt::Result {
+        match expr {
+            Expr::Missing => self.f.write_str("<missing>"),
+            Expr::Binary { op, lhs, rhs } => {
+                let (left_binding_power, right_binding_power) = match op {
+                    BinaryOp::Add | BinaryOp::Sub => (1, 2),
+                    BinaryOp::Mul | BinaryOp::Div => (3, 4),
+                };
+                let needs_parens = left_binding_power < min_binding_power;
+
+                if needs_parens {
+                    self.f.write_char('(')?;
+                }
+
+                self.expr(lhs, left_binding_power)?;
+                write!(self.f, " {} ", op)?;
+                self.expr(rhs, right_binding_power)?;
+
+                if needs_parens {
+                    self.f.write_char(')')?;
+                }
+
+                Ok(())
+            }
+            Expr::Literal { n } => write!(self.f, "{}", n),
+            Expr::Unary { op, expr } => {
+                write!(self.f, "{}", op)?;
+                self.expr(expr, 5)
+            }
+            Expr::VariableRef { var } => self.f.write_str(var),
+            Expr::Block { stmts } if stmts.is_empty() => self.f.write_str("{ }"),
+            Expr::Block { stmts } => {
+                self.f.write_str("{\n")?;
+
+                self.indent += 1;
+                for stmt in stmts {
+                    self.stmt(stmt)?;
+                }
+                self.indent -= 1;
+
+                self.start_line()?;
+                self.f.write_char('}')
+            }
+            Expr::Call { callee, args } => {
+                write!(self.f, "{}(", callee)?;
+
+                for (idx, arg) in args.iter().enumerate() {
+                    if idx != 0 {
+                        self.f.write_str(", ")?;
+                    }
+                    self.expr(arg, 0)?;
+                }
+
+                self.f.write_char(')')
+            }
+        }
+    }
+
+    fn start_line(&mut self) -> fmt::Result {
+        write!(self.f, "{:indent$}", "", indent = self.indent * 4)
+    }
+}
+
+impl Display for BinaryOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Add => "+",
+            Self::Sub => "-",
+            Self::Mul => "*",
+            Self::Div => "/",
+        })
+    }
+}
+
+impl Display for UnaryOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Neg => "-",
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lower;
+    use ast::AstNode;
+    use expect_test::{expect, Expect};
+
+    fn check(input: &str, expected: Expect) {
+        let parse = parser::parse(input);
+        let program = lower(ast::Root::cast(parse.syntax()).unwrap());
+
+        expected.assert_eq(&program.to_string());
+    }
+
+    #[test]
+    fn print_statements() {
+        check(
+            "let a = 1\n  a   +2",
+            expect![[r#"
+                let a = 1
+                a + 2
+            "#]],
+        );
+    }
+
+    #[test]
+    fn print_only_necessary_parentheses() {
+        check(
+            "((1 + 2)) * (3 - (4 - 5)) + (6 / 2) - -(7 * 8) + --x",
+            expect![[r#"
+                (1 + 2) * (3 - (4 - 5)) + 6 / 2 - -(7 * 8) + --x
+            "#]],
+        );
+    }
+
+    #[test]
+    fn print_imports_and_uses_first() {
+        check(
+            "m.f(m.x)\nuse m.f\nimport \"m.ff\" as m",
+            expect![[r#"
+            import "m.ff" as m
+            use m.f
+            m.f(m.x)
+        "#]],
+        );
+    }
+
+    #[test]
+    fn print_functions_first() {
+        check(
+            "f(1, 2)\nfn f(x, y) { let z = x { } z + y }",
+            expect![[r#"
+                fn f(x, y) {
+                    let z = x
+                    { }
+                    z + y
+                }
+                f(1, 2)
+            "#]],
+        );
+    }
+}
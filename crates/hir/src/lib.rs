@@ -0,0 +1,97 @@
+mod display;
+mod lower;
+mod scope;
+
+pub use lower::lower;
+pub use scope::ModuleScope;
+
+use text_size::TextRange;
+
+/// A lowered program. Imports, uses and function definitions are hoisted out of the statement
+/// list, so every function is visible from every statement regardless of where it was written.
+///
+/// Names from imported modules are kept qualified, like `m.value`, until the modules are linked
+/// in.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Program {
+    pub imports: Vec<Import>,
+    pub uses: Vec<Use>,
+    pub fns: Vec<FnDef>,
+    pub stmts: Vec<Stmt>,
+}
+
+/// `import "path" as alias`, with the path as written.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Import {
+    pub path: String,
+    pub alias: String,
+    pub range: TextRange,
+}
+
+/// `use module.name`, which lets `name` stand for `module.name`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Use {
+    pub module: String,
+    pub name: String,
+    pub range: TextRange,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FnDef {
+    pub name: String,
+    pub params: Vec<String>,
+    pub body: Expr,
+    pub range: TextRange,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Stmt {
+    pub kind: StmtKind,
+    pub range: TextRange,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StmtKind {
+    VariableDef { name: String, value: Expr },
+    Expr(Expr),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Expr {
+    Missing,
+    Binary {
+        op: BinaryOp,
+        lhs: Box<Self>,
+        rhs: Box<Self>,
+    },
+    Literal {
+        n: u64,
+    },
+    Unary {
+        op: UnaryOp,
+        expr: Box<Self>,
+    },
+    VariableRef {
+        var: String,
+    },
+    Block {
+        stmts: Vec<Stmt>,
+    },
+    Call {
+        callee: String,
+        args: Vec<Self>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnaryOp {
+    Neg,
+}
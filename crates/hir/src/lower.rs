@@ -0,0 +1,457 @@
+use crate::{BinaryOp, Expr, FnDef, Import, Program, Stmt, StmtKind, UnaryOp, Use};
+use ast::AstNode;
+use syntax::{SyntaxKind, SyntaxToken};
+
+pub fn lower(ast: ast::Root) -> Program {
+    let mut ctx = LowerCtx::default();
+    let stmts = ast
+        .stmts()
+        .filter_map(|stmt| ctx.lower_stmt(stmt))
+        .collect();
+
+    Program {
+        imports: ctx.imports,
+        uses: ctx.uses,
+        fns: ctx.fns,
+        stmts,
+    }
+}
+
+#[derive(Default)]
+struct LowerCtx {
+    imports: Vec<Import>,
+    uses: Vec<Use>,
+    fns: Vec<FnDef>,
+}
+
+impl LowerCtx {
+    fn lower_stmt(&mut self, ast: ast::Stmt) -> Option<Stmt> {
+        let range = ast.syntax().text_range();
+
+        let kind = match ast {
+            ast::Stmt::Import(ast) => {
+                self.imports.push(Import {
+                    path: ast.path_text()?,
+                    alias: ast.alias()?.text().to_string(),
+                    range,
+                });
+
+                return None;
+            }
+            ast::Stmt::Use(ast) => {
+                self.uses.push(Use {
+                    module: ast.module()?.text().to_string(),
+                    name: ast.name()?.text().to_string(),
+                    range,
+                });
+
+                return None;
+            }
+            ast::Stmt::VariableDef(ast) => StmtKind::VariableDef {
+                name: ast.name()?.text().to_string(),
+                value: self.lower_expr(ast.value()),
+            },
+            ast::Stmt::FnDef(ast) => {
+                let fn_def = self.lower_fn_def(ast)?;
+                self.fns.push(fn_def);
+
+                return None;
+            }
+            ast::Stmt::Expr(ast) => StmtKind::Expr(self.lower_expr(Some(ast))),
+        };
+
+        Some(Stmt { kind, range })
+    }
+
+    fn lower_fn_def(&mut self, ast: ast::FnDef) -> Option<FnDef> {
+        let name = ast.name()?.text().to_string();
+
+        let params = ast
+            .param_list()
+            .into_iter()
+            .flat_map(|param_list| param_list.params())
+            .filter_map(|param| Some(param.name()?.text().to_string()))
+            .collect();
+
+        let body = match ast.body() {
+            Some(body) => self.lower_block(body),
+            None => Expr::Missing,
+        };
+
+        Some(FnDef {
+            name,
+            params,
+            body,
+            range: ast.syntax().text_range(),
+        })
+    }
+
+    fn lower_expr(&mut self, ast: Option<ast::Expr>) -> Expr {
+        let Some(ast) = ast else {
+            return Expr::Missing;
+        };
+
+        match ast {
+            ast::Expr::InfixExpr(ast) => self.lower_infix(ast),
+            ast::Expr::Literal(ast) => match ast.parse() {
+                Some(n) => Expr::Literal { n },
+                None => Expr::Missing,
+            },
+            ast::Expr::ParenExpr(ast) => self.lower_expr(ast.expr()),
+            ast::Expr::PrefixExpr(ast) => self.lower_prefix(ast),
+            ast::Expr::VariableRef(ast) => match qualified_name(ast.qualifier(), ast.name()) {
+                Some(var) => Expr::VariableRef { var },
+                None => Expr::Missing,
+            },
+            ast::Expr::Block(ast) => self.lower_block(ast),
+            ast::Expr::CallExpr(ast) => self.lower_call(ast),
+        }
+    }
+
+    fn lower_infix(&mut self, ast: ast::InfixExpr) -> Expr {
+        let op = match ast.op().map(|op| op.kind()) {
+            Some(SyntaxKind::Plus) => BinaryOp::Add,
+            Some(SyntaxKind::Minus) => BinaryOp::Sub,
+            Some(SyntaxKind::Star) => BinaryOp::Mul,
+            Some(SyntaxKind::Slash) => BinaryOp::Div,
+            _ => return Expr::Missing,
+        };
+
+        Expr::Binary {
+            op,
+            lhs: Box::new(self.lower_expr(ast.lhs())),
+            rhs: Box::new(self.lower_expr(ast.rhs())),
+        }
+    }
+
+    fn lower_prefix(&mut self, ast: ast::PrefixExpr) -> Expr {
+        let op = match ast.op().map(|op| op.kind()) {
+            Some(SyntaxKind::Minus) => UnaryOp::Neg,
+            _ => return Expr::Missing,
+        };
+
+        Expr::Unary {
+            op,
+            expr: Box::new(self.lower_expr(ast.expr())),
+        }
+    }
+
+    fn lower_block(&mut self, ast: ast::Block) -> Expr {
+        Expr::Block {
+            stmts: ast
+                .stmts()
+                .filter_map(|stmt| self.lower_stmt(stmt))
+                .collect(),
+        }
+    }
+
+    fn lower_call(&mut self, ast: ast::CallExpr) -> Expr {
+        let Some(callee) = qualified_name(ast.qualifier(), ast.name()) else {
+            return Expr::Missing;
+        };
+
+        Expr::Call {
+            callee,
+            args: ast.args().map(|arg| self.lower_expr(Some(arg))).collect(),
+        }
+    }
+}
+
+/// `name`, or `module.name` if it's from an imported module.
+fn qualified_name(module: Option<SyntaxToken>, name: Option<SyntaxToken>) -> Option<String> {
+    let name = name?;
+
+    Some(match module {
+        Some(module) => format!("{}.{}", module.text(), name.text()),
+        None => name.text().to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use expect_test::{expect, Expect};
+
+    fn check(input: &str, expected: Expect) {
+        let parse = parser::parse(input);
+        let root = ast::Root::cast(parse.syntax()).unwrap();
+
+        expected.assert_debug_eq(&lower(root));
+    }
+
+    #[test]
+    fn lower_variable_def() {
+        check(
+            "let foo = bar",
+            expect![[r#"
+                Program {
+                    imports: [],
+                    uses: [],
+                    fns: [],
+                    stmts: [
+                        Stmt {
+                            kind: VariableDef {
+                                name: "foo",
+                                value: VariableRef {
+                                    var: "bar",
+                                },
+                            },
+                            range: 0..13,
+                        },
+                    ],
+                }
+            "#]],
+        );
+    }
+
+    #[test]
+    fn lower_variable_def_without_value() {
+        check(
+            "let a =",
+            expect![[r#"
+                Program {
+                    imports: [],
+                    uses: [],
+                    fns: [],
+                    stmts: [
+                        Stmt {
+                            kind: VariableDef {
+                                name: "a",
+                                value: Missing,
+                            },
+                            range: 0..7,
+                        },
+                    ],
+                }
+            "#]],
+        );
+    }
+
+    #[test]
+    fn lower_variable_def_without_name() {
+        check(
+            "let = 10",
+            expect![[r#"
+                Program {
+                    imports: [],
+                    uses: [],
+                    fns: [],
+                    stmts: [],
+                }
+            "#]],
+        );
+    }
+
+    #[test]
+    fn lower_paren_expr_to_inner_expr() {
+        check(
+            "((-1))",
+            expect![[r#"
+                Program {
+                    imports: [],
+                    uses: [],
+                    fns: [],
+                    stmts: [
+                        Stmt {
+                            kind: Expr(
+                                Unary {
+                                    op: Neg,
+                                    expr: Literal {
+                                        n: 1,
+                                    },
+                                },
+                            ),
+                            range: 0..6,
+                        },
+                    ],
+                }
+            "#]],
+        );
+    }
+
+    #[test]
+    fn lower_binary_expr_with_missing_rhs() {
+        check(
+            "1 +",
+            expect![[r#"
+                Program {
+                    imports: [],
+                    uses: [],
+                    fns: [],
+                    stmts: [
+                        Stmt {
+                            kind: Expr(
+                                Binary {
+                                    op: Add,
+                                    lhs: Literal {
+                                        n: 1,
+                                    },
+                                    rhs: Missing,
+                                },
+                            ),
+                            range: 0..3,
+                        },
+                    ],
+                }
+            "#]],
+        );
+    }
+
+    #[test]
+    fn hoist_fn_defs_out_of_blocks() {
+        check(
+            "{ fn one() { 1 } one() }",
+            expect![[r#"
+                Program {
+                    imports: [],
+                    uses: [],
+                    fns: [
+                        FnDef {
+                            name: "one",
+                            params: [],
+                            body: Block {
+                                stmts: [
+                                    Stmt {
+                                        kind: Expr(
+                                            Literal {
+                                                n: 1,
+                                            },
+                                        ),
+                                        range: 13..15,
+                                    },
+                                ],
+                            },
+                            range: 2..17,
+                        },
+                    ],
+                    stmts: [
+                        Stmt {
+                            kind: Expr(
+                                Block {
+                                    stmts: [
+                                        Stmt {
+                                            kind: Expr(
+                                                Call {
+                                                    callee: "one",
+                                                    args: [],
+                                                },
+                                            ),
+                                            range: 17..23,
+                                        },
+                                    ],
+                                },
+                            ),
+                            range: 0..24,
+                        },
+                    ],
+                }
+            "#]],
+        );
+    }
+
+    #[test]
+    fn hoist_imports_and_uses_and_keep_names_qualified() {
+        check(
+            "import \"lib/math.ff\" as math\nuse math.square\nmath.pi + math.square(2)",
+            expect![[r#"
+                Program {
+                    imports: [
+                        Import {
+                            path: "lib/math.ff",
+                            alias: "math",
+                            range: 0..29,
+                        },
+                    ],
+                    uses: [
+                        Use {
+                            module: "math",
+                            name: "square",
+                            range: 29..45,
+                        },
+                    ],
+                    fns: [],
+                    stmts: [
+                        Stmt {
+                            kind: Expr(
+                                Binary {
+                                    op: Add,
+                                    lhs: VariableRef {
+                                        var: "math.pi",
+                                    },
+                                    rhs: Call {
+                                        callee: "math.square",
+                                        args: [
+                                            Literal {
+                                                n: 2,
+                                            },
+                                        ],
+                                    },
+                                },
+                            ),
+                            range: 45..69,
+                        },
+                    ],
+                }
+            "#]],
+        );
+    }
+
+    #[test]
+    fn lower_call_with_args() {
+        check(
+            "fn add(x, y) { x + y }\nadd(1, 2)",
+            expect![[r#"
+                Program {
+                    imports: [],
+                    uses: [],
+                    fns: [
+                        FnDef {
+                            name: "add",
+                            params: [
+                                "x",
+                                "y",
+                            ],
+                            body: Block {
+                                stmts: [
+                                    Stmt {
+                                        kind: Expr(
+                                            Binary {
+                                                op: Add,
+                                                lhs: VariableRef {
+                                                    var: "x",
+                                                },
+                                                rhs: VariableRef {
+                                                    var: "y",
+                                                },
+                                            },
+                                        ),
+                                        range: 15..21,
+                                    },
+                                ],
+                            },
+                            range: 0..23,
+                        },
+                    ],
+                    stmts: [
+                        Stmt {
+                            kind: Expr(
+                                Call {
+                                    callee: "add",
+                                    args: [
+                                        Literal {
+                                            n: 1,
+                                        },
+                                        Literal {
+                                            n: 2,
+                                        },
+                                    ],
+                                },
+                            ),
+                            range: 23..32,
+                        },
+                    ],
+                }
+            "#]],
+        );
+    }
+}
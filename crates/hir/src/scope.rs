@@ -0,0 +1,76 @@
+use crate::{FnDef, Program, StmtKind};
+use std::collections::HashMap;
+
+/// The names a compiled program can refer to at the top level, for backends that lay out every
+/// global and function up front.
+#[derive(Debug)]
+pub struct ModuleScope<'a> {
+    /// Every top-level variable, in order of its first definition.
+    pub globals: Vec<&'a str>,
+    /// Every function, in order of its first definition. Like the interpreter, a later definition
+    /// of a function replaces an earlier one.
+    pub fns: Vec<&'a FnDef>,
+    pub arities: HashMap<&'a str, usize>,
+}
+
+impl<'a> ModuleScope<'a> {
+    pub fn new(program: &'a Program) -> Self {
+        let mut globals = Vec::new();
+        for stmt in &program.stmts {
+            if let StmtKind::VariableDef { name, .. } = &stmt.kind {
+                if !globals.contains(&name.as_str()) {
+                    globals.push(name.as_str());
+                }
+            }
+        }
+
+        let mut fns: Vec<&FnDef> = Vec::new();
+        for fn_def in &program.fns {
+            match fns.iter_mut().find(|existing| existing.name == fn_def.name) {
+                Some(existing) => *existing = fn_def,
+                None => fns.push(fn_def),
+            }
+        }
+
+        let arities = fns
+            .iter()
+            .map(|fn_def| (fn_def.name.as_str(), fn_def.params.len()))
+            .collect();
+
+        Self {
+            globals,
+            fns,
+            arities,
+        }
+    }
+
+    pub fn is_global(&self, name: &str) -> bool {
+        self.globals.contains(&name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ast::AstNode;
+
+    #[test]
+    fn keep_first_position_and_last_definition() {
+        let parse =
+            parser::parse("let a = 1\nfn f(x) { x }\nfn g() { 2 }\nlet a = 3\nfn f() { 4 }");
+        let root = ast::Root::cast(parse.syntax()).unwrap();
+        let program = crate::lower(root);
+        let scope = ModuleScope::new(&program);
+
+        assert_eq!(scope.globals, ["a"]);
+        assert_eq!(
+            scope
+                .fns
+                .iter()
+                .map(|fn_def| (fn_def.name.as_str(), fn_def.params.len()))
+                .collect::<Vec<_>>(),
+            [("f", 0), ("g", 0)],
+        );
+        assert_eq!(scope.arities["f"], 0);
+    }
+}
@@ -0,0 +1,406 @@
+use crate::Analysis;
+use ast::{AstNode, Stmt};
+use std::collections::HashSet;
+use syntax::{SyntaxKind, SyntaxNode, SyntaxToken};
+use text_size::{TextRange, TextSize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompletionKind {
+    Variable,
+    Function,
+    Module,
+    Keyword,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompletionItem {
+    pub label: String,
+    pub kind: CompletionKind,
+    pub detail: Option<String>,
+    /// The text that replaces `range`, which is a snippet with `$1`-style tab stops if
+    /// `is_snippet` is set.
+    pub insert_text: String,
+    pub is_snippet: bool,
+    pub range: TextRange,
+}
+
+/// What can be typed at the cursor, as far as can be told from the tokens before it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Position {
+    /// A new statement could start here, although an operator could follow the last one too.
+    Stmt,
+    /// Only an expression can come next, like after an operator or `=`.
+    Expr,
+    /// A new name is being defined.
+    Name,
+    /// A name from an imported module, whose contents aren't known here.
+    Member,
+}
+
+/// Suggests the names in scope at `offset`, and keywords where a statement can start, that
+/// begin with the identifier being typed there.
+///
+/// Scoping follows name resolution: code in a function sees its parameters, its own locals and
+/// every global, while top-level code only sees the globals and locals defined before it.
+pub(crate) fn completions(analysis: &Analysis, offset: TextSize) -> Vec<CompletionItem> {
+    let root = analysis.syntax();
+    if !root.text_range().contains_inclusive(offset) {
+        return Vec::new();
+    }
+
+    let Some(left) = root.token_at_offset(offset).left_biased() else {
+        return keywords(TextRange::empty(offset));
+    };
+
+    // The identifier being typed, which could also be the start of a keyword.
+    let typed = matches!(
+        left.kind(),
+        SyntaxKind::Ident
+            | SyntaxKind::LetKw
+            | SyntaxKind::FnKw
+            | SyntaxKind::ImportKw
+            | SyntaxKind::AsKw
+            | SyntaxKind::UseKw
+    ) && left.text_range().end() == offset;
+
+    let (range, before) = if typed {
+        (
+            TextRange::new(left.text_range().start(), offset),
+            left.prev_token(),
+        )
+    } else {
+        (TextRange::empty(offset), Some(left.clone()))
+    };
+    let prefix = &root.text().to_string()[range];
+
+    let before = std::iter::successors(before, |token| token.prev_token())
+        .find(|token| !token.kind().is_trivia());
+
+    let position = match before.as_ref().map(|token| token.kind()) {
+        _ if left
+            .parent_ancestors()
+            .any(|node| node.kind() == SyntaxKind::ParamList) =>
+        {
+            Position::Name
+        }
+        Some(SyntaxKind::LetKw | SyntaxKind::FnKw | SyntaxKind::ImportKw | SyntaxKind::AsKw) => {
+            Position::Name
+        }
+        Some(SyntaxKind::Dot | SyntaxKind::UseKw) => Position::Member,
+        Some(
+            SyntaxKind::Plus
+            | SyntaxKind::Minus
+            | SyntaxKind::Star
+            | SyntaxKind::Slash
+            | SyntaxKind::Equals
+            | SyntaxKind::LParen
+            | SyntaxKind::Comma,
+        ) => Position::Expr,
+        _ => Position::Stmt,
+    };
+
+    if matches!(position, Position::Name | Position::Member) {
+        return Vec::new();
+    }
+
+    let context = left.parent().unwrap();
+    let mut items = variables(&root, &context, offset, range);
+    items.extend(functions(&root, range));
+    items.extend(imports(&root, range));
+    if position == Position::Stmt {
+        items.extend(keywords(range));
+    }
+
+    items.retain(|item| item.label.starts_with(prefix));
+    items
+}
+
+fn variables(
+    root: &SyntaxNode,
+    context: &SyntaxNode,
+    offset: TextSize,
+    range: TextRange,
+) -> Vec<CompletionItem> {
+    let mut seen = HashSet::new();
+    let mut items = Vec::new();
+
+    let mut add = |name: SyntaxToken, detail: &str| {
+        if seen.insert(name.text().to_string()) {
+            items.push(CompletionItem {
+                label: name.text().to_string(),
+                kind: CompletionKind::Variable,
+                detail: Some(format!("{} {}", detail, name.text())),
+                insert_text: name.text().to_string(),
+                is_snippet: false,
+                range,
+            });
+        }
+    };
+
+    let fn_def = context
+        .ancestors()
+        .find(|node| node.kind() == SyntaxKind::FnDef);
+
+    let blocks = context
+        .ancestors()
+        .take_while(|node| node.kind() != SyntaxKind::FnDef)
+        .filter(|node| node.kind() == SyntaxKind::Block);
+
+    for block in blocks {
+        for name in defined_before(&block, offset).into_iter().rev() {
+            add(name, "let");
+        }
+    }
+
+    if let Some(Stmt::FnDef(fn_def)) = fn_def.clone().and_then(Stmt::cast) {
+        for param in fn_def.param_list().iter().flat_map(|list| list.params()) {
+            if let Some(name) = param.name() {
+                add(name, "param");
+            }
+        }
+    }
+
+    // Functions can be called at any point, so they see globals defined after them too.
+    let globals = if fn_def.is_some() {
+        defined_before(root, root.text_range().end() + TextSize::from(1))
+    } else {
+        defined_before(root, offset)
+    };
+    for name in globals.into_iter().rev() {
+        add(name, "let");
+    }
+
+    items
+}
+
+/// The names of the `let`s directly in `node` that are complete before `offset`.
+fn defined_before(node: &SyntaxNode, offset: TextSize) -> Vec<SyntaxToken> {
+    node.children()
+        .filter_map(Stmt::cast)
+        .filter_map(|stmt| match stmt {
+            Stmt::VariableDef(variable_def) => {
+                let end = last_token(variable_def.syntax())?.text_range().end();
+                (end < offset).then(|| variable_def.name()).flatten()
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+fn functions(root: &SyntaxNode, range: TextRange) -> Vec<CompletionItem> {
+    let mut items: Vec<CompletionItem> = Vec::new();
+
+    for node in root.descendants() {
+        let Some(Stmt::FnDef(fn_def)) = Stmt::cast(node) else {
+            continue;
+        };
+        let Some(name) = fn_def.name() else {
+            continue;
+        };
+
+        let params: Vec<_> = fn_def
+            .param_list()
+            .iter()
+            .flat_map(|list| list.params())
+            .filter_map(|param| param.name())
+            .map(|name| name.text().to_string())
+            .collect();
+        let tab_stops: Vec<_> = params
+            .iter()
+            .enumerate()
+            .map(|(idx, param)| format!("${{{}:{}}}", idx + 1, param))
+            .collect();
+
+        let item = CompletionItem {
+            label: name.text().to_string(),
+            kind: CompletionKind::Function,
+            detail: Some(format!("fn {}({})", name.text(), params.join(", "))),
+            insert_text: format!("{}({})", name.text(), tab_stops.join(", ")),
+            is_snippet: !params.is_empty(),
+            range,
+        };
+
+        // Later definitions replace earlier ones.
+        match items.iter_mut().find(|other| other.label == item.label) {
+            Some(other) => *other = item,
+            None => items.push(item),
+        }
+    }
+
+    items
+}
+
+/// The aliases of imported modules, followed by the names brought in by `use`.
+fn imports(root: &SyntaxNode, range: TextRange) -> Vec<CompletionItem> {
+    let stmts: Vec<_> = root.descendants().filter_map(Stmt::cast).collect();
+
+    let modules = stmts.iter().filter_map(|stmt| match stmt {
+        Stmt::Import(import) => {
+            let alias = import.alias()?;
+            let detail = format!("import {} as {}", import.path()?.text(), alias.text());
+            Some((alias, CompletionKind::Module, detail))
+        }
+        _ => None,
+    });
+    let uses = stmts.iter().filter_map(|stmt| match stmt {
+        Stmt::Use(use_) => {
+            let name = use_.name()?;
+            let detail = format!("use {}.{}", use_.module()?.text(), name.text());
+            Some((name, CompletionKind::Variable, detail))
+        }
+        _ => None,
+    });
+
+    let mut seen = HashSet::new();
+    modules
+        .chain(uses)
+        .filter(|(name, _, _)| seen.insert(name.text().to_string()))
+        .map(|(name, kind, detail)| CompletionItem {
+            label: name.text().to_string(),
+            kind,
+            detail: Some(detail),
+            insert_text: name.text().to_string(),
+            is_snippet: false,
+            range,
+        })
+        .collect()
+}
+
+fn keywords(range: TextRange) -> Vec<CompletionItem> {
+    [
+        ("let", "let ${1:name} = $0"),
+        ("fn", "fn ${1:name}($2) {\n    $0\n}"),
+        ("import", "import \"$1\" as ${2:name}"),
+        ("use", "use ${1:module}.${2:name}"),
+    ]
+    .into_iter()
+    .map(|(label, snippet)| CompletionItem {
+        label: label.to_string(),
+        kind: CompletionKind::Keyword,
+        detail: None,
+        insert_text: snippet.to_string(),
+        is_snippet: true,
+        range,
+    })
+    .collect()
+}
+
+fn last_token(node: &SyntaxNode) -> Option<SyntaxToken> {
+    node.descendants_with_tokens()
+        .filter_map(|element| element.into_token())
+        .filter(|token| !token.kind().is_trivia())
+        .last()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::analysis_at;
+    use expect_test::{expect, Expect};
+
+    fn check(input: &str, expected: Expect) {
+        let (analysis, offset) = analysis_at(input);
+
+        let actual: String = analysis
+            .completions(offset)
+            .into_iter()
+            .map(|item| {
+                format!(
+                    "{:?} {} {:?} {:?}\n",
+                    item.kind,
+                    item.label,
+                    item.detail.unwrap_or_default(),
+                    item.insert_text
+                )
+            })
+            .collect();
+
+        expected.assert_eq(&actual);
+    }
+
+    #[test]
+    fn complete_globals_defined_before_cursor() {
+        check(
+            "let apple = 1\nlet avocado = 2\nlet b = a$0\nlet apricot = 3",
+            expect![[r#"
+                Variable avocado "let avocado" "avocado"
+                Variable apple "let apple" "apple"
+            "#]],
+        );
+    }
+
+    #[test]
+    fn complete_in_function_body() {
+        check(
+            "let g = 1\nfn f(x, y) { let z = 2 $0 }\nlet h = 2",
+            expect![[r#"
+                Variable z "let z" "z"
+                Variable x "param x" "x"
+                Variable y "param y" "y"
+                Variable h "let h" "h"
+                Variable g "let g" "g"
+                Function f "fn f(x, y)" "f(${1:x}, ${2:y})"
+                Keyword let "" "let ${1:name} = $0"
+                Keyword fn "" "fn ${1:name}($2) {\n    $0\n}"
+                Keyword import "" "import \"$1\" as ${2:name}"
+                Keyword use "" "use ${1:module}.${2:name}"
+            "#]],
+        );
+    }
+
+    #[test]
+    fn inner_definitions_shadow_outer_ones() {
+        check(
+            "let a = 1\n{ let a = 2 { a$0 } }",
+            expect![[r#"
+                Variable a "let a" "a"
+            "#]],
+        );
+        check(
+            "let ab = 1\n{ let ab = 2 { let ac = ab a$0 } }",
+            expect![[r#"
+                Variable ac "let ac" "ac"
+                Variable ab "let ab" "ab"
+            "#]],
+        );
+    }
+
+    #[test]
+    fn complete_keywords_only_where_statements_start() {
+        check(
+            "l$0",
+            expect![[r#"
+                Keyword let "" "let ${1:name} = $0"
+            "#]],
+        );
+        check("let a = 1 + f$0", expect![""]);
+        check("fn f(a$0", expect![""]);
+        check("let a$0", expect![""]);
+    }
+
+    #[test]
+    fn complete_modules_and_used_names() {
+        check(
+            "import \"lib/math.ff\" as math\nuse math.square\nlet a = m$0",
+            expect![[r#"
+                Module math "import \"lib/math.ff\" as math" "math"
+            "#]],
+        );
+        check(
+            "import \"lib/math.ff\" as math\nuse math.square\nlet a = s$0",
+            expect![[r#"
+                Variable square "use math.square" "square"
+            "#]],
+        );
+        check("import \"lib/math.ff\" as math\nmath.s$0", expect![""]);
+    }
+
+    #[test]
+    fn complete_functions_with_parameter_snippets() {
+        check(
+            "fn add(x, y) { x + y }\nfn one() { 1 }\nlet a = add(o$0",
+            expect![[r#"
+                Function one "fn one()" "one()"
+            "#]],
+        );
+    }
+}
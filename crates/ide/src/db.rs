@@ -0,0 +1,449 @@
+use crate::infer::Inference;
+use crate::resolve::Resolutions;
+use crate::{Analysis, Diagnostic};
+use ast::AstNode;
+use parser::Parse;
+use std::cell::RefCell;
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::Arc;
+use syntax::{SyntaxToken, TextEdit};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct FileId(pub u32);
+
+/// The paths of the files in the database, which imports are looked up in.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FileSet {
+    paths: Vec<PathBuf>,
+}
+
+impl FileSet {
+    pub fn file(&self, path: &Path) -> Option<FileId> {
+        let path = modules::normalize(path);
+
+        self.paths
+            .iter()
+            .position(|known| *known == path)
+            .map(|idx| FileId(idx as u32))
+    }
+
+    pub fn path(&self, file: FileId) -> &Path {
+        &self.paths[file.0 as usize]
+    }
+
+    fn insert(&mut self, path: &Path) -> FileId {
+        self.file(path).unwrap_or_else(|| {
+            self.paths.push(modules::normalize(path));
+            FileId(self.paths.len() as u32 - 1)
+        })
+    }
+}
+
+/// The globals and functions a file defines, which are all that files importing it depend on.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Exports {
+    pub globals: BTreeSet<String>,
+    pub fns: BTreeSet<String>,
+}
+
+impl Exports {
+    fn contains(&self, name: &str) -> bool {
+        self.globals.contains(name) || self.fns.contains(name)
+    }
+}
+
+/// Everything known about a set of files, computed on demand and remembered until the text it
+/// came from changes.
+///
+/// Queries are recomputed lazily after an input changes, and only if something they read has
+/// changed: editing a file reparses just that file, and files importing it are only checked
+/// again if what it exports changed.
+#[salsa::query_group(SourceDatabaseStorage)]
+pub trait SourceDatabase: salsa::Database + Edits {
+    #[salsa::input]
+    fn file_text(&self, file: FileId) -> Arc<String>;
+
+    #[salsa::input]
+    fn files(&self) -> Arc<FileSet>;
+
+    fn parse(&self, file: FileId) -> Arc<Parse>;
+
+    fn lower(&self, file: FileId) -> Arc<hir::Program>;
+
+    /// Resolutions point into the syntax tree, which can't be shared between threads.
+    fn resolve(&self, file: FileId) -> Rc<Resolutions>;
+
+    fn infer(&self, file: FileId) -> Arc<Inference>;
+
+    fn exports(&self, file: FileId) -> Arc<Exports>;
+
+    /// Parse errors, along with names used from imported files that they don't define. Files
+    /// that aren't in the database aren't checked.
+    fn diagnostics(&self, file: FileId) -> Arc<Vec<Diagnostic>>;
+}
+
+/// The last edit made to a file, which its next parse can start from.
+pub struct LastEdit {
+    old: Arc<Parse>,
+    edit: TextEdit,
+    /// The text after the edit, to tell whether the file has been set to something else since.
+    text: Arc<String>,
+}
+
+pub trait Edits {
+    fn last_edit(&self, file: FileId) -> Option<Arc<LastEdit>>;
+}
+
+fn parse(db: &dyn SourceDatabase, file: FileId) -> Arc<Parse> {
+    let text = db.file_text(file);
+
+    // Reparsing gives the same tree as parsing from scratch, so reading the edit outside of
+    // the inputs doesn't change the result, only how fast it's found.
+    match db.last_edit(file) {
+        Some(last) if Arc::ptr_eq(&last.text, &text) => {
+            Arc::new(last.old.reparse(last.edit.clone()))
+        }
+        _ => Arc::new(parser::parse(&text)),
+    }
+}
+
+fn lower(db: &dyn SourceDatabase, file: FileId) -> Arc<hir::Program> {
+    let root = ast::Root::cast(db.parse(file).syntax()).unwrap();
+    Arc::new(hir::lower(root))
+}
+
+fn resolve(db: &dyn SourceDatabase, file: FileId) -> Rc<Resolutions> {
+    let root = ast::Root::cast(db.parse(file).syntax()).unwrap();
+    Rc::new(Resolutions::new(&root))
+}
+
+fn infer(db: &dyn SourceDatabase, file: FileId) -> Arc<Inference> {
+    Arc::new(Inference::new(&db.resolve(file)))
+}
+
+fn exports(db: &dyn SourceDatabase, file: FileId) -> Arc<Exports> {
+    let program = db.lower(file);
+    let mut exports = Exports::default();
+
+    for stmt in &program.stmts {
+        if let hir::StmtKind::VariableDef { name, .. } = &stmt.kind {
+            exports.globals.insert(name.clone());
+        }
+    }
+    for fn_def in &program.fns {
+        exports.fns.insert(fn_def.name.clone());
+    }
+
+    Arc::new(exports)
+}
+
+fn diagnostics(db: &dyn SourceDatabase, file: FileId) -> Arc<Vec<Diagnostic>> {
+    let parse = db.parse(file);
+    let mut diagnostics: Vec<_> = parse
+        .errors()
+        .iter()
+        .map(|error| Diagnostic {
+            range: error.range(),
+            message: error.message(),
+        })
+        .collect();
+
+    let files = db.files();
+    let dir = files.path(file).parent().unwrap_or(Path::new(""));
+    let root = ast::Root::cast(parse.syntax()).unwrap();
+
+    let modules: HashMap<_, _> = db
+        .lower(file)
+        .imports
+        .iter()
+        .filter_map(|import| Some((import.alias.clone(), files.file(&dir.join(&import.path))?)))
+        .collect();
+
+    let mut check = |module: Option<SyntaxToken>, name: Option<SyntaxToken>| {
+        let (Some(module), Some(name)) = (module, name) else {
+            return;
+        };
+        let Some(&imported) = modules.get(module.text()) else {
+            return;
+        };
+
+        if !db.exports(imported).contains(name.text()) {
+            diagnostics.push(Diagnostic {
+                range: name.text_range(),
+                message: format!("module '{}' has no '{}'", module.text(), name.text()),
+            });
+        }
+    };
+
+    for node in root.syntax().descendants() {
+        if let Some(use_) = ast::Use::cast(node.clone()) {
+            check(use_.module(), use_.name());
+        } else if let Some(variable_ref) = ast::VariableRef::cast(node.clone()) {
+            check(variable_ref.qualifier(), variable_ref.name());
+        } else if let Some(call_expr) = ast::CallExpr::cast(node) {
+            check(call_expr.qualifier(), call_expr.name());
+        }
+    }
+
+    diagnostics.sort_by_key(|diagnostic| diagnostic.range.start());
+
+    Arc::new(diagnostics)
+}
+
+#[salsa::database(SourceDatabaseStorage)]
+pub struct RootDatabase {
+    storage: salsa::Storage<Self>,
+    last_edits: RefCell<HashMap<FileId, Arc<LastEdit>>>,
+    #[cfg(test)]
+    executed: RefCell<Vec<String>>,
+}
+
+impl salsa::Database for RootDatabase {
+    #[cfg(test)]
+    fn salsa_event(&self, event: salsa::Event) {
+        if let salsa::EventKind::WillExecute { database_key } = event.kind {
+            let executed = format!("{:?}", database_key.debug(self));
+            self.executed.borrow_mut().push(executed);
+        }
+    }
+}
+
+impl Edits for RootDatabase {
+    fn last_edit(&self, file: FileId) -> Option<Arc<LastEdit>> {
+        self.last_edits.borrow().get(&file).cloned()
+    }
+}
+
+impl Default for RootDatabase {
+    fn default() -> Self {
+        let mut db = Self {
+            storage: salsa::Storage::default(),
+            last_edits: RefCell::default(),
+            #[cfg(test)]
+            executed: RefCell::default(),
+        };
+        db.set_files(Arc::default());
+
+        db
+    }
+}
+
+impl RootDatabase {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the text of the file at `path`, adding it to the database if it isn't already.
+    pub fn set_file(&mut self, path: &Path, text: String) -> FileId {
+        let file = match self.files().file(path) {
+            Some(file) => file,
+            None => {
+                let mut files = FileSet::clone(&self.files());
+                let file = files.insert(path);
+                self.set_files(Arc::new(files));
+                file
+            }
+        };
+
+        self.set_file_text(file, Arc::new(text));
+
+        file
+    }
+
+    /// Applies `edit` to the text of `file`, so that it's only reparsed as far as the edit
+    /// reaches.
+    pub fn apply_edit(&mut self, file: FileId, edit: TextEdit) {
+        let old = self.parse(file);
+        let mut text = String::clone(&self.file_text(file));
+        edit.apply(&mut text);
+        let text = Arc::new(text);
+
+        self.last_edits.borrow_mut().insert(
+            file,
+            Arc::new(LastEdit {
+                old,
+                edit,
+                text: Arc::clone(&text),
+            }),
+        );
+        self.set_file_text(file, text);
+    }
+
+    /// The files imported by `file`, as paths relative to the current directory.
+    pub fn imports(&self, file: FileId) -> Vec<PathBuf> {
+        let files = self.files();
+        let dir = files.path(file).parent().unwrap_or(Path::new(""));
+
+        self.lower(file)
+            .imports
+            .iter()
+            .map(|import| modules::normalize(&dir.join(&import.path)))
+            .collect()
+    }
+
+    pub fn analysis(&self, file: FileId) -> Analysis {
+        Analysis {
+            parse: self.parse(file),
+            resolutions: self.resolve(file),
+            inference: self.infer(file),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use expect_test::{expect, Expect};
+    use text_size::TextRange;
+
+    fn executed(db: &RootDatabase, f: impl FnOnce()) -> String {
+        db.executed.borrow_mut().clear();
+        f();
+        db.executed.borrow().join("\n")
+    }
+
+    fn check_diagnostics(db: &RootDatabase, file: FileId, expected: Expect) {
+        expected.assert_debug_eq(&db.diagnostics(file));
+    }
+
+    fn two_files() -> (RootDatabase, FileId, FileId) {
+        let mut db = RootDatabase::new();
+        let main = db.set_file(
+            Path::new("src/main.ff"),
+            "import \"lib/m.ff\" as m\nuse m.f\nf(m.x)".to_string(),
+        );
+        let lib = db.set_file(
+            Path::new("src/lib/m.ff"),
+            "let x = 1\nfn f(a) { a }".to_string(),
+        );
+
+        (db, main, lib)
+    }
+
+    #[test]
+    fn check_names_used_from_imported_files() {
+        let (mut db, main, _) = two_files();
+        check_diagnostics(
+            &db,
+            main,
+            expect![[r#"
+            []
+        "#]],
+        );
+
+        db.set_file(
+            Path::new("src/lib/../lib/m.ff"),
+            "let y = 1\nfn g(a) { a }".to_string(),
+        );
+        check_diagnostics(
+            &db,
+            main,
+            expect![[r#"
+            [
+                Diagnostic {
+                    range: 29..30,
+                    message: "module 'm' has no 'f'",
+                },
+                Diagnostic {
+                    range: 35..36,
+                    message: "module 'm' has no 'x'",
+                },
+            ]
+        "#]],
+        );
+    }
+
+    #[test]
+    fn do_not_check_files_outside_the_database() {
+        let mut db = RootDatabase::new();
+        let main = db.set_file(
+            Path::new("main.ff"),
+            "import \"m.ff\" as m\nm.x +".to_string(),
+        );
+
+        check_diagnostics(
+            &db,
+            main,
+            expect![[r#"
+            [
+                Diagnostic {
+                    range: 23..24,
+                    message: "expected number, identifier, '-', '(' or '{'",
+                },
+            ]
+        "#]],
+        );
+    }
+
+    #[test]
+    fn only_recompute_the_edited_file() {
+        let (mut db, main, lib) = two_files();
+        db.diagnostics(main);
+        db.diagnostics(lib);
+
+        db.apply_edit(
+            main,
+            TextEdit::replace(TextRange::new(33.into(), 36.into()), "m.x + 1"),
+        );
+        let actual = executed(&db, || {
+            db.diagnostics(main);
+            db.diagnostics(lib);
+        });
+        expect![[r#"
+            parse(FileId(0))
+            diagnostics(FileId(0))
+            lower(FileId(0))"#]]
+        .assert_eq(&actual);
+    }
+
+    #[test]
+    fn only_recheck_importers_when_exports_change() {
+        let (mut db, main, lib) = two_files();
+        db.diagnostics(main);
+
+        db.apply_edit(
+            lib,
+            TextEdit::replace(TextRange::new(20.into(), 21.into()), "a * 2"),
+        );
+        let actual = executed(&db, || {
+            db.diagnostics(main);
+        });
+        expect![[r#"
+            parse(FileId(1))
+            lower(FileId(1))
+            exports(FileId(1))"#]]
+        .assert_eq(&actual);
+
+        db.apply_edit(
+            lib,
+            TextEdit::replace(TextRange::new(4.into(), 5.into()), "y"),
+        );
+        let actual = executed(&db, || {
+            db.diagnostics(main);
+        });
+        expect![[r#"
+            parse(FileId(1))
+            lower(FileId(1))
+            exports(FileId(1))
+            diagnostics(FileId(0))"#]]
+        .assert_eq(&actual);
+    }
+
+    #[test]
+    fn edits_parse_like_the_new_text() {
+        let (mut db, _, lib) = two_files();
+        db.parse(lib);
+
+        db.apply_edit(
+            lib,
+            TextEdit::replace(TextRange::new(20.into(), 21.into()), "a * 2"),
+        );
+
+        assert_eq!(
+            db.parse(lib).debug_tree(),
+            parser::parse(&db.file_text(lib)).debug_tree(),
+        );
+    }
+}
@@ -0,0 +1,245 @@
+use syntax::{SyntaxKind, SyntaxNode, SyntaxToken};
+use text_size::TextRange;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HighlightTag {
+    Keyword,
+    VariableDef,
+    VariableRef,
+    Number,
+    String,
+    Operator,
+    Comment,
+    Error,
+}
+
+impl HighlightTag {
+    fn ansi_code(self) -> &'static str {
+        match self {
+            Self::Keyword => "35",
+            Self::VariableDef => "1;34",
+            Self::VariableRef => "34",
+            Self::Number => "33",
+            Self::String => "32",
+            Self::Operator => "36",
+            Self::Comment => "90",
+            Self::Error => "4;31",
+        }
+    }
+
+    fn css_class(self) -> &'static str {
+        match self {
+            Self::Keyword => "keyword",
+            Self::VariableDef => "variable-def",
+            Self::VariableRef => "variable-ref",
+            Self::Number => "number",
+            Self::String => "string",
+            Self::Operator => "operator",
+            Self::Comment => "comment",
+            Self::Error => "error",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HighlightedRange {
+    pub range: TextRange,
+    pub tag: HighlightTag,
+}
+
+/// Classifies the tokens under `root`, in source order. Whitespace and punctuation aren't
+/// highlighted.
+pub(crate) fn highlight(root: &SyntaxNode) -> Vec<HighlightedRange> {
+    root.descendants_with_tokens()
+        .filter_map(|element| element.into_token())
+        .filter_map(|token| {
+            Some(HighlightedRange {
+                range: token.text_range(),
+                tag: tag(&token)?,
+            })
+        })
+        .collect()
+}
+
+fn tag(token: &SyntaxToken) -> Option<HighlightTag> {
+    let parent = token.parent().map(|parent| parent.kind());
+
+    if parent == Some(SyntaxKind::Error) && !token.kind().is_trivia() {
+        return Some(HighlightTag::Error);
+    }
+
+    let tag = match token.kind() {
+        SyntaxKind::FnKw
+        | SyntaxKind::LetKw
+        | SyntaxKind::ImportKw
+        | SyntaxKind::AsKw
+        | SyntaxKind::UseKw => HighlightTag::Keyword,
+        SyntaxKind::Number => HighlightTag::Number,
+        SyntaxKind::String => HighlightTag::String,
+        SyntaxKind::Plus
+        | SyntaxKind::Minus
+        | SyntaxKind::Star
+        | SyntaxKind::Slash
+        | SyntaxKind::Equals => HighlightTag::Operator,
+        SyntaxKind::Comment => HighlightTag::Comment,
+        SyntaxKind::Ident => match parent? {
+            SyntaxKind::VariableDef
+            | SyntaxKind::FnDef
+            | SyntaxKind::Param
+            | SyntaxKind::Import => HighlightTag::VariableDef,
+            // `use m.name` refers to `m` and defines `name`.
+            SyntaxKind::Use
+                if token.prev_token().map(|prev| prev.kind()) == Some(SyntaxKind::Dot) =>
+            {
+                HighlightTag::VariableDef
+            }
+            SyntaxKind::Use => HighlightTag::VariableRef,
+            SyntaxKind::VariableRef | SyntaxKind::CallExpr => HighlightTag::VariableRef,
+            _ => return None,
+        },
+        _ => return None,
+    };
+
+    Some(tag)
+}
+
+/// Renders `text` with ANSI escape codes for a terminal.
+pub fn render_ansi(text: &str, highlights: &[HighlightedRange]) -> String {
+    render(text, highlights, str::to_string, |tag, text| {
+        format!("\x1b[{}m{}\x1b[0m", tag.ansi_code(), text)
+    })
+}
+
+/// Renders `text` as a standalone HTML page, with a CSS class on every highlighted token.
+pub fn render_html(text: &str, highlights: &[HighlightedRange]) -> String {
+    let code = render(text, highlights, escape_html, |tag, text| {
+        format!(
+            "<span class=\"{}\">{}</span>",
+            tag.css_class(),
+            escape_html(text)
+        )
+    });
+
+    format!(
+        "{}<pre><code>{}</code></pre>\n</body>\n</html>\n",
+        HTML_HEADER, code
+    )
+}
+
+const HTML_HEADER: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<style>
+pre { background: #fafafa; color: #383a42; padding: 1em; }
+.keyword { color: #a626a4; }
+.variable-def { color: #4078f2; font-weight: bold; }
+.variable-ref { color: #4078f2; }
+.number { color: #986801; }
+.operator { color: #0184bc; }
+.comment { color: #a0a1a7; font-style: italic; }
+.error { color: #e45649; text-decoration: underline wavy; }
+</style>
+</head>
+<body>
+"#;
+
+fn render(
+    text: &str,
+    highlights: &[HighlightedRange],
+    plain: impl Fn(&str) -> String,
+    highlighted: impl Fn(HighlightTag, &str) -> String,
+) -> String {
+    let mut out = String::new();
+    let mut end = 0;
+
+    for highlight in highlights {
+        let range = highlight.range;
+        out.push_str(&plain(&text[end..usize::from(range.start())]));
+        out.push_str(&highlighted(highlight.tag, &text[range]));
+        end = usize::from(range.end());
+    }
+
+    out.push_str(&plain(&text[end..]));
+
+    out
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Analysis;
+    use expect_test::{expect, Expect};
+
+    fn check(input: &str, expected: Expect) {
+        let analysis = Analysis::new(input);
+
+        let actual: String = analysis
+            .highlight()
+            .iter()
+            .map(|highlight| format!("{:?} {:?}\n", highlight.tag, &input[highlight.range]))
+            .collect();
+
+        expected.assert_eq(&actual);
+    }
+
+    #[test]
+    fn classify_tokens() {
+        check(
+            "fn f(x) { x * 2 } # double\nlet a = f(1) +",
+            expect![[r##"
+                Keyword "fn"
+                VariableDef "f"
+                VariableDef "x"
+                VariableRef "x"
+                Operator "*"
+                Number "2"
+                Comment "# double"
+                Keyword "let"
+                VariableDef "a"
+                Operator "="
+                VariableRef "f"
+                Number "1"
+                Operator "+"
+            "##]],
+        );
+    }
+
+    #[test]
+    fn classify_imports() {
+        check(
+            "import \"m.ff\" as m\nuse m.f\nf(m.x)",
+            expect![[r#"
+                Keyword "import"
+                String "\"m.ff\""
+                Keyword "as"
+                VariableDef "m"
+                Keyword "use"
+                VariableRef "m"
+                VariableDef "f"
+                VariableRef "f"
+                VariableRef "m"
+                VariableRef "x"
+            "#]],
+        );
+    }
+
+    #[test]
+    fn render_errors_and_escape_html() {
+        let text = "let a = ) # <b>";
+        let analysis = Analysis::new(text);
+        let html = render_html(text, &analysis.highlight());
+
+        expect![[r##"<span class="keyword">let</span> <span class="variable-def">a</span> <span class="operator">=</span> <span class="error">)</span> <span class="comment"># &lt;b&gt;</span>"##]]
+        .assert_eq(&html[html.find("<code>").unwrap() + 6..html.find("</code>").unwrap()]);
+
+        expect![[r#""\u{1b}[35mlet\u{1b}[0m \u{1b}[1;34ma\u{1b}[0m \u{1b}[36m=\u{1b}[0m \u{1b}[4;31m)\u{1b}[0m \u{1b}[90m# <b>\u{1b}[0m""#]]
+        .assert_eq(&format!("{:?}", render_ansi(text, &analysis.highlight())));
+    }
+}
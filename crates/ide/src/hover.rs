@@ -0,0 +1,152 @@
+use crate::resolve::{DefId, DefinitionKind};
+use crate::Analysis;
+use ast::{AstNode, Expr, Stmt};
+use syntax::SyntaxKind;
+use text_size::{TextRange, TextSize};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hover {
+    pub range: TextRange,
+    pub text: String,
+}
+
+/// Shows the signature of a definition when hovering over its name, and the kind and type of
+/// the innermost expression otherwise.
+pub(crate) fn hover(analysis: &Analysis, offset: TextSize) -> Option<Hover> {
+    let token = syntax::token_at_offset(&analysis.syntax(), offset)
+        .filter(|token| !token.kind().is_trivia())?;
+
+    if token.kind() == SyntaxKind::Ident {
+        if let Some(id) = analysis.resolutions.def_at(token.text_range().start()) {
+            let def = analysis.resolutions.def(id);
+
+            if def.name_range == token.text_range() {
+                return Some(Hover {
+                    range: def.name_range,
+                    text: signature(analysis, id),
+                });
+            }
+        }
+    }
+
+    let expr = token.parent_ancestors().find_map(Expr::cast)?;
+
+    Some(Hover {
+        range: expr.syntax().text_range(),
+        text: format!(
+            "{}: {}",
+            describe(&expr),
+            analysis.inference.expr(&analysis.resolutions, &expr)
+        ),
+    })
+}
+
+fn describe(expr: &Expr) -> &'static str {
+    match expr {
+        Expr::InfixExpr(_) => "infix expression",
+        Expr::Literal(_) => "literal",
+        Expr::ParenExpr(_) => "parenthesized expression",
+        Expr::PrefixExpr(_) => "prefix expression",
+        Expr::VariableRef(_) => "variable reference",
+        Expr::Block(_) => "block",
+        Expr::CallExpr(_) => "call",
+    }
+}
+
+fn signature(analysis: &Analysis, id: DefId) -> String {
+    let def = analysis.resolutions.def(id);
+    let ty = analysis.inference.def(id);
+
+    match def.kind {
+        DefinitionKind::Global | DefinitionKind::Local => format!("let {}: {}", def.name, ty),
+        DefinitionKind::Param => format!("param {}", def.name),
+        DefinitionKind::Module | DefinitionKind::Imported => trimmed_text(&def.syntax),
+        DefinitionKind::Fn => {
+            let Some(Stmt::FnDef(fn_def)) = Stmt::cast(def.syntax.clone()) else {
+                unreachable!()
+            };
+            let params: Vec<_> = fn_def
+                .param_list()
+                .iter()
+                .flat_map(|list| list.params())
+                .filter_map(|param| param.name())
+                .map(|name| name.text().to_string())
+                .collect();
+
+            format!("fn {}({}): {}", def.name, params.join(", "), ty)
+        }
+    }
+}
+
+/// The text of `node` without the trivia the parser attaches to its end.
+fn trimmed_text(node: &syntax::SyntaxNode) -> String {
+    let tokens: Vec<_> = node
+        .descendants_with_tokens()
+        .filter_map(|element| element.into_token())
+        .collect();
+    let end = tokens
+        .iter()
+        .rposition(|token| !token.kind().is_trivia())
+        .map_or(0, |idx| idx + 1);
+
+    tokens[..end].iter().map(|token| token.text()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::analysis_at;
+    use expect_test::{expect, Expect};
+
+    fn check(input: &str, expected: Expect) {
+        let (analysis, offset) = analysis_at(input);
+
+        let actual = match analysis.hover(offset) {
+            Some(hover) => format!("{:?} {}", hover.range, hover.text),
+            None => "no hover".to_string(),
+        };
+
+        expected.assert_eq(&actual);
+    }
+
+    #[test]
+    fn hover_expressions() {
+        check("1 +$0 2", expect!["0..5 infix expression: int"]);
+        check("let a = ({ $0})", expect!["9..12 block: ()"]);
+        check(
+            "let a = 1\nlet b = -a$0",
+            expect!["19..20 variable reference: int"],
+        );
+        check("fn f(x) { x }\nf$0(1)", expect!["14..18 call: {unknown}"]);
+    }
+
+    #[test]
+    fn hover_definitions() {
+        check("let a$0 = { let b = 1 }", expect!["4..5 let a: ()"]);
+        check(
+            "fn add$0(x, y) { x + y }",
+            expect!["3..6 fn add(x, y): int"],
+        );
+        check("fn f(x$0) { x }", expect!["5..6 param x"]);
+        check(
+            "import \"lib/math.ff\" as m$0\nuse m.sq\nsq(2)",
+            expect![[r#"24..25 import "lib/math.ff" as m"#]],
+        );
+        check(
+            "import \"lib/math.ff\" as m\nuse m.sq$0\nsq(2)",
+            expect!["32..34 use m.sq"],
+        );
+    }
+
+    #[test]
+    fn recursive_definitions_have_unknown_type() {
+        check(
+            "fn f$0() { g() }\nfn g() { f() }",
+            expect!["3..4 fn f(): {unknown}"],
+        );
+    }
+
+    #[test]
+    fn no_hover_outside_expressions() {
+        check("let a = 1\n$0\n", expect!["no hover"]);
+    }
+}
@@ -0,0 +1,135 @@
+use crate::resolve::{DefId, Resolutions};
+use ast::{AstNode, Expr, Stmt};
+use std::collections::HashMap;
+use std::fmt;
+
+/// The type of the value an expression evaluates to, as far as it can be told without running
+/// it. Parameters can be bound to anything, so their type is unknown.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Ty {
+    Int,
+    Unit,
+    Unknown,
+}
+
+impl fmt::Display for Ty {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Int => "int",
+            Self::Unit => "()",
+            Self::Unknown => "{unknown}",
+        })
+    }
+}
+
+/// The types of every variable's value and function's result in a file.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Inference {
+    types: HashMap<DefId, Ty>,
+}
+
+impl Inference {
+    pub(crate) fn new(resolutions: &Resolutions) -> Self {
+        let mut types = HashMap::new();
+
+        for (id, _) in resolutions.defs() {
+            let ty = Infer::new(resolutions, &types).def(id);
+            types.insert(id, ty);
+        }
+
+        Self { types }
+    }
+
+    pub(crate) fn def(&self, id: DefId) -> Ty {
+        self.types.get(&id).copied().unwrap_or(Ty::Unknown)
+    }
+
+    pub(crate) fn expr(&self, resolutions: &Resolutions, expr: &Expr) -> Ty {
+        Infer::new(resolutions, &self.types).expr(expr)
+    }
+}
+
+struct Infer<'a> {
+    resolutions: &'a Resolutions,
+    /// The types inferred so far.
+    types: &'a HashMap<DefId, Ty>,
+    /// The definitions whose types are being inferred, to give up on recursive ones.
+    visiting: Vec<DefId>,
+}
+
+impl<'a> Infer<'a> {
+    fn new(resolutions: &'a Resolutions, types: &'a HashMap<DefId, Ty>) -> Self {
+        Self {
+            resolutions,
+            types,
+            visiting: Vec::new(),
+        }
+    }
+
+    fn def(&mut self, id: DefId) -> Ty {
+        if let Some(&ty) = self.types.get(&id) {
+            return ty;
+        }
+        if self.visiting.contains(&id) {
+            return Ty::Unknown;
+        }
+
+        let def = self.resolutions.def(id);
+        let value = match Stmt::cast(def.syntax.clone()) {
+            Some(Stmt::VariableDef(variable_def)) => variable_def.value(),
+            Some(Stmt::FnDef(fn_def)) => fn_def
+                .body()
+                .and_then(|body| Expr::cast(body.syntax().clone())),
+            _ => None,
+        };
+
+        let Some(value) = value else {
+            return Ty::Unknown;
+        };
+
+        self.visiting.push(id);
+        let ty = self.expr(&value);
+        self.visiting.pop();
+
+        ty
+    }
+
+    fn expr(&mut self, expr: &Expr) -> Ty {
+        match expr {
+            Expr::InfixExpr(_) | Expr::Literal(_) | Expr::PrefixExpr(_) => Ty::Int,
+            Expr::ParenExpr(paren_expr) => match paren_expr.expr() {
+                Some(expr) => self.expr(&expr),
+                None => Ty::Unknown,
+            },
+            Expr::VariableRef(variable_ref) => self.reference(variable_ref.name()),
+            Expr::CallExpr(call_expr) => self.reference(call_expr.name()),
+            Expr::Block(block) => {
+                // Function definitions are hoisted out of blocks, so they don't give it a value.
+                let last = block
+                    .stmts()
+                    .filter(|stmt| !matches!(stmt, Stmt::FnDef(_)))
+                    .last();
+
+                match last {
+                    Some(Stmt::Expr(expr)) => self.expr(&expr),
+                    _ => Ty::Unit,
+                }
+            }
+        }
+    }
+
+    fn reference(&mut self, name: Option<syntax::SyntaxToken>) -> Ty {
+        let id = name.and_then(|name| {
+            self.resolutions
+                .refs()
+                .iter()
+                .find(|reference| reference.range == name.text_range())
+                .and_then(|reference| reference.def)
+        });
+
+        match id {
+            Some(id) => self.def(id),
+            None => Ty::Unknown,
+        }
+    }
+}
@@ -0,0 +1,257 @@
+mod completion;
+mod db;
+mod highlight;
+mod hover;
+mod infer;
+mod lint;
+mod rename;
+mod resolve;
+mod symbols;
+
+pub use completion::{CompletionItem, CompletionKind};
+pub use db::{Exports, FileId, FileSet, RootDatabase, SourceDatabase};
+pub use highlight::{render_ansi, render_html, HighlightTag, HighlightedRange};
+pub use hover::Hover;
+pub use infer::Ty;
+pub use lint::{lint_rules, Lint, Severity};
+pub use rename::RenameError;
+pub use resolve::{Definition, DefinitionKind};
+pub use symbols::{Symbol, SymbolKind};
+
+use ast::AstNode;
+use infer::Inference;
+use parser::Parse;
+use resolve::Resolutions;
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::sync::Arc;
+use syntax::{SyntaxNode, TextEdit};
+use text_size::{TextRange, TextSize};
+
+/// Editor features for a single file, either computed up front from its text or taken from a
+/// [`RootDatabase`].
+pub struct Analysis {
+    parse: Arc<Parse>,
+    resolutions: Rc<Resolutions>,
+    inference: Arc<Inference>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub range: TextRange,
+    pub message: String,
+}
+
+impl Analysis {
+    pub fn new(text: &str) -> Self {
+        Self::from_parse(parser::parse(text))
+    }
+
+    pub fn from_parse(parse: Parse) -> Self {
+        let resolutions = Resolutions::new(&ast::Root::cast(parse.syntax()).unwrap());
+        let inference = Inference::new(&resolutions);
+
+        Self {
+            parse: Arc::new(parse),
+            resolutions: Rc::new(resolutions),
+            inference: Arc::new(inference),
+        }
+    }
+
+    pub fn parse(&self) -> &Parse {
+        &self.parse
+    }
+
+    pub fn syntax(&self) -> SyntaxNode {
+        self.parse.syntax()
+    }
+
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        self.parse
+            .errors()
+            .iter()
+            .map(|error| Diagnostic {
+                range: error.range(),
+                message: error.message(),
+            })
+            .collect()
+    }
+
+    pub fn document_symbols(&self) -> Vec<Symbol> {
+        symbols::symbols(&self.syntax())
+    }
+
+    /// The definition of the name at `offset`, which may be the name of a definition itself.
+    pub fn definition(&self, offset: TextSize) -> Option<&Definition> {
+        self.resolutions
+            .def_at(offset)
+            .map(|id| self.resolutions.def(id))
+    }
+
+    /// Every use of the name at `offset`, in source order.
+    pub fn references(&self, offset: TextSize, include_declaration: bool) -> Vec<TextRange> {
+        let Some(id) = self.resolutions.def_at(offset) else {
+            return Vec::new();
+        };
+
+        let declaration = self.resolutions.def(id).name_range;
+        let mut references: Vec<_> = include_declaration
+            .then_some(declaration)
+            .into_iter()
+            .chain(self.resolutions.references(id))
+            .collect();
+        references.sort_by_key(|range| range.start());
+
+        references
+    }
+
+    /// The names and keywords that could be typed at `offset`, in order of relevance.
+    pub fn completions(&self, offset: TextSize) -> Vec<CompletionItem> {
+        completion::completions(self, offset)
+    }
+
+    pub fn highlight(&self) -> Vec<HighlightedRange> {
+        highlight::highlight(&self.syntax())
+    }
+
+    pub fn hover(&self, offset: TextSize) -> Option<Hover> {
+        hover::hover(self, offset)
+    }
+
+    /// The problems found by the lint rules that aren't allowed in the file.
+    pub fn lints(&self) -> Vec<Lint> {
+        lint::lints(self, &HashMap::new())
+    }
+
+    /// Like [`Analysis::lints`], but with the severities of some rules, keyed by their name or
+    /// code, changed from the default.
+    pub fn lints_with_levels(&self, levels: &HashMap<String, Severity>) -> Vec<Lint> {
+        lint::lints(self, levels)
+    }
+
+    /// The edits that rename the variable or function at `offset` to `new_name` everywhere.
+    pub fn rename(&self, offset: TextSize, new_name: &str) -> Result<Vec<TextEdit>, RenameError> {
+        rename::rename(self, offset, new_name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use expect_test::{expect, Expect};
+
+    /// Finds the offset marked by `$0` and removes the marker.
+    pub(crate) fn analysis_at(input: &str) -> (Analysis, TextSize) {
+        let offset = input.find("$0").expect("no `$0` marker");
+        let text = input.replace("$0", "");
+
+        (Analysis::new(&text), TextSize::try_from(offset).unwrap())
+    }
+
+    fn check_definition(input: &str, expected: Expect) {
+        let (analysis, offset) = analysis_at(input);
+
+        let actual = match analysis.definition(offset) {
+            Some(def) => format!("{:?} {} {:?}", def.kind, def.name, def.name_range),
+            None => "unresolved".to_string(),
+        };
+
+        expected.assert_eq(&actual);
+    }
+
+    fn check_references(input: &str, expected: Expect) {
+        let (analysis, offset) = analysis_at(input);
+        expected.assert_debug_eq(&analysis.references(offset, true));
+    }
+
+    #[test]
+    fn diagnostics_come_from_parse_errors() {
+        expect![[r#"
+            [
+                Diagnostic {
+                    range: 8..9,
+                    message: "expected number, identifier, '-', '(' or '{', but found '+'",
+                },
+            ]
+        "#]]
+        .assert_debug_eq(&Analysis::new("let a = + 1").diagnostics());
+    }
+
+    #[test]
+    fn globals_in_top_level_code_resolve_to_latest_definition() {
+        check_definition("let a = 1\nlet a = a + 1\na$0", expect!["Global a 14..15"]);
+        check_definition("let a = 1\nlet a = a$0 + 1", expect!["Global a 4..5"]);
+    }
+
+    #[test]
+    fn globals_in_functions_resolve_to_first_definition() {
+        check_definition(
+            "fn f() { g$0 }\nlet g = 1\nlet g = 2",
+            expect!["Global g 17..18"],
+        );
+    }
+
+    #[test]
+    fn locals_and_params_shadow_globals() {
+        check_definition("let x = 1\nfn f(x) { x$0 }", expect!["Param x 15..16"]);
+        check_definition(
+            "let x = 1\n{ let x = 2 { x$0 } }",
+            expect!["Local x 16..17"],
+        );
+        check_definition("{ let x = 2 }\nx$0", expect!["unresolved"]);
+    }
+
+    #[test]
+    fn functions_do_not_see_caller_locals() {
+        check_definition("{ let y = 1 fn f() { y$0 } }", expect!["unresolved"]);
+    }
+
+    #[test]
+    fn qualified_names_resolve_to_their_import() {
+        check_definition(
+            "import \"lib/m.ff\" as m\nm$0.value",
+            expect!["Module m 21..22"],
+        );
+        check_definition("import \"lib/m.ff\" as m\nm.val$0ue", expect!["unresolved"]);
+        check_definition(
+            "import \"lib/m.ff\" as m\nuse m.f\nf$0(1)",
+            expect!["Imported f 29..30"],
+        );
+    }
+
+    #[test]
+    fn calls_resolve_to_last_function_definition() {
+        check_definition(
+            "f$0(1)\nfn f(x) { x }\nfn f(x) { x * 2 }",
+            expect!["Fn f 22..23"],
+        );
+    }
+
+    #[test]
+    fn find_references_from_definition() {
+        check_references(
+            "let a$0 = 1\nfn f(a) { a }\na + { let a = 2 a } + a",
+            expect![[r#"
+                [
+                    4..5,
+                    24..25,
+                    46..47,
+                ]
+            "#]],
+        );
+    }
+
+    #[test]
+    fn find_references_from_call() {
+        check_references(
+            "fn add(x, y) { x + y }\nadd(1, add$0(2, 3))",
+            expect![[r#"
+                [
+                    3..6,
+                    23..26,
+                    30..33,
+                ]
+            "#]],
+        );
+    }
+}
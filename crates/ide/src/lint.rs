@@ -0,0 +1,319 @@
+mod rules;
+
+use crate::resolve::Resolutions;
+use crate::Analysis;
+use std::collections::HashMap;
+use std::fmt;
+use syntax::{SyntaxKind, SyntaxNode, TextEdit};
+use text_size::{TextRange, TextSize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    Allow,
+    Warning,
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Allow => write!(f, "allow"),
+            Self::Warning => write!(f, "warning"),
+            Self::Error => write!(f, "error"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Lint {
+    pub code: &'static str,
+    pub name: &'static str,
+    pub severity: Severity,
+    pub range: TextRange,
+    pub message: String,
+    /// The edits that fix the problem, if it can be fixed automatically.
+    pub fix: Option<Vec<TextEdit>>,
+}
+
+/// A problem found by a rule, before its severity is worked out.
+pub(crate) struct Finding {
+    pub(crate) range: TextRange,
+    pub(crate) message: String,
+    pub(crate) fix: Option<Vec<TextEdit>>,
+}
+
+pub(crate) struct LintContext<'a> {
+    pub(crate) root: SyntaxNode,
+    pub(crate) resolutions: &'a Resolutions,
+    directives: Vec<Directive>,
+}
+
+pub(crate) trait Rule {
+    fn code(&self) -> &'static str;
+
+    /// The name used to allow or deny the rule in comments.
+    fn name(&self) -> &'static str;
+
+    fn default_severity(&self) -> Severity {
+        Severity::Warning
+    }
+
+    fn check(&self, ctx: &LintContext<'_>, findings: &mut Vec<Finding>);
+}
+
+const RULES: &[&dyn Rule] = &[
+    &rules::UnusedVariable,
+    &rules::ShadowedBinding,
+    &rules::RedundantParens,
+    &rules::DoubleNegation,
+    &rules::SelfAssignment,
+    &rules::UnknownRule,
+];
+
+/// The code and name of every rule.
+pub fn lint_rules() -> impl Iterator<Item = (&'static str, &'static str)> {
+    RULES.iter().map(|rule| (rule.code(), rule.name()))
+}
+
+/// Runs every rule over the file, in source order.
+///
+/// `levels` overrides the default severities of rules, keyed by their name or code. A
+/// `# felix-flow: allow(rule)` comment turns a rule off from the line it's on to the end of the
+/// enclosing block, or of the file at the top level. `warn(rule)` and `deny(rule)` set the
+/// severity the same way, and several rules can be listed at once. Rules are named by either
+/// their name or their code, and naming one that doesn't exist is itself a lint.
+pub(crate) fn lints(analysis: &Analysis, levels: &HashMap<String, Severity>) -> Vec<Lint> {
+    let root = analysis.syntax();
+    let ctx = LintContext {
+        directives: directives(&root),
+        root,
+        resolutions: &analysis.resolutions,
+    };
+
+    let mut lints = Vec::new();
+
+    for rule in RULES {
+        let mut findings = Vec::new();
+        rule.check(&ctx, &mut findings);
+
+        for finding in findings {
+            // Later directives are either further in or further down, so they take precedence.
+            let severity = ctx
+                .directives
+                .iter()
+                .rev()
+                .find(|directive| {
+                    directive.scope.contains(finding.range.start())
+                        && directive
+                            .rules
+                            .iter()
+                            .any(|(name, _)| name == rule.name() || name == rule.code())
+                })
+                .map(|directive| directive.severity)
+                .or_else(|| levels.get(rule.name()).or(levels.get(rule.code())).copied())
+                .unwrap_or(rule.default_severity());
+
+            if severity != Severity::Allow {
+                lints.push(Lint {
+                    code: rule.code(),
+                    name: rule.name(),
+                    severity,
+                    range: finding.range,
+                    message: finding.message,
+                    fix: finding.fix,
+                });
+            }
+        }
+    }
+
+    lints.sort_by_key(|lint| (lint.range.start(), lint.code));
+    lints
+}
+
+struct Directive {
+    scope: TextRange,
+    severity: Severity,
+    /// The rules as they're named, along with where.
+    rules: Vec<(String, TextRange)>,
+}
+
+fn is_rule(name: &str) -> bool {
+    RULES
+        .iter()
+        .any(|rule| name == rule.name() || name == rule.code())
+}
+
+fn directives(root: &SyntaxNode) -> Vec<Directive> {
+    let text = root.to_string();
+
+    root.descendants_with_tokens()
+        .filter_map(|element| element.into_token())
+        .filter(|token| token.kind() == SyntaxKind::Comment)
+        .filter_map(|comment| {
+            let start = comment.text_range().start();
+            let directive = comment
+                .text()
+                .strip_prefix('#')?
+                .trim()
+                .strip_prefix("felix-flow:")?;
+            let (level, rules) = directive.trim().split_once('(')?;
+
+            let severity = match level.trim_end() {
+                "allow" => Severity::Allow,
+                "warn" => Severity::Warning,
+                "deny" => Severity::Error,
+                _ => return None,
+            };
+
+            // Work out where each rule is from how much of the comment comes after it.
+            let mut offset = start + TextSize::of(comment.text()) - TextSize::of(rules);
+            let rules = rules
+                .strip_suffix(')')?
+                .split(',')
+                .map(|rule| {
+                    let name = rule.trim();
+                    let name_start = offset + TextSize::of(rule) - TextSize::of(rule.trim_start());
+                    offset += TextSize::of(rule) + TextSize::of(',');
+
+                    (
+                        name.to_string(),
+                        TextRange::at(name_start, TextSize::of(name)),
+                    )
+                })
+                .collect();
+
+            let line_start = text[..usize::from(start)]
+                .rfind('\n')
+                .map_or(0, |idx| idx + 1);
+
+            // Trailing comments end up in the node before them, so the block that encloses a
+            // comment is the innermost one whose `}` comes after it.
+            let end = comment
+                .parent_ancestors()
+                .filter(|node| node.kind() == SyntaxKind::Block)
+                .filter_map(|block| {
+                    block
+                        .children_with_tokens()
+                        .find(|element| element.kind() == SyntaxKind::RBrace)
+                })
+                .map(|rbrace| rbrace.text_range().start())
+                .find(|&rbrace| rbrace > start)
+                .unwrap_or_else(|| root.text_range().end());
+
+            Some(Directive {
+                scope: TextRange::new(TextSize::try_from(line_start).unwrap(), end),
+                severity,
+                rules,
+            })
+        })
+        .collect()
+}
+
+/// The range of `node` without its trailing trivia.
+fn trimmed_range(node: &SyntaxNode) -> TextRange {
+    let mut tokens = node
+        .descendants_with_tokens()
+        .filter_map(|element| element.into_token())
+        .filter(|token| !token.kind().is_trivia());
+
+    match (tokens.next(), tokens.last()) {
+        (Some(first), Some(last)) => first.text_range().cover(last.text_range()),
+        (Some(first), None) => first.text_range(),
+        _ => TextRange::empty(node.text_range().start()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Analysis, Severity};
+    use expect_test::{expect, Expect};
+    use std::collections::HashMap;
+
+    pub(super) fn check(input: &str, expected: Expect) {
+        check_with_levels(input, &HashMap::new(), expected);
+    }
+
+    fn check_with_levels(input: &str, levels: &HashMap<String, Severity>, expected: Expect) {
+        let analysis = Analysis::new(input);
+
+        let actual: String = analysis
+            .lints_with_levels(levels)
+            .into_iter()
+            .map(|lint| {
+                let mut line = format!(
+                    "{}[{} {}] {:?} {:?}: {}",
+                    lint.severity,
+                    lint.code,
+                    lint.name,
+                    lint.range,
+                    &input[lint.range],
+                    lint.message
+                );
+                if let Some(fix) = lint.fix {
+                    let mut text = input.to_string();
+                    for edit in fix.iter().rev() {
+                        edit.apply(&mut text);
+                    }
+                    line.push_str(&format!("\n    fix: {:?}", text));
+                }
+                line + "\n"
+            })
+            .collect();
+
+        expected.assert_eq(&actual);
+    }
+
+    #[test]
+    fn allow_and_deny_rules_until_the_end_of_the_block() {
+        check(
+            "{\n    # felix-flow: allow(unused-variable)\n    let a = 1\n}\nlet b = 2",
+            expect![[r#"
+                warning[L001 unused-variable] 63..64 "b": variable 'b' is never used
+            "#]],
+        );
+        check(
+            "let a = 1 # felix-flow: deny(L001, self-assignment)\nlet b = 2",
+            expect![[r#"
+                error[L001 unused-variable] 4..5 "a": variable 'a' is never used
+                error[L001 unused-variable] 56..57 "b": variable 'b' is never used
+            "#]],
+        );
+    }
+
+    #[test]
+    fn later_directives_take_precedence() {
+        check(
+            "# felix-flow: allow(unused-variable)\n{\n    # felix-flow: warn(unused-variable)\n    let a = 1\n}\nlet b = 2",
+            expect![[r#"
+                warning[L001 unused-variable] 87..88 "a": variable 'a' is never used
+            "#]],
+        );
+    }
+
+    #[test]
+    fn comments_take_precedence_over_levels() {
+        let levels = HashMap::from([
+            ("unused-variable".to_string(), Severity::Error),
+            ("L002".to_string(), Severity::Allow),
+        ]);
+
+        check_with_levels(
+            "let a = 1\n{ let a = 2 }\n# felix-flow: warn(unused-variable)\nlet b = a",
+            &levels,
+            expect![[r#"
+                error[L001 unused-variable] 16..17 "a": variable 'a' is never used
+                warning[L001 unused-variable] 64..65 "b": variable 'b' is never used
+            "#]],
+        );
+    }
+
+    #[test]
+    fn ignore_other_comments() {
+        check(
+            "# felix-flow allow(unused-variable)\n# felix-flow: forbid(unused-variable)\nlet a = 1",
+            expect![[r#"
+                warning[L001 unused-variable] 78..79 "a": variable 'a' is never used
+            "#]],
+        );
+    }
+}
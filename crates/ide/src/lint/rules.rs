@@ -0,0 +1,343 @@
+use super::{is_rule, trimmed_range, Finding, LintContext, Rule};
+use crate::DefinitionKind;
+use ast::{AstNode, Expr, Stmt};
+use syntax::{SyntaxKind, SyntaxNode, TextEdit};
+use text_size::TextRange;
+
+pub(super) struct UnusedVariable;
+
+impl Rule for UnusedVariable {
+    fn code(&self) -> &'static str {
+        "L001"
+    }
+
+    fn name(&self) -> &'static str {
+        "unused-variable"
+    }
+
+    fn check(&self, ctx: &LintContext<'_>, findings: &mut Vec<Finding>) {
+        for (id, def) in ctx.resolutions.defs() {
+            if def.kind == DefinitionKind::Fn || ctx.resolutions.references(id).next().is_some() {
+                continue;
+            }
+
+            let (message, fix) = match def.kind {
+                DefinitionKind::Param => (format!("parameter '{}' is never used", def.name), None),
+                DefinitionKind::Module => (
+                    format!("module '{}' is never used", def.name),
+                    remove_stmt(&def.syntax),
+                ),
+                DefinitionKind::Imported => (
+                    format!("imported name '{}' is never used", def.name),
+                    remove_stmt(&def.syntax),
+                ),
+                _ => (
+                    format!("variable '{}' is never used", def.name),
+                    remove_stmt(&def.syntax),
+                ),
+            };
+
+            findings.push(Finding {
+                range: def.name_range,
+                message,
+                fix,
+            });
+        }
+    }
+}
+
+pub(super) struct ShadowedBinding;
+
+impl Rule for ShadowedBinding {
+    fn code(&self) -> &'static str {
+        "L002"
+    }
+
+    fn name(&self) -> &'static str {
+        "shadowed-binding"
+    }
+
+    fn check(&self, ctx: &LintContext<'_>, findings: &mut Vec<Finding>) {
+        for &(id, shadowed) in ctx.resolutions.shadowed() {
+            let def = ctx.resolutions.def(id);
+            let kind = match ctx.resolutions.def(shadowed).kind {
+                DefinitionKind::Global => "global",
+                DefinitionKind::Local => "local variable",
+                DefinitionKind::Param => "parameter",
+                DefinitionKind::Imported => "imported name",
+                DefinitionKind::Fn | DefinitionKind::Module => {
+                    unreachable!("functions and modules aren't variables")
+                }
+            };
+
+            findings.push(Finding {
+                range: def.name_range,
+                message: format!("'{}' shadows a {} of the same name", def.name, kind),
+                fix: None,
+            });
+        }
+    }
+}
+
+pub(super) struct RedundantParens;
+
+impl Rule for RedundantParens {
+    fn code(&self) -> &'static str {
+        "L003"
+    }
+
+    fn name(&self) -> &'static str {
+        "redundant-parens"
+    }
+
+    fn check(&self, ctx: &LintContext<'_>, findings: &mut Vec<Finding>) {
+        let paren_exprs = ctx
+            .root
+            .descendants()
+            .filter(|node| node.kind() == SyntaxKind::ParenExpr && formatter::is_redundant(node));
+
+        for paren_expr in paren_exprs {
+            let fix = paren_expr
+                .children_with_tokens()
+                .filter(|element| matches!(element.kind(), SyntaxKind::LParen | SyntaxKind::RParen))
+                .map(|paren| TextEdit::delete(paren.text_range()))
+                .collect();
+
+            findings.push(Finding {
+                range: trimmed_range(&paren_expr),
+                message: "unnecessary parentheses".to_string(),
+                fix: Some(fix),
+            });
+        }
+    }
+}
+
+pub(super) struct DoubleNegation;
+
+impl Rule for DoubleNegation {
+    fn code(&self) -> &'static str {
+        "L004"
+    }
+
+    fn name(&self) -> &'static str {
+        "double-negation"
+    }
+
+    fn check(&self, ctx: &LintContext<'_>, findings: &mut Vec<Finding>) {
+        for expr in ctx.root.descendants().filter_map(Expr::cast) {
+            // `a - -b` becomes `a + b`, and `- -a` becomes `a`.
+            let (message, first_op, negated) = match &expr {
+                Expr::InfixExpr(infix_expr) => (
+                    "subtracting a negated value; add it instead",
+                    infix_expr.op().filter(|op| op.kind() == SyntaxKind::Minus),
+                    infix_expr.rhs(),
+                ),
+                Expr::PrefixExpr(prefix_expr) => (
+                    "negating a negated value",
+                    prefix_expr.op(),
+                    prefix_expr.expr(),
+                ),
+                _ => continue,
+            };
+
+            let (Some(first_op), Some(Expr::PrefixExpr(negated))) = (first_op, negated) else {
+                continue;
+            };
+            let (Some(op), Some(operand)) = (negated.op(), negated.expr()) else {
+                continue;
+            };
+
+            let fix = if expr.syntax().kind() == SyntaxKind::InfixExpr {
+                vec![
+                    TextEdit::replace(first_op.text_range(), "+"),
+                    TextEdit::delete(TextRange::new(
+                        op.text_range().start(),
+                        operand.syntax().text_range().start(),
+                    )),
+                ]
+            } else {
+                vec![TextEdit::delete(TextRange::new(
+                    first_op.text_range().start(),
+                    operand.syntax().text_range().start(),
+                ))]
+            };
+
+            findings.push(Finding {
+                range: trimmed_range(expr.syntax()),
+                message: message.to_string(),
+                fix: Some(fix),
+            });
+        }
+    }
+}
+
+pub(super) struct SelfAssignment;
+
+impl Rule for SelfAssignment {
+    fn code(&self) -> &'static str {
+        "L005"
+    }
+
+    fn name(&self) -> &'static str {
+        "self-assignment"
+    }
+
+    fn check(&self, ctx: &LintContext<'_>, findings: &mut Vec<Finding>) {
+        for node in ctx.root.descendants() {
+            let Some(Stmt::VariableDef(variable_def)) = Stmt::cast(node) else {
+                continue;
+            };
+            let (Some(name), Some(Expr::VariableRef(value))) =
+                (variable_def.name(), variable_def.value())
+            else {
+                continue;
+            };
+
+            if value
+                .name()
+                .is_some_and(|value| value.text() == name.text())
+            {
+                findings.push(Finding {
+                    range: trimmed_range(variable_def.syntax()),
+                    message: format!("'{}' is assigned to itself", name.text()),
+                    fix: remove_stmt(variable_def.syntax()),
+                });
+            }
+        }
+    }
+}
+
+pub(super) struct UnknownRule;
+
+impl Rule for UnknownRule {
+    fn code(&self) -> &'static str {
+        "L006"
+    }
+
+    fn name(&self) -> &'static str {
+        "unknown-rule"
+    }
+
+    fn check(&self, ctx: &LintContext<'_>, findings: &mut Vec<Finding>) {
+        for directive in &ctx.directives {
+            for (name, range) in &directive.rules {
+                if !is_rule(name) {
+                    findings.push(Finding {
+                        range: *range,
+                        message: format!("unknown lint rule '{}'", name),
+                        fix: None,
+                    });
+                }
+            }
+        }
+    }
+}
+
+/// Deletes `stmt` along with the whitespace after it. Comments aren't deleted, so statements with
+/// any are left alone, as is the last statement of a block, which gives the block its value.
+fn remove_stmt(stmt: &SyntaxNode) -> Option<Vec<TextEdit>> {
+    let next = stmt.next_sibling()?;
+
+    if stmt
+        .descendants_with_tokens()
+        .any(|element| element.kind() == SyntaxKind::Comment)
+    {
+        return None;
+    }
+
+    Some(vec![TextEdit::delete(TextRange::new(
+        stmt.text_range().start(),
+        next.text_range().start(),
+    ))])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::check;
+    use expect_test::expect;
+
+    #[test]
+    fn unused_variables() {
+        check(
+            "let a = 1\nlet c = 2\nfn f(x, y) { let z = x z }\nlet b = f(a, 2)",
+            expect![[r#"
+                warning[L001 unused-variable] 14..15 "c": variable 'c' is never used
+                    fix: "let a = 1\nfn f(x, y) { let z = x z }\nlet b = f(a, 2)"
+                warning[L001 unused-variable] 28..29 "y": parameter 'y' is never used
+                warning[L001 unused-variable] 51..52 "b": variable 'b' is never used
+            "#]],
+        );
+    }
+
+    #[test]
+    fn unused_imports() {
+        check(
+            "import \"a.ff\" as a\nimport \"b.ff\" as b\nuse b.x\nuse b.y\nlet z = a.f(y)\nz",
+            expect![[r#"
+                warning[L001 unused-variable] 44..45 "x": imported name 'x' is never used
+                    fix: "import \"a.ff\" as a\nimport \"b.ff\" as b\nuse b.y\nlet z = a.f(y)\nz"
+            "#]],
+        );
+    }
+
+    #[test]
+    fn shadowed_bindings() {
+        check(
+            "let a = 1\nfn f(a) { { let a = 2 a } + a }\nlet a = f(a)\na",
+            expect![[r#"
+                warning[L002 shadowed-binding] 15..16 "a": 'a' shadows a global of the same name
+                warning[L002 shadowed-binding] 26..27 "a": 'a' shadows a parameter of the same name
+                warning[L002 shadowed-binding] 46..47 "a": 'a' shadows a global of the same name
+            "#]],
+        );
+    }
+
+    #[test]
+    fn redundant_parens() {
+        check(
+            "let a = (1) + (2 * 3)\n(a - 1) * 2",
+            expect![[r#"
+            warning[L003 redundant-parens] 8..11 "(1)": unnecessary parentheses
+                fix: "let a = 1 + (2 * 3)\n(a - 1) * 2"
+        "#]],
+        );
+    }
+
+    #[test]
+    fn double_negation() {
+        check(
+            "let a = 1\nlet b = a - -1\nlet c = - -b\nc",
+            expect![[r#"
+                warning[L004 double-negation] 18..24 "a - -1": subtracting a negated value; add it instead
+                    fix: "let a = 1\nlet b = a + 1\nlet c = - -b\nc"
+                warning[L004 double-negation] 33..37 "- -b": negating a negated value
+                    fix: "let a = 1\nlet b = a - -1\nlet c = b\nc"
+            "#]],
+        );
+    }
+
+    #[test]
+    fn self_assignment() {
+        check(
+            "let a = 1\nfn f() { let a = a\n    a }\nlet a = a # copy\nf() + a",
+            expect![[r#"
+                warning[L005 self-assignment] 19..28 "let a = a": 'a' is assigned to itself
+                    fix: "let a = 1\nfn f() { a }\nlet a = a # copy\nf() + a"
+                warning[L002 shadowed-binding] 23..24 "a": 'a' shadows a global of the same name
+                warning[L005 self-assignment] 37..46 "let a = a": 'a' is assigned to itself
+                warning[L002 shadowed-binding] 41..42 "a": 'a' shadows a global of the same name
+            "#]],
+        );
+    }
+
+    #[test]
+    fn unknown_rules() {
+        check(
+            "# felix-flow: allow(unused-variables,L001)\nlet a = 1 # felix-flow: deny( L007 , L006)",
+            expect![[r#"
+                warning[L006 unknown-rule] 20..36 "unused-variables": unknown lint rule 'unused-variables'
+                error[L006 unknown-rule] 73..77 "L007": unknown lint rule 'L007'
+            "#]],
+        );
+    }
+}
@@ -0,0 +1,233 @@
+use crate::resolve::{DefinitionKind, Resolutions};
+use crate::Analysis;
+use ast::AstNode;
+use lexer::{Lexer, TokenKind};
+use std::fmt;
+use syntax::TextEdit;
+use text_size::{TextRange, TextSize};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RenameError {
+    NothingToRename,
+    Keyword(String),
+    InvalidName(String),
+    /// The name comes from another module, and renaming it here would import something else.
+    Imported(String),
+    /// The reference at `reference` would refer to `definition`, or to nothing, instead.
+    Conflict {
+        reference: TextRange,
+        definition: Option<TextRange>,
+    },
+}
+
+impl fmt::Display for RenameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NothingToRename => write!(f, "there is no variable or function to rename here"),
+            Self::Keyword(name) => write!(f, "'{}' is a keyword", name),
+            Self::InvalidName(name) => write!(f, "'{}' is not a valid name", name),
+            Self::Imported(name) => write!(f, "'{}' is defined in another module", name),
+            Self::Conflict {
+                reference,
+                definition: Some(definition),
+            } => write!(
+                f,
+                "the name at {:?} would refer to the definition at {:?} instead",
+                reference, definition
+            ),
+            Self::Conflict {
+                reference,
+                definition: None,
+            } => write!(
+                f,
+                "the name at {:?} would no longer refer to anything",
+                reference
+            ),
+        }
+    }
+}
+
+impl std::error::Error for RenameError {}
+
+/// Renames the definition at `offset` along with all of its references.
+///
+/// Rather than working out every way a new name could clash with others, the renamed text is
+/// resolved again, and the rename is refused if any name in it would refer to a different
+/// definition than before.
+pub(crate) fn rename(
+    analysis: &Analysis,
+    offset: TextSize,
+    new_name: &str,
+) -> Result<Vec<TextEdit>, RenameError> {
+    check_name(new_name)?;
+
+    let resolutions = &analysis.resolutions;
+    let id = resolutions
+        .def_at(offset)
+        .ok_or(RenameError::NothingToRename)?;
+
+    if resolutions.def(id).kind == DefinitionKind::Imported {
+        return Err(RenameError::Imported(resolutions.def(id).name.clone()));
+    }
+
+    let mut ranges: Vec<_> = resolutions.references(id).collect();
+    ranges.push(resolutions.def(id).name_range);
+    ranges.sort_by_key(|range| range.start());
+
+    let edits: Vec<_> = ranges
+        .iter()
+        .map(|range| TextEdit::replace(*range, new_name))
+        .collect();
+
+    let mut text = analysis.syntax().to_string();
+    for edit in edits.iter().rev() {
+        edit.apply(&mut text);
+    }
+
+    // Only names change, so the new tree has the same shape, and its definitions and references
+    // come in the same order.
+    let parse = parser::parse(&text);
+    let renamed = Resolutions::new(&ast::Root::cast(parse.syntax()).unwrap());
+
+    for (before, after) in resolutions.refs().iter().zip(renamed.refs()) {
+        if before.def != after.def {
+            return Err(RenameError::Conflict {
+                reference: before.range,
+                definition: after.def.map(|def| resolutions.def(def).name_range),
+            });
+        }
+    }
+
+    Ok(edits)
+}
+
+fn check_name(name: &str) -> Result<(), RenameError> {
+    // The lexer can't handle characters outside the language.
+    if !name.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return Err(RenameError::InvalidName(name.to_string()));
+    }
+
+    let kinds: Vec<_> = Lexer::new(name).map(|token| token.kind).collect();
+
+    match kinds.as_slice() {
+        [TokenKind::Ident] => Ok(()),
+        [TokenKind::LetKw
+        | TokenKind::FnKw
+        | TokenKind::ImportKw
+        | TokenKind::AsKw
+        | TokenKind::UseKw] => Err(RenameError::Keyword(name.to_string())),
+        _ => Err(RenameError::InvalidName(name.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::analysis_at;
+    use expect_test::{expect, Expect};
+
+    fn check(input: &str, new_name: &str, expected: Expect) {
+        let (analysis, offset) = analysis_at(input);
+
+        let actual = match analysis.rename(offset, new_name) {
+            Ok(edits) => {
+                let mut text = analysis.syntax().to_string();
+                for edit in edits.iter().rev() {
+                    edit.apply(&mut text);
+                }
+                text
+            }
+            Err(e) => format!("error: {}", e),
+        };
+
+        expected.assert_eq(&actual);
+    }
+
+    #[test]
+    fn rename_variable_and_its_references() {
+        check(
+            "let a$0 = 1\nfn f(a) { a }\na + { let a = 2 a } + a",
+            "total",
+            expect![[r#"
+                let total = 1
+                fn f(a) { a }
+                total + { let a = 2 a } + total"#]],
+        );
+    }
+
+    #[test]
+    fn rename_function_from_call() {
+        check(
+            "fn add(x, y) { x + y }\nadd$0(1, 2)",
+            "sum",
+            expect![[r#"
+                fn sum(x, y) { x + y }
+                sum(1, 2)"#]],
+        );
+    }
+
+    #[test]
+    fn refuse_keywords_and_invalid_names() {
+        check("let a$0 = 1", "let", expect!["error: 'let' is a keyword"]);
+        check("let a$0 = 1", "as", expect!["error: 'as' is a keyword"]);
+        check(
+            "let a$0 = 1",
+            "1a",
+            expect!["error: '1a' is not a valid name"],
+        );
+        check(
+            "let a$0 = 1",
+            "a?",
+            expect!["error: 'a?' is not a valid name"],
+        );
+        check(
+            "let a = 1\n1 +$0 2",
+            "b",
+            expect!["error: there is no variable or function to rename here"],
+        );
+    }
+
+    #[test]
+    fn rename_modules_but_not_imported_names() {
+        check(
+            "import \"lib/m.ff\" as m\nuse m.f\nm$0.x + f(1)",
+            "lib",
+            expect![[r#"
+                import "lib/m.ff" as lib
+                use lib.f
+                lib.x + f(1)"#]],
+        );
+        check(
+            "import \"lib/m.ff\" as m\nuse m.f\nm.x + f$0(1)",
+            "g",
+            expect!["error: 'f' is defined in another module"],
+        );
+    }
+
+    #[test]
+    fn refuse_capturing_other_references() {
+        check(
+            "let a$0 = 1\nlet b = 2\n{ a + b }",
+            "b",
+            expect!["error: the name at 22..23 would refer to the definition at 14..15 instead"],
+        );
+        check(
+            "let a$0 = 1\nb",
+            "b",
+            expect!["error: the name at 10..11 would refer to the definition at 4..5 instead"],
+        );
+    }
+
+    #[test]
+    fn refuse_being_shadowed() {
+        check(
+            "let a$0 = 1\n{ let b = 2 a }",
+            "b",
+            expect!["error: the name at 22..23 would refer to the definition at 16..17 instead"],
+        );
+        check(
+            "fn f$0() { 1 }\nfn g() { 2 }\nf()",
+            "g",
+            expect!["error: the name at 26..27 would refer to the definition at 16..17 instead"],
+        );
+    }
+}
@@ -0,0 +1,307 @@
+use ast::{AstNode, Expr, Stmt};
+use std::collections::HashMap;
+use syntax::{SyntaxNode, SyntaxToken};
+use text_size::{TextRange, TextSize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DefinitionKind {
+    Global,
+    Local,
+    Param,
+    Fn,
+    /// The alias of an imported module.
+    Module,
+    /// A name brought in from a module by `use`.
+    Imported,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Definition {
+    pub name: String,
+    pub kind: DefinitionKind,
+    pub name_range: TextRange,
+    /// The `VariableDef`, `Param`, `FnDef`, `Import` or `Use` node.
+    pub syntax: SyntaxNode,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) struct DefId(usize);
+
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct Reference {
+    pub(crate) range: TextRange,
+    pub(crate) def: Option<DefId>,
+}
+
+/// Links every name in a file to its definition, following the same scoping rules as
+/// evaluation.
+///
+/// Top-level code sees the latest preceding definition of a global, while function bodies,
+/// which can run at any point, see the first one. Calls refer to the last definition of a
+/// function anywhere in the file, since later definitions replace earlier ones.
+///
+/// Imports and uses apply to the whole file. In `m.value`, `m` refers to the import, but `value`
+/// lives in another file and isn't resolved.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Resolutions {
+    defs: Vec<Definition>,
+    refs: Vec<Reference>,
+    /// Pairs of a variable and the one its name hid when it was defined.
+    shadowed: Vec<(DefId, DefId)>,
+}
+
+impl Resolutions {
+    pub(crate) fn new(root: &ast::Root) -> Self {
+        let mut resolver = Resolver::default();
+        resolver.declare(root);
+
+        for stmt in root.stmts() {
+            resolver.stmt(stmt, true);
+        }
+
+        resolver.resolutions
+    }
+
+    pub(crate) fn def(&self, id: DefId) -> &Definition {
+        &self.defs[id.0]
+    }
+
+    pub(crate) fn defs(&self) -> impl Iterator<Item = (DefId, &Definition)> {
+        self.defs
+            .iter()
+            .enumerate()
+            .map(|(idx, def)| (DefId(idx), def))
+    }
+
+    pub(crate) fn refs(&self) -> &[Reference] {
+        &self.refs
+    }
+
+    pub(crate) fn shadowed(&self) -> &[(DefId, DefId)] {
+        &self.shadowed
+    }
+
+    /// The definition whose name, or one of whose references, is at `offset`.
+    pub(crate) fn def_at(&self, offset: TextSize) -> Option<DefId> {
+        let defined = self
+            .defs
+            .iter()
+            .position(|def| def.name_range.contains_inclusive(offset))
+            .map(DefId);
+
+        defined.or_else(|| {
+            self.refs
+                .iter()
+                .find(|reference| reference.range.contains_inclusive(offset))
+                .and_then(|reference| reference.def)
+        })
+    }
+
+    pub(crate) fn references(&self, id: DefId) -> impl Iterator<Item = TextRange> + '_ {
+        self.refs
+            .iter()
+            .filter(move |reference| reference.def == Some(id))
+            .map(|reference| reference.range)
+    }
+}
+
+#[derive(Default)]
+struct Resolver {
+    resolutions: Resolutions,
+    /// Definitions made before walking the tree, keyed by the range of their name.
+    declared: HashMap<TextRange, DefId>,
+    fns: HashMap<String, DefId>,
+    modules: HashMap<String, DefId>,
+    imported: HashMap<String, DefId>,
+    first_globals: HashMap<String, DefId>,
+    globals: Vec<(String, DefId)>,
+    locals: Vec<(String, DefId)>,
+    in_fn: bool,
+}
+
+impl Resolver {
+    /// Defines every function, import and global up front, since references to them can come
+    /// before their definitions.
+    fn declare(&mut self, root: &ast::Root) {
+        for node in root.syntax().descendants() {
+            match Stmt::cast(node) {
+                Some(Stmt::FnDef(fn_def)) => {
+                    if let Some(name) = fn_def.name() {
+                        let id = self.define(&name, DefinitionKind::Fn, fn_def.syntax());
+                        self.fns.insert(name.text().to_string(), id);
+                    }
+                }
+                Some(Stmt::Import(import)) => {
+                    if let Some(alias) = import.alias() {
+                        let id = self.define(&alias, DefinitionKind::Module, import.syntax());
+                        self.modules.insert(alias.text().to_string(), id);
+                    }
+                }
+                Some(Stmt::Use(use_)) => {
+                    if let Some(name) = use_.name() {
+                        let id = self.define(&name, DefinitionKind::Imported, use_.syntax());
+                        self.imported.insert(name.text().to_string(), id);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        for stmt in root.stmts() {
+            if let Stmt::VariableDef(variable_def) = stmt {
+                if let Some(name) = variable_def.name() {
+                    let id = self.define(&name, DefinitionKind::Global, variable_def.syntax());
+                    self.first_globals
+                        .entry(name.text().to_string())
+                        .or_insert(id);
+                }
+            }
+        }
+    }
+
+    fn define(&mut self, name: &SyntaxToken, kind: DefinitionKind, syntax: &SyntaxNode) -> DefId {
+        let id = DefId(self.resolutions.defs.len());
+
+        self.resolutions.defs.push(Definition {
+            name: name.text().to_string(),
+            kind,
+            name_range: name.text_range(),
+            syntax: syntax.clone(),
+        });
+        self.declared.insert(name.text_range(), id);
+
+        id
+    }
+
+    fn stmt(&mut self, stmt: Stmt, top_level: bool) {
+        match stmt {
+            Stmt::Import(_) => {}
+            Stmt::Use(use_) => self.qualifier(use_.module()),
+            Stmt::VariableDef(variable_def) => {
+                if let Some(value) = variable_def.value() {
+                    self.expr(value);
+                }
+
+                let Some(name) = variable_def.name() else {
+                    return;
+                };
+
+                let shadowed = self.lookup_variable(name.text());
+
+                let id = if top_level {
+                    let id = self.declared[&name.text_range()];
+                    self.globals.push((name.text().to_string(), id));
+                    id
+                } else {
+                    let id = self.define(&name, DefinitionKind::Local, variable_def.syntax());
+                    self.locals.push((name.text().to_string(), id));
+                    id
+                };
+
+                if let Some(shadowed) = shadowed {
+                    self.resolutions.shadowed.push((id, shadowed));
+                }
+            }
+            Stmt::FnDef(fn_def) => {
+                let outer_locals = std::mem::take(&mut self.locals);
+                let outer_in_fn = std::mem::replace(&mut self.in_fn, true);
+
+                for param in fn_def.param_list().iter().flat_map(|list| list.params()) {
+                    if let Some(name) = param.name() {
+                        let shadowed = self.lookup_variable(name.text());
+                        let id = self.define(&name, DefinitionKind::Param, param.syntax());
+                        self.locals.push((name.text().to_string(), id));
+
+                        if let Some(shadowed) = shadowed {
+                            self.resolutions.shadowed.push((id, shadowed));
+                        }
+                    }
+                }
+
+                if let Some(body) = fn_def.body() {
+                    self.block(body.stmts());
+                }
+
+                self.locals = outer_locals;
+                self.in_fn = outer_in_fn;
+            }
+            Stmt::Expr(expr) => self.expr(expr),
+        }
+    }
+
+    fn block(&mut self, stmts: impl Iterator<Item = Stmt>) {
+        let scope_start = self.locals.len();
+
+        for stmt in stmts {
+            self.stmt(stmt, false);
+        }
+
+        self.locals.truncate(scope_start);
+    }
+
+    fn expr(&mut self, expr: Expr) {
+        match expr {
+            Expr::VariableRef(variable_ref) => {
+                if variable_ref.qualifier().is_some() {
+                    self.qualifier(variable_ref.qualifier());
+                } else if let Some(name) = variable_ref.name() {
+                    let def = self.lookup_variable(name.text());
+                    self.refer(&name, def);
+                }
+            }
+            Expr::CallExpr(call_expr) => {
+                if call_expr.qualifier().is_some() {
+                    self.qualifier(call_expr.qualifier());
+                } else if let Some(name) = call_expr.name() {
+                    let def = self.fns.get(name.text()).or(self.imported.get(name.text()));
+                    self.refer(&name, def.copied());
+                }
+
+                for arg in call_expr.args() {
+                    self.expr(arg);
+                }
+            }
+            Expr::Block(block) => self.block(block.stmts()),
+            Expr::InfixExpr(_) | Expr::Literal(_) | Expr::ParenExpr(_) | Expr::PrefixExpr(_) => {
+                for child in expr.syntax().children().filter_map(Expr::cast) {
+                    self.expr(child);
+                }
+            }
+        }
+    }
+
+    fn lookup_variable(&self, name: &str) -> Option<DefId> {
+        let find = |scope: &[(String, DefId)]| {
+            scope
+                .iter()
+                .rev()
+                .find(|(defined, _)| defined == name)
+                .map(|(_, id)| *id)
+        };
+
+        find(&self.locals)
+            .or_else(|| {
+                if self.in_fn {
+                    self.first_globals.get(name).copied()
+                } else {
+                    find(&self.globals)
+                }
+            })
+            .or_else(|| self.imported.get(name).copied())
+    }
+
+    /// Refers to the module named before the dot in `m.value` or `use m.value`.
+    fn qualifier(&mut self, module: Option<SyntaxToken>) {
+        if let Some(module) = module {
+            let def = self.modules.get(module.text()).copied();
+            self.refer(&module, def);
+        }
+    }
+
+    fn refer(&mut self, name: &SyntaxToken, def: Option<DefId>) {
+        self.resolutions.refs.push(Reference {
+            range: name.text_range(),
+            def,
+        });
+    }
+}
@@ -0,0 +1,112 @@
+use ast::{AstNode, Stmt};
+use syntax::SyntaxNode;
+use text_size::TextRange;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolKind {
+    Variable,
+    Function,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Symbol {
+    pub name: String,
+    pub kind: SymbolKind,
+    pub range: TextRange,
+    pub name_range: TextRange,
+    pub children: Vec<Symbol>,
+}
+
+/// The variable and function definitions under `node`, nested under the definitions that
+/// contain them.
+pub(crate) fn symbols(node: &SyntaxNode) -> Vec<Symbol> {
+    let mut symbols = Vec::new();
+
+    for child in node.children() {
+        let (name, kind) = match Stmt::cast(child.clone()) {
+            Some(Stmt::VariableDef(variable_def)) => (variable_def.name(), SymbolKind::Variable),
+            Some(Stmt::FnDef(fn_def)) => (fn_def.name(), SymbolKind::Function),
+            _ => (None, SymbolKind::Variable),
+        };
+
+        match name {
+            Some(name) => symbols.push(Symbol {
+                name: name.text().to_string(),
+                kind,
+                range: trimmed_range(&child),
+                name_range: name.text_range(),
+                children: self::symbols(&child),
+            }),
+            None => symbols.extend(self::symbols(&child)),
+        }
+    }
+
+    symbols
+}
+
+/// The range of `node` without the trivia the parser attaches to its end.
+fn trimmed_range(node: &SyntaxNode) -> TextRange {
+    let end = node
+        .descendants_with_tokens()
+        .filter_map(|element| element.into_token())
+        .filter(|token| !token.kind().is_trivia())
+        .last()
+        .map_or(node.text_range().end(), |token| token.text_range().end());
+
+    TextRange::new(node.text_range().start(), end)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Analysis;
+    use expect_test::expect;
+
+    #[test]
+    fn nest_symbols_under_their_definitions() {
+        let analysis =
+            Analysis::new("let a = { let b = 1 b }\nfn f(x) { fn g() { 1 } let c = x c }");
+
+        expect![[r#"
+            [
+                Symbol {
+                    name: "a",
+                    kind: Variable,
+                    range: 0..23,
+                    name_range: 4..5,
+                    children: [
+                        Symbol {
+                            name: "b",
+                            kind: Variable,
+                            range: 10..19,
+                            name_range: 14..15,
+                            children: [],
+                        },
+                    ],
+                },
+                Symbol {
+                    name: "f",
+                    kind: Function,
+                    range: 24..60,
+                    name_range: 27..28,
+                    children: [
+                        Symbol {
+                            name: "g",
+                            kind: Function,
+                            range: 34..46,
+                            name_range: 37..38,
+                            children: [],
+                        },
+                        Symbol {
+                            name: "c",
+                            kind: Variable,
+                            range: 47..56,
+                            name_range: 51..52,
+                            children: [],
+                        },
+                    ],
+                },
+            ]
+        "#]]
+        .assert_debug_eq(&analysis.document_symbols());
+    }
+}
@@ -34,7 +34,7 @@ impl<'a> Iterator for Lexer<'a> {
         };
 
         Some(Self::Item {
-            kind: kind.unwrap(),
+            kind: kind.unwrap_or(TokenKind::Error),
             text,
             range,
         })
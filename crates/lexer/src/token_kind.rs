@@ -1,8 +1,11 @@
 use std::fmt::Display;
 
 use logos::Logos;
+use num_derive::{FromPrimitive, ToPrimitive};
 
-#[derive(Logos, Debug, PartialEq, Eq, Clone, Copy, PartialOrd, Ord, Hash)]
+#[derive(
+    Logos, Debug, PartialEq, Eq, Clone, Copy, PartialOrd, Ord, Hash, FromPrimitive, ToPrimitive,
+)]
 pub enum TokenKind {
     #[regex("[ \n]+")]
     Whitespace,
@@ -13,12 +16,24 @@ pub enum TokenKind {
     #[token("let")]
     LetKw,
 
+    #[token("import")]
+    ImportKw,
+
+    #[token("as")]
+    AsKw,
+
+    #[token("use")]
+    UseKw,
+
     #[regex("[A-Za-z][A-Za-z0-9]*")]
     Ident,
 
     #[regex("[0-9]+")]
     Number,
 
+    #[regex("\"[^\"\n]*\"")]
+    String,
+
     #[token("+")]
     Plus,
 
@@ -46,8 +61,17 @@ pub enum TokenKind {
     #[token(")")]
     RParen,
 
+    #[token(",")]
+    Comma,
+
+    #[token(".")]
+    Dot,
+
     #[regex("#.*")]
     Comment,
+
+    /// A character that doesn't start any token.
+    Error,
 }
 
 impl TokenKind {
@@ -62,8 +86,12 @@ impl Display for TokenKind {
             TokenKind::Whitespace => "whitespace",
             TokenKind::FnKw => "'fn'",
             TokenKind::LetKw => "'let'",
+            TokenKind::ImportKw => "'import'",
+            TokenKind::AsKw => "'as'",
+            TokenKind::UseKw => "'use'",
             TokenKind::Ident => "identifier",
             TokenKind::Number => "number",
+            TokenKind::String => "string",
             TokenKind::Plus => "'+'",
             TokenKind::Minus => "'-'",
             TokenKind::Star => "'*'",
@@ -71,9 +99,12 @@ impl Display for TokenKind {
             TokenKind::Equals => "'='",
             TokenKind::LParen => "'('",
             TokenKind::RParen => "')'",
+            TokenKind::Comma => "','",
+            TokenKind::Dot => "'.'",
             TokenKind::LBrace => "'{'",
             TokenKind::RBrace => "'}'",
             TokenKind::Comment => "comment",
+            TokenKind::Error => "unrecognized character",
         })
     }
 }
@@ -111,6 +142,21 @@ mod tests {
         check("let", TokenKind::LetKw);
     }
 
+    #[test]
+    fn lex_import_keyword() {
+        check("import", TokenKind::ImportKw);
+    }
+
+    #[test]
+    fn lex_as_keyword() {
+        check("as", TokenKind::AsKw);
+    }
+
+    #[test]
+    fn lex_use_keyword() {
+        check("use", TokenKind::UseKw);
+    }
+
     #[test]
     fn lex_alphabetic_ident() {
         check("abcd", TokenKind::Ident);
@@ -130,6 +176,11 @@ mod tests {
         check("123456", TokenKind::Number);
     }
 
+    #[test]
+    fn lex_string() {
+        check("\"lib/math.ff\"", TokenKind::String);
+    }
+
     #[test]
     fn lex_plus() {
         check("+", TokenKind::Plus);
@@ -175,8 +226,23 @@ mod tests {
         check(")", TokenKind::RParen);
     }
 
+    #[test]
+    fn lex_comma() {
+        check(",", TokenKind::Comma);
+    }
+
+    #[test]
+    fn lex_dot() {
+        check(".", TokenKind::Dot);
+    }
+
     #[test]
     fn lex_comment() {
         check("# foo", TokenKind::Comment);
     }
+
+    #[test]
+    fn lex_unrecognized_character() {
+        check("\t", TokenKind::Error);
+    }
 }
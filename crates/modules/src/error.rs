@@ -0,0 +1,90 @@
+use std::fmt;
+use std::path::PathBuf;
+use syntax::LineCol;
+
+/// A place in a file, shown as `path:line:column`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Location {
+    pub path: PathBuf,
+    pub line_col: LineCol,
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}:{}",
+            self.path.display(),
+            self.line_col.line + 1,
+            self.line_col.col + 1
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LoadError {
+    /// The file imported at `at` couldn't be read.
+    Io {
+        at: Location,
+        path: PathBuf,
+        message: String,
+    },
+    Parse(Vec<(Location, String)>),
+    /// The import at `at` leads back to a module that is still being loaded. The chain starts
+    /// and ends with that module.
+    Cycle {
+        at: Location,
+        chain: Vec<PathBuf>,
+    },
+    UnknownModule {
+        at: Location,
+        name: String,
+    },
+    UnknownName {
+        at: Location,
+        module: String,
+        name: String,
+    },
+    /// A name is both brought in by `use` and defined in the same file.
+    Conflict {
+        at: Location,
+        name: String,
+    },
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io { at, path, message } => {
+                write!(f, "{}: can't read '{}': {}", at, path.display(), message)
+            }
+            Self::Parse(errors) => {
+                for (idx, (at, message)) in errors.iter().enumerate() {
+                    if idx != 0 {
+                        writeln!(f)?;
+                    }
+                    write!(f, "{}: {}", at, message)?;
+                }
+                Ok(())
+            }
+            Self::Cycle { at, chain } => {
+                let chain: Vec<_> = chain
+                    .iter()
+                    .map(|path| path.display().to_string())
+                    .collect();
+                write!(f, "{}: import cycle: {}", at, chain.join(" -> "))
+            }
+            Self::UnknownModule { at, name } => write!(f, "{}: no module named '{}'", at, name),
+            Self::UnknownName { at, module, name } => {
+                write!(f, "{}: module '{}' has no '{}'", at, module, name)
+            }
+            Self::Conflict { at, name } => write!(
+                f,
+                "{}: '{}' is both used from a module and defined here",
+                at, name
+            ),
+        }
+    }
+}
+
+impl std::error::Error for LoadError {}
@@ -0,0 +1,599 @@
+mod error;
+mod link;
+
+pub use error::{LoadError, Location};
+
+use ast::AstNode;
+use hir::{Program, StmtKind};
+use std::collections::{HashMap, HashSet};
+use std::path::{Component, Path, PathBuf};
+use std::{fs, io};
+use syntax::LineIndex;
+use text_size::{TextRange, TextSize};
+
+type Reader = Box<dyn FnMut(&Path) -> io::Result<String>>;
+
+/// Loads the files a program imports and links them into it, so that the result can be run by
+/// any backend.
+///
+/// Each module is loaded once, and its statements run before those of the code that first
+/// imports it. Its globals and functions are renamed to `prefix_name`, with the prefix taken
+/// from the file name; names in the language can't contain `_`, so these never clash with
+/// anything else. The ranges of a module's statements are moved past the end of the code before
+/// it, so that they can be mapped to lines of the module with the [`LineIndex`] passed to
+/// [`Loader::link`].
+///
+/// A loader remembers the modules it has loaded and the imports of the code it has linked, so
+/// that the REPL can import a module on one line and use it on the next.
+pub struct Loader {
+    read: Reader,
+    modules: HashMap<PathBuf, Module>,
+    /// What the linked code can see, which builds up across calls to [`Loader::link`].
+    main: Scope,
+}
+
+#[derive(Debug, Clone)]
+struct Module {
+    prefix: String,
+    globals: HashSet<String>,
+    fns: HashSet<String>,
+}
+
+impl Module {
+    fn linked_name(&self, name: &str) -> String {
+        format!("{}_{}", self.prefix, name)
+    }
+}
+
+/// The names a file sees from other modules, and the ones it defines itself.
+#[derive(Debug, Clone, Default)]
+struct Scope {
+    /// Module aliases, mapped to the path of the module.
+    modules: HashMap<String, PathBuf>,
+    /// Names brought in by `use`, mapped to the names they're linked as.
+    uses: HashMap<String, String>,
+    defined: HashSet<String>,
+}
+
+/// The file being linked, used to point at problems in it.
+struct File<'a> {
+    path: &'a Path,
+    line_index: &'a LineIndex,
+    /// Where the file starts in the linked code.
+    start: TextSize,
+}
+
+impl File<'_> {
+    fn at(&self, range: TextRange) -> Location {
+        Location {
+            path: self.path.to_path_buf(),
+            line_col: self.line_index.line_col(range.start()),
+        }
+    }
+}
+
+impl Default for Loader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Loader {
+    /// A loader that reads modules from the file system.
+    pub fn new() -> Self {
+        Self::with_reader(|path| fs::read_to_string(path))
+    }
+
+    /// A loader that gets the text of modules from `read`.
+    pub fn with_reader(read: impl FnMut(&Path) -> io::Result<String> + 'static) -> Self {
+        Self {
+            read: Box::new(read),
+            modules: HashMap::new(),
+            main: Scope::default(),
+        }
+    }
+
+    /// Links `program`, lowered from the file at `path`, with the modules it imports, which
+    /// are found relative to the directory `path` is in. The result has no imports or uses
+    /// left, and only includes the modules that haven't been linked before.
+    ///
+    /// `line_index` is for the text of `path`, and is used to point at problems in it. The
+    /// text of each module linked in is appended to it, so that it covers the ranges of the
+    /// result.
+    pub fn link(
+        &mut self,
+        program: &Program,
+        path: &Path,
+        line_index: &mut LineIndex,
+    ) -> Result<Program, LoadError> {
+        let saved = (self.modules.clone(), self.main.clone());
+
+        let result = self.link_main(program, path, line_index);
+        if result.is_err() {
+            (self.modules, self.main) = saved;
+        }
+
+        result
+    }
+
+    fn link_main(
+        &mut self,
+        program: &Program,
+        path: &Path,
+        line_index: &mut LineIndex,
+    ) -> Result<Program, LoadError> {
+        let path = normalize(path);
+        let main_index = line_index.clone();
+        let file = File {
+            path: &path,
+            line_index: &main_index,
+            start: TextSize::from(0),
+        };
+
+        let mut linked = Program::default();
+        let mut scope = std::mem::take(&mut self.main);
+
+        self.import(
+            program,
+            &file,
+            &mut scope,
+            &mut vec![path.clone()],
+            &mut linked,
+            line_index,
+        )?;
+        link::link(program, &file, &self.modules, &scope, None, &mut linked)?;
+
+        self.main = scope;
+        Ok(linked)
+    }
+
+    /// Loads the modules `program` imports, and adds them and the names it uses from them to
+    /// `scope`. `stack` holds the modules being loaded, to catch import cycles.
+    fn import(
+        &mut self,
+        program: &Program,
+        file: &File<'_>,
+        scope: &mut Scope,
+        stack: &mut Vec<PathBuf>,
+        linked: &mut Program,
+        line_index: &mut LineIndex,
+    ) -> Result<(), LoadError> {
+        let dir = file.path.parent().unwrap_or(Path::new(""));
+
+        for import in &program.imports {
+            let path = normalize(&dir.join(&import.path));
+            self.load(&path, file.at(import.range), stack, linked, line_index)?;
+            scope.modules.insert(import.alias.clone(), path);
+        }
+
+        let defined: Vec<_> = definitions(program).collect();
+        scope
+            .defined
+            .extend(defined.iter().map(|(name, _)| name.to_string()));
+
+        for use_ in &program.uses {
+            let at = file.at(use_.range);
+
+            let Some(module) = scope.modules.get(&use_.module) else {
+                return Err(LoadError::UnknownModule {
+                    at,
+                    name: use_.module.clone(),
+                });
+            };
+            let module = &self.modules[module];
+
+            if !module.globals.contains(&use_.name) && !module.fns.contains(&use_.name) {
+                return Err(LoadError::UnknownName {
+                    at,
+                    module: use_.module.clone(),
+                    name: use_.name.clone(),
+                });
+            }
+            if scope.defined.contains(&use_.name) {
+                return Err(LoadError::Conflict {
+                    at,
+                    name: use_.name.clone(),
+                });
+            }
+
+            scope
+                .uses
+                .insert(use_.name.clone(), module.linked_name(&use_.name));
+        }
+
+        // Names used by earlier REPL lines can't be defined by later ones either.
+        if let Some((name, range)) = defined
+            .into_iter()
+            .find(|(name, _)| scope.uses.contains_key(*name))
+        {
+            return Err(LoadError::Conflict {
+                at: file.at(range),
+                name: name.to_string(),
+            });
+        }
+
+        Ok(())
+    }
+
+    fn load(
+        &mut self,
+        path: &Path,
+        at: Location,
+        stack: &mut Vec<PathBuf>,
+        linked: &mut Program,
+        line_index: &mut LineIndex,
+    ) -> Result<(), LoadError> {
+        if let Some(start) = stack.iter().position(|loading| loading == path) {
+            let mut chain = stack[start..].to_vec();
+            chain.push(path.to_path_buf());
+            return Err(LoadError::Cycle { at, chain });
+        }
+
+        if self.modules.contains_key(path) {
+            return Ok(());
+        }
+
+        let text = (self.read)(path).map_err(|e| LoadError::Io {
+            at,
+            path: path.to_path_buf(),
+            message: e.to_string(),
+        })?;
+        let file_index = LineIndex::new(&text);
+        let file = File {
+            path,
+            line_index: &file_index,
+            start: line_index.append(&text),
+        };
+
+        let parse = parser::parse(&text);
+        if !parse.errors().is_empty() {
+            return Err(LoadError::Parse(
+                parse
+                    .errors()
+                    .iter()
+                    .map(|error| (file.at(error.range()), error.message()))
+                    .collect(),
+            ));
+        }
+        let program = hir::lower(ast::Root::cast(parse.syntax()).unwrap());
+
+        let mut scope = Scope::default();
+        stack.push(path.to_path_buf());
+        self.import(&program, &file, &mut scope, stack, linked, line_index)?;
+        stack.pop();
+
+        let module = Module {
+            prefix: self.prefix(path),
+            globals: program
+                .stmts
+                .iter()
+                .filter_map(|stmt| match &stmt.kind {
+                    StmtKind::VariableDef { name, .. } => Some(name.clone()),
+                    StmtKind::Expr(_) => None,
+                })
+                .collect(),
+            fns: program
+                .fns
+                .iter()
+                .map(|fn_def| fn_def.name.clone())
+                .collect(),
+        };
+
+        link::link(
+            &program,
+            &file,
+            &self.modules,
+            &scope,
+            Some(&module.prefix),
+            linked,
+        )?;
+        self.modules.insert(path.to_path_buf(), module);
+
+        Ok(())
+    }
+
+    /// The file name reduced to letters and digits, numbered if another module has it already.
+    fn prefix(&self, path: &Path) -> String {
+        let stem: String = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy())
+            .unwrap_or_default()
+            .chars()
+            .filter(char::is_ascii_alphanumeric)
+            .collect();
+        let stem = if stem.is_empty() {
+            "module".to_string()
+        } else {
+            stem
+        };
+
+        let taken = |prefix: &str| self.modules.values().any(|module| module.prefix == prefix);
+        if !taken(&stem) {
+            return stem;
+        }
+
+        (2..)
+            .map(|n| format!("{}_{}", stem, n))
+            .find(|prefix| !taken(prefix))
+            .unwrap()
+    }
+}
+
+/// The globals and functions defined by `program`, with where they're defined.
+fn definitions(program: &Program) -> impl Iterator<Item = (&str, TextRange)> {
+    let globals = program.stmts.iter().filter_map(|stmt| match &stmt.kind {
+        StmtKind::VariableDef { name, .. } => Some((name.as_str(), stmt.range)),
+        StmtKind::Expr(_) => None,
+    });
+    let fns = program
+        .fns
+        .iter()
+        .map(|fn_def| (fn_def.name.as_str(), fn_def.range));
+
+    globals.chain(fns)
+}
+
+/// Removes `.` and `..` from `path` without looking at the file system, so that a module
+/// imported through different paths is only loaded once.
+pub fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir
+                if matches!(
+                    normalized.components().next_back(),
+                    Some(Component::Normal(_))
+                ) =>
+            {
+                normalized.pop();
+            }
+            _ => normalized.push(component),
+        }
+    }
+
+    normalized
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use eval::{Interpreter, Value};
+    use expect_test::{expect, Expect};
+
+    const MAIN: &str = "main.ff";
+
+    /// Links `main.ff` with the modules it imports, all taken from `files`, returning the line
+    /// index for the result too.
+    fn link(files: &[(&str, &str)]) -> Result<(Program, LineIndex), LoadError> {
+        let files: HashMap<PathBuf, String> = files
+            .iter()
+            .map(|(path, text)| (PathBuf::from(path), text.to_string()))
+            .collect();
+        let text = files[Path::new(MAIN)].clone();
+
+        let mut loader = Loader::with_reader(move |path| {
+            files
+                .get(path)
+                .cloned()
+                .ok_or_else(|| io::Error::from(io::ErrorKind::NotFound))
+        });
+
+        let parse = parser::parse(&text);
+        let program = hir::lower(ast::Root::cast(parse.syntax()).unwrap());
+        let mut line_index = LineIndex::new(&text);
+        let linked = loader.link(&program, Path::new(MAIN), &mut line_index)?;
+        Ok((linked, line_index))
+    }
+
+    fn check(files: &[(&str, &str)], expected: Expect) {
+        let (linked, _) = link(files).unwrap();
+        let value = Interpreter::new().run(&linked).unwrap();
+
+        expected.assert_eq(&format!("{}=> {}\n", linked, value));
+    }
+
+    fn check_error(files: &[(&str, &str)], expected: Expect) {
+        expected.assert_eq(&link(files).unwrap_err().to_string());
+    }
+
+    #[test]
+    fn link_qualified_and_used_names() {
+        check(
+            &[
+                (
+                    MAIN,
+                    "import \"lib/math.ff\" as math\nuse math.square\nsquare(math.two) + math.two",
+                ),
+                (
+                    "lib/math.ff",
+                    "import \"../util.ff\" as util\nlet two = util.one + 1\nfn square(x) { util.mul(x, x) }",
+                ),
+                ("util.ff", "let one = 1\nfn mul(a, b) { a * b }"),
+            ],
+            expect![[r#"
+                fn util_mul(a, b) {
+                    a * b
+                }
+                fn math_square(x) {
+                    util_mul(x, x)
+                }
+                let util_one = 1
+                let math_two = util_one + 1
+                math_square(math_two) + math_two
+                => 6
+            "#]],
+        );
+    }
+
+    #[test]
+    fn keep_locals_and_parameters() {
+        check(
+            &[
+                (
+                    MAIN,
+                    "import \"m.ff\" as m\nuse m.x\nfn f(x) { x }\nf(2) + x + m.g(3)",
+                ),
+                (
+                    "m.ff",
+                    "let x = 10\nfn g(x) { { let y = x y } + h() }\nfn h() { x }",
+                ),
+            ],
+            expect![[r#"
+                fn m_g(x) {
+                    {
+                        let y = x
+                        y
+                    } + m_h()
+                }
+                fn m_h() {
+                    m_x
+                }
+                fn f(x) {
+                    x
+                }
+                let m_x = 10
+                f(2) + m_x + m_g(3)
+                => 25
+            "#]],
+        );
+    }
+
+    #[test]
+    fn load_each_module_once() {
+        check(
+            &[
+                (
+                    MAIN,
+                    "import \"a.ff\" as a\nimport \"b.ff\" as b\na.x + b.x",
+                ),
+                ("a.ff", "import \"shared/c.ff\" as c\nlet x = c.x"),
+                (
+                    "b.ff",
+                    "import \"./shared/../shared/c.ff\" as c\nlet x = c.x * 2",
+                ),
+                ("shared/c.ff", "let x = 1"),
+            ],
+            expect![[r#"
+                let c_x = 1
+                let a_x = c_x
+                let b_x = c_x * 2
+                a_x + b_x
+                => 3
+            "#]],
+        );
+    }
+
+    #[test]
+    fn map_ranges_to_lines_of_their_own_files() {
+        let (linked, line_index) = link(&[
+            (MAIN, "import \"m.ff\" as m\n\nm.f()"),
+            ("m.ff", "let x = 1\n\nfn f() {\n    x\n}"),
+        ])
+        .unwrap();
+        let line = |range: TextRange| line_index.line_col(range.start()).line;
+
+        assert_eq!(line(linked.fns[0].range), 2);
+        assert_eq!(
+            linked
+                .stmts
+                .iter()
+                .map(|stmt| line(stmt.range))
+                .collect::<Vec<_>>(),
+            [0, 2],
+        );
+        assert_eq!(linked.stmts[0].range.start(), TextSize::from(25));
+    }
+
+    #[test]
+    fn number_modules_with_the_same_name() {
+        check(
+            &[
+                (
+                    MAIN,
+                    "import \"a/m.ff\" as a\nimport \"b/m.ff\" as b\na.x - b.x",
+                ),
+                ("a/m.ff", "let x = 5"),
+                ("b/m.ff", "let x = 3"),
+            ],
+            expect![[r#"
+                let m_x = 5
+                let m_2_x = 3
+                m_x - m_2_x
+                => 2
+            "#]],
+        );
+    }
+
+    #[test]
+    fn report_import_cycles() {
+        check_error(
+            &[
+                (MAIN, "import \"a.ff\" as a\na.x"),
+                ("a.ff", "import \"lib/b.ff\" as b\nlet x = b.y"),
+                ("lib/b.ff", "\nimport \"../a.ff\" as a\nlet y = 1"),
+            ],
+            expect!["lib/b.ff:2:1: import cycle: a.ff -> lib/b.ff -> a.ff"],
+        );
+        check_error(
+            &[(MAIN, "import \"main.ff\" as me")],
+            expect!["main.ff:1:1: import cycle: main.ff -> main.ff"],
+        );
+    }
+
+    #[test]
+    fn report_bad_imports() {
+        check_error(
+            &[(MAIN, "import \"nope.ff\" as m")],
+            expect!["main.ff:1:1: can't read 'nope.ff': entity not found"],
+        );
+        check_error(
+            &[
+                (MAIN, "import \"m.ff\" as m"),
+                ("m.ff", "let a = \nlet b = 1 +"),
+            ],
+            expect![[r#"
+                m.ff:2:1: expected number, identifier, '-', '(' or '{', but found 'let'
+                m.ff:2:11: expected number, identifier, '-', '(' or '{'"#]],
+        );
+        check_error(
+            &[(MAIN, "use m.x")],
+            expect!["main.ff:1:1: no module named 'm'"],
+        );
+        check_error(
+            &[
+                (MAIN, "import \"m.ff\" as m\n\nm.f()"),
+                ("m.ff", "let f = 1"),
+            ],
+            expect!["main.ff:3:1: module 'm' has no 'f'"],
+        );
+        check_error(
+            &[
+                (MAIN, "import \"m.ff\" as m\nuse m.f\nfn f() { 1 }"),
+                ("m.ff", "fn f() { 2 }"),
+            ],
+            expect!["main.ff:2:1: 'f' is both used from a module and defined here"],
+        );
+    }
+
+    #[test]
+    fn remember_imports_across_links() {
+        let mut loader = Loader::with_reader(|_| Ok("let x = 1\nfn f() { 2 }".to_string()));
+        let mut interpreter = Interpreter::new();
+
+        let mut run = |input: &str| {
+            let parse = parser::parse(input);
+            let program = hir::lower(ast::Root::cast(parse.syntax()).unwrap());
+            let linked = loader.link(&program, Path::new("<repl>"), &mut LineIndex::new(input))?;
+            Ok::<_, LoadError>(interpreter.run(&linked).unwrap())
+        };
+
+        assert_eq!(run("import \"m.ff\" as m"), Ok(Value::Unit));
+        assert_eq!(run("use m.f"), Ok(Value::Unit));
+        assert_eq!(run("m.x + f()"), Ok(Value::Int(3)));
+        assert!(run("let f = 1").is_err());
+        // The module isn't run again.
+        assert_eq!(run("import \"m.ff\" as n\nn.x"), Ok(Value::Int(1)));
+    }
+}
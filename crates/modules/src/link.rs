@@ -0,0 +1,164 @@
+use crate::{File, LoadError, Module, Scope};
+use hir::{Expr, FnDef, Program, Stmt, StmtKind};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use text_size::TextRange;
+
+/// Renames the names in `program` to the ones they're linked as, and adds its functions and
+/// statements to `linked`. `prefix` is the module's own prefix, or `None` for the code being
+/// linked, whose names are left alone. Ranges in the result start from where the file does.
+pub(crate) fn link(
+    program: &Program,
+    file: &File<'_>,
+    modules: &HashMap<PathBuf, Module>,
+    scope: &Scope,
+    prefix: Option<&str>,
+    linked: &mut Program,
+) -> Result<(), LoadError> {
+    let mut linker = Linker {
+        file,
+        modules,
+        scope,
+        prefix,
+        locals: Vec::new(),
+        range: TextRange::default(),
+    };
+
+    for fn_def in &program.fns {
+        linker.range = fn_def.range;
+        linker.locals = fn_def.params.clone();
+
+        linked.fns.push(FnDef {
+            name: linker.global(&fn_def.name),
+            body: linker.expr(&fn_def.body)?,
+            range: fn_def.range + file.start,
+            ..fn_def.clone()
+        });
+    }
+
+    linker.locals.clear();
+    for stmt in &program.stmts {
+        let stmt = linker.stmt(stmt, true)?;
+        linked.stmts.push(stmt);
+    }
+
+    Ok(())
+}
+
+struct Linker<'a> {
+    file: &'a File<'a>,
+    modules: &'a HashMap<PathBuf, Module>,
+    scope: &'a Scope,
+    prefix: Option<&'a str>,
+    /// The parameters and block-local variables in scope, which are never renamed.
+    locals: Vec<String>,
+    /// The range of the statement being linked, used for error reporting.
+    range: TextRange,
+}
+
+impl Linker<'_> {
+    fn stmt(&mut self, stmt: &Stmt, top_level: bool) -> Result<Stmt, LoadError> {
+        let outer_range = std::mem::replace(&mut self.range, stmt.range);
+
+        let kind = match &stmt.kind {
+            StmtKind::VariableDef { name, value } => {
+                let value = self.expr(value)?;
+
+                let name = if top_level {
+                    self.global(name)
+                } else {
+                    self.locals.push(name.clone());
+                    name.clone()
+                };
+
+                StmtKind::VariableDef { name, value }
+            }
+            StmtKind::Expr(expr) => StmtKind::Expr(self.expr(expr)?),
+        };
+
+        self.range = outer_range;
+
+        Ok(Stmt {
+            kind,
+            range: stmt.range + self.file.start,
+        })
+    }
+
+    fn expr(&mut self, expr: &Expr) -> Result<Expr, LoadError> {
+        let expr = match expr {
+            Expr::Missing | Expr::Literal { .. } => expr.clone(),
+            Expr::Binary { op, lhs, rhs } => Expr::Binary {
+                op: *op,
+                lhs: Box::new(self.expr(lhs)?),
+                rhs: Box::new(self.expr(rhs)?),
+            },
+            Expr::Unary { op, expr } => Expr::Unary {
+                op: *op,
+                expr: Box::new(self.expr(expr)?),
+            },
+            Expr::VariableRef { var } => Expr::VariableRef {
+                var: self.name(var, false)?,
+            },
+            Expr::Block { stmts } => {
+                let scope_start = self.locals.len();
+                let stmts = stmts
+                    .iter()
+                    .map(|stmt| self.stmt(stmt, false))
+                    .collect::<Result<_, _>>()?;
+                self.locals.truncate(scope_start);
+
+                Expr::Block { stmts }
+            }
+            Expr::Call { callee, args } => Expr::Call {
+                callee: self.name(callee, true)?,
+                args: args
+                    .iter()
+                    .map(|arg| self.expr(arg))
+                    .collect::<Result<_, _>>()?,
+            },
+        };
+
+        Ok(expr)
+    }
+
+    /// The name that `name`, which is being called if `is_fn` is set, is linked as.
+    fn name(&self, name: &str, is_fn: bool) -> Result<String, LoadError> {
+        if let Some((module_name, member)) = name.split_once('.') {
+            let Some(module) = self.scope.modules.get(module_name) else {
+                return Err(LoadError::UnknownModule {
+                    at: self.file.at(self.range),
+                    name: module_name.to_string(),
+                });
+            };
+            let module = &self.modules[module];
+
+            let exported = if is_fn { &module.fns } else { &module.globals };
+            if !exported.contains(member) {
+                return Err(LoadError::UnknownName {
+                    at: self.file.at(self.range),
+                    module: module_name.to_string(),
+                    name: member.to_string(),
+                });
+            }
+
+            return Ok(module.linked_name(member));
+        }
+
+        // Function calls can't refer to variables, so locals only hide other variables.
+        if !is_fn && self.locals.iter().any(|local| local == name) {
+            return Ok(name.to_string());
+        }
+
+        match self.scope.uses.get(name) {
+            Some(linked) => Ok(linked.clone()),
+            None => Ok(self.global(name)),
+        }
+    }
+
+    fn global(&self, name: &str) -> String {
+        match self.prefix {
+            Some(prefix) => format!("{}_{}", prefix, name),
+            None => name.to_string(),
+        }
+    }
+}
@@ -0,0 +1,22 @@
+use std::fmt::Display;
+use text_size::TextRange;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OptimizeError {
+    DivisionByZero(TextRange),
+}
+
+impl Display for OptimizeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::DivisionByZero(range) => write!(
+                f,
+                "error at {}..{}: division by zero",
+                u32::from(range.start()),
+                u32::from(range.end()),
+            ),
+        }
+    }
+}
+
+impl std::error::Error for OptimizeError {}
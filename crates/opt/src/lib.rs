@@ -0,0 +1,536 @@
+mod error;
+
+pub use error::OptimizeError;
+
+use hir::{BinaryOp, Expr, FnDef, Program, Stmt, StmtKind, UnaryOp};
+use std::collections::HashMap;
+use text_size::TextRange;
+
+/// Folds constant arithmetic, propagates constant `let`s and removes arithmetic identities.
+///
+/// Folding uses the interpreter's checked arithmetic, so anything that would overflow is left
+/// for the runtime to report. Dividing by a constant zero is reported here instead, even if
+/// the division would never be evaluated.
+///
+/// The identity rewrites (`x * 1`, `x + 0`, ...) only apply when `x` is known to be an integer
+/// whenever it evaluates to anything at all, and `--x` only when `x` also can't be `i64::MIN`,
+/// which is the one integer that can't be negated.
+pub fn optimize(program: &Program) -> Result<Program, OptimizeError> {
+    let fns: Vec<_> = program
+        .fns
+        .iter()
+        .map(|fn_def| {
+            // Function bodies can't see the caller's locals, and the globals they see depend on
+            // when they're called, so only their parameters are in scope. Functions can also be
+            // redefined before they're called, so nothing is known about what calls return.
+            let mut optimizer = Optimizer::new(fn_def.range);
+            optimizer.scope = params(fn_def);
+
+            Ok(FnDef {
+                body: optimizer.expr(&fn_def.body)?,
+                ..fn_def.clone()
+            })
+        })
+        .collect::<Result<_, _>>()?;
+
+    let mut optimizer = Optimizer::new(TextRange::default());
+    optimizer.returns = returns(&fns);
+    let stmts = program
+        .stmts
+        .iter()
+        .map(|stmt| optimizer.stmt(stmt))
+        .collect::<Result<_, _>>()?;
+
+    Ok(Program {
+        imports: program.imports.clone(),
+        uses: program.uses.clone(),
+        fns,
+        stmts,
+    })
+}
+
+/// What's known about the value of an expression, from least to most.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Known {
+    /// It could be unit or any integer.
+    Nothing,
+    /// It's an integer whenever it evaluates to anything at all, since anything else fails.
+    Int,
+    /// Like [`Known::Int`], but never `i64::MIN`, so negating it can't overflow.
+    Negatable,
+}
+
+struct Variable {
+    name: String,
+    /// The variable's value if it's constant.
+    value: Option<i64>,
+    known: Known,
+}
+
+struct Optimizer {
+    /// The variables in scope, innermost last.
+    scope: Vec<Variable>,
+    /// What's known about the values returned by the program's functions, by name.
+    returns: HashMap<String, Known>,
+    /// The range of the statement being optimized, used for error reporting.
+    range: TextRange,
+}
+
+impl Optimizer {
+    fn new(range: TextRange) -> Self {
+        Self {
+            scope: Vec::new(),
+            returns: HashMap::new(),
+            range,
+        }
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) -> Result<Stmt, OptimizeError> {
+        let outer_range = std::mem::replace(&mut self.range, stmt.range);
+
+        let kind = match &stmt.kind {
+            StmtKind::VariableDef { name, value } => {
+                let value = self.expr(value)?;
+                self.define(name, &value);
+
+                StmtKind::VariableDef {
+                    name: name.clone(),
+                    value,
+                }
+            }
+            StmtKind::Expr(expr) => StmtKind::Expr(self.expr(expr)?),
+        };
+
+        self.range = outer_range;
+
+        Ok(Stmt {
+            kind,
+            range: stmt.range,
+        })
+    }
+
+    fn expr(&mut self, expr: &Expr) -> Result<Expr, OptimizeError> {
+        let expr = match expr {
+            Expr::Missing | Expr::Literal { .. } => expr.clone(),
+            Expr::Binary { op, lhs, rhs } => {
+                let lhs = self.expr_boxed(lhs)?;
+                let rhs = self.expr_boxed(rhs)?;
+
+                self.binary(*op, lhs, rhs)?
+            }
+            Expr::Unary { op, expr } => {
+                let expr = self.expr_boxed(expr)?;
+
+                self.unary(*op, expr)
+            }
+            Expr::VariableRef { var } => match self.lookup(var).and_then(|var| var.value) {
+                Some(n) => int(n).unwrap_or_else(|| expr.clone()),
+                None => expr.clone(),
+            },
+            Expr::Block { stmts } => {
+                let scope_len = self.scope.len();
+                let stmts = stmts
+                    .iter()
+                    .map(|stmt| self.stmt(stmt))
+                    .collect::<Result<_, _>>();
+                self.scope.truncate(scope_len);
+
+                Expr::Block { stmts: stmts? }
+            }
+            Expr::Call { callee, args } => Expr::Call {
+                callee: callee.clone(),
+                args: args
+                    .iter()
+                    .map(|arg| self.expr(arg))
+                    .collect::<Result<_, _>>()?,
+            },
+        };
+
+        Ok(expr)
+    }
+
+    fn expr_boxed(&mut self, expr: &Expr) -> Result<Box<Expr>, OptimizeError> {
+        self.expr(expr).map(Box::new)
+    }
+
+    fn binary(
+        &mut self,
+        op: BinaryOp,
+        lhs: Box<Expr>,
+        rhs: Box<Expr>,
+    ) -> Result<Expr, OptimizeError> {
+        match (constant(&lhs), constant(&rhs)) {
+            (_, Some(0)) if op == BinaryOp::Div => Err(OptimizeError::DivisionByZero(self.range)),
+            (Some(lhs_value), Some(rhs_value)) => {
+                match eval::binary(op, lhs_value, rhs_value).ok().and_then(int) {
+                    Some(folded) => Ok(folded),
+                    None => Ok(Expr::Binary { op, lhs, rhs }),
+                }
+            }
+            (_, Some(0)) if matches!(op, BinaryOp::Add | BinaryOp::Sub) && self.is_int(&lhs) => {
+                Ok(*lhs)
+            }
+            (_, Some(1)) if matches!(op, BinaryOp::Mul | BinaryOp::Div) && self.is_int(&lhs) => {
+                Ok(*lhs)
+            }
+            (Some(0), _) if op == BinaryOp::Add && self.is_int(&rhs) => Ok(*rhs),
+            (Some(1), _) if op == BinaryOp::Mul && self.is_int(&rhs) => Ok(*rhs),
+            _ => Ok(Expr::Binary { op, lhs, rhs }),
+        }
+    }
+
+    fn unary(&mut self, op: UnaryOp, expr: Box<Expr>) -> Expr {
+        if let Some(value) = constant(&expr) {
+            if let Some(folded) = eval::unary(op, value).ok().and_then(int) {
+                return folded;
+            }
+        }
+
+        match (op, *expr) {
+            (
+                UnaryOp::Neg,
+                Expr::Unary {
+                    op: UnaryOp::Neg,
+                    expr,
+                },
+            ) if self.known(&expr) == Known::Negatable => *expr,
+            (op, expr) => Expr::Unary {
+                op,
+                expr: Box::new(expr),
+            },
+        }
+    }
+
+    fn is_int(&mut self, expr: &Expr) -> bool {
+        self.known(expr) >= Known::Int
+    }
+
+    /// Works out what's known about the value of `expr`, which has already been optimized, in
+    /// the current scope.
+    fn known(&mut self, expr: &Expr) -> Known {
+        match expr {
+            Expr::Literal { .. } | Expr::Unary { .. } if constant(expr).is_some() => {
+                Known::Negatable
+            }
+            Expr::Unary {
+                op: UnaryOp::Neg, ..
+            } => Known::Negatable,
+            // Arithmetic either produces an integer or fails.
+            Expr::Binary { .. } => Known::Int,
+            Expr::VariableRef { var } => self.lookup(var).map_or(Known::Nothing, |var| var.known),
+            Expr::Block { stmts } => {
+                let scope_len = self.scope.len();
+                let mut known = Known::Nothing;
+
+                for stmt in stmts {
+                    known = match &stmt.kind {
+                        StmtKind::VariableDef { name, value } => {
+                            self.define(name, value);
+                            Known::Nothing
+                        }
+                        StmtKind::Expr(expr) => self.known(expr),
+                    };
+                }
+
+                self.scope.truncate(scope_len);
+                known
+            }
+            Expr::Call { callee, .. } => {
+                self.returns.get(callee).copied().unwrap_or(Known::Nothing)
+            }
+            Expr::Missing | Expr::Literal { .. } => Known::Nothing,
+        }
+    }
+
+    fn define(&mut self, name: &str, value: &Expr) {
+        let known = self.known(value);
+        self.scope.push(Variable {
+            name: name.to_string(),
+            value: constant(value),
+            known,
+        });
+    }
+
+    fn lookup(&self, var: &str) -> Option<&Variable> {
+        self.scope
+            .iter()
+            .rev()
+            .find(|variable| variable.name == var)
+    }
+}
+
+fn params(fn_def: &FnDef) -> Vec<Variable> {
+    fn_def
+        .params
+        .iter()
+        .map(|param| Variable {
+            name: param.clone(),
+            value: None,
+            known: Known::Nothing,
+        })
+        .collect()
+}
+
+/// Works out what's known about the values returned by `fns` when they're called from the rest
+/// of the program, where they can't have been redefined. Calls within them might depend on
+/// each other, so this starts from knowing nothing and repeats until nothing more is learned.
+fn returns(fns: &[FnDef]) -> HashMap<String, Known> {
+    // Later definitions replace earlier ones with the same name.
+    let fns: HashMap<_, _> = fns
+        .iter()
+        .map(|fn_def| (fn_def.name.clone(), fn_def))
+        .collect();
+    let mut optimizer = Optimizer::new(TextRange::default());
+    optimizer.returns = fns
+        .keys()
+        .map(|name| (name.clone(), Known::Nothing))
+        .collect();
+
+    loop {
+        let mut changed = false;
+
+        for (name, fn_def) in &fns {
+            optimizer.scope = params(fn_def);
+            let known = optimizer.known(&fn_def.body);
+
+            if optimizer.returns.insert(name.clone(), known) != Some(known) {
+                changed = true;
+            }
+        }
+
+        if !changed {
+            return optimizer.returns;
+        }
+    }
+}
+
+/// Returns the value of `expr` if it's an integer constant, which is either a literal or a
+/// negated literal since literals can't be negative.
+fn constant(expr: &Expr) -> Option<i64> {
+    match expr {
+        Expr::Literal { n } => i64::try_from(*n).ok(),
+        Expr::Unary {
+            op: UnaryOp::Neg,
+            expr,
+        } => match **expr {
+            Expr::Literal { n } => i64::try_from(n).ok().map(|n| -n),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Builds the expression for an integer constant. `i64::MIN` has no such expression, since
+/// its magnitude doesn't fit in a literal, so it's left unfolded.
+fn int(n: i64) -> Option<Expr> {
+    let literal = Expr::Literal {
+        n: n.unsigned_abs(),
+    };
+
+    if n >= 0 {
+        Some(literal)
+    } else if n == i64::MIN {
+        None
+    } else {
+        Some(Expr::Unary {
+            op: UnaryOp::Neg,
+            expr: Box::new(literal),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ast::AstNode;
+    use eval::{Interpreter, Value};
+    use expect_test::{expect, Expect};
+
+    fn lower(input: &str) -> Program {
+        let parse = parser::parse(input);
+        assert!(parse.errors().is_empty(), "{}", parse.debug_tree());
+
+        hir::lower(ast::Root::cast(parse.syntax()).unwrap())
+    }
+
+    fn check(input: &str, expected: Expect) {
+        let actual = match optimize(&lower(input)) {
+            Ok(program) => program.to_string(),
+            Err(e) => e.to_string(),
+        };
+
+        expected.assert_eq(&actual);
+    }
+
+    #[test]
+    fn fold_constant_arithmetic() {
+        check(
+            "let a = (1 + 2) * 3\nlet b = -(10 - 4 / 2)",
+            expect![[r#"
+                let a = 9
+                let b = -8
+            "#]],
+        );
+    }
+
+    #[test]
+    fn propagate_constant_lets() {
+        check(
+            "let a = 2\nlet b = a * 5\nlet c = x\nb - a + c",
+            expect![[r#"
+                let a = 2
+                let b = 10
+                let c = x
+                8 + c
+            "#]],
+        );
+    }
+
+    #[test]
+    fn redefinitions_shadow_constants() {
+        check(
+            "let a = 1\nlet a = f()\na + { let a = 3 a } + a",
+            expect![[r#"
+                let a = 1
+                let a = f()
+                a + {
+                    let a = 3
+                    3
+                } + a
+            "#]],
+        );
+    }
+
+    #[test]
+    fn functions_only_see_constant_locals() {
+        check(
+            "let g = 1\nfn f(x) { let y = 2 g + x * y * 1 }",
+            expect![[r#"
+                fn f(x) {
+                    let y = 2
+                    g + x * 2
+                }
+                let g = 1
+            "#]],
+        );
+    }
+
+    #[test]
+    fn simplify_identities() {
+        check(
+            "let a = (x - y) * 1 + 1 * -y\nlet b = 0 + (x + y) - 0\nlet c = (x * y) / 1",
+            expect![[r#"
+                let a = x - y + -y
+                let b = x + y
+                let c = x * y
+            "#]],
+        );
+    }
+
+    #[test]
+    fn simplify_identities_of_known_integers() {
+        check(
+            "fn add(a, b) { a + b }\nfn neg(a) { -a }\nlet a = add(9, 2) * 1\nlet b = 0 + a\nlet c = --neg(b)\nlet d = { let e = -c e }\nlet f = --d",
+            expect![[r#"
+                fn add(a, b) {
+                    a + b
+                }
+                fn neg(a) {
+                    -a
+                }
+                let a = add(9, 2)
+                let b = a
+                let c = neg(b)
+                let d = {
+                    let e = -c
+                    e
+                }
+                let f = d
+            "#]],
+        );
+    }
+
+    #[test]
+    fn keep_identities_that_might_fail() {
+        check(
+            "let a = x * 1 + 0\nlet b = 1 * f() / 1\nlet c = 0 + { }\nlet d = --x",
+            expect![[r#"
+                let a = x * 1
+                let b = 1 * f()
+                let c = 0 + { }
+                let d = --x
+            "#]],
+        );
+        check(
+            "fn f() { }\nfn g(x) { x * 1 + --(x + 1) }\nfn h(x) { g(x) * 1 }\nlet a = f() * 1\nlet b = -9223372036854775807 - 1\nlet c = --b",
+            expect![[r#"
+                fn f() { }
+                fn g(x) {
+                    x * 1 + --(x + 1)
+                }
+                fn h(x) {
+                    g(x) * 1
+                }
+                let a = f() * 1
+                let b = -9223372036854775807 - 1
+                let c = --b
+            "#]],
+        );
+    }
+
+    #[test]
+    fn leave_overflow_to_runtime() {
+        check(
+            "let a = 9223372036854775807 + 1\nlet b = -9223372036854775807 - 1",
+            expect![[r#"
+                let a = 9223372036854775807 + 1
+                let b = -9223372036854775807 - 1
+            "#]],
+        );
+    }
+
+    #[test]
+    fn report_division_by_zero() {
+        check(
+            "let a = 1\nlet b = a / (a - 1)",
+            expect!["error at 10..29: division by zero"],
+        );
+        check(
+            "fn f(x) { 1 + x / 0 }",
+            expect!["error at 10..20: division by zero"],
+        );
+    }
+
+    #[test]
+    fn optimized_programs_evaluate_the_same() {
+        let programs = [
+            "let a = (1 + 2) * 3\na * a",
+            "fn f(x, y) { let z = 2 * 3 x * z + y - 0 }\nf(4, 5) * 1",
+            "let a = 10\n{ let b = a / 3 --b + { let a = -1 a * 1 } }",
+            "let a = -9223372036854775807 let b = a - 1 b",
+            "fn f(x) { --x }\nf(-9223372036854775807 - 1)",
+            "fn f(x) { x * 1 }\nf({ })",
+            "fn f(x) { 0 + x }\nf({ })",
+            "fn f() { }\nfn g() { f() }\ng() * 1",
+            "fn f(x) { 1 * x }\nlet a = f({ })\n0 + a",
+            "let a = -9223372036854775807 - 1\nlet b = --a\nb",
+            "fn f(x) { -x }\nlet a = --f(3)\nlet b = --a * 1\nb",
+        ];
+
+        for input in programs {
+            let program = lower(input);
+
+            assert_eq!(
+                Interpreter::new().run(&optimize(&program).unwrap()),
+                Interpreter::new().run(&program),
+                "{}",
+                input,
+            );
+        }
+
+        assert_eq!(
+            Interpreter::new().run(&optimize(&lower("7 - 2 * 3")).unwrap()),
+            Ok(Value::Int(1)),
+        );
+    }
+}
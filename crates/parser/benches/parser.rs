@@ -0,0 +1,41 @@
+//! How fast each stage of parsing is on large synthetic programs: lexing, turning tokens into
+//! events, and building the tree from them.
+
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion, Throughput};
+use parser::{inputs, stages};
+
+fn bench(c: &mut Criterion) {
+    let inputs = [
+        ("variable defs", inputs::variable_defs()),
+        ("nested exprs", inputs::nested_exprs()),
+        ("comments", inputs::comments()),
+    ];
+
+    for (name, input) in &inputs {
+        assert!(
+            parser::parse(input).errors().is_empty(),
+            "{} has errors",
+            name
+        );
+
+        let tokens = stages::lex(input);
+
+        let mut group = c.benchmark_group(format!("parse {}", name));
+        group.throughput(Throughput::Bytes(input.len() as u64));
+
+        group.bench_function("lex", |b| b.iter(|| stages::lex(input)));
+        group.bench_function("events", |b| b.iter(|| stages::parse(&tokens)));
+        group.bench_function("tree", |b| {
+            b.iter_batched(
+                || stages::parse(&tokens),
+                |events| stages::build(&tokens, events),
+                BatchSize::SmallInput,
+            )
+        });
+
+        group.finish();
+    }
+}
+
+criterion_group!(benches, bench);
+criterion_main!(benches);
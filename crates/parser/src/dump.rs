@@ -0,0 +1,933 @@
+use crate::Parse;
+use num_traits::FromPrimitive;
+use rowan::{Checkpoint, GreenNode, GreenNodeBuilder, Language, NodeOrToken, TextRange, TextSize};
+use serde::Serialize;
+use std::fmt;
+use syntax::{FelixFlowLanguage, SyntaxKind, SyntaxNode};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LoadError {
+    Malformed(String),
+    UnknownKind(String),
+    WrongRange {
+        kind: String,
+        expected: TextRange,
+        found: TextRange,
+    },
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Malformed(message) => write!(f, "malformed tree: {}", message),
+            Self::UnknownKind(kind) => write!(f, "unknown syntax kind '{}'", kind),
+            Self::WrongRange {
+                kind,
+                expected,
+                found,
+            } => write!(
+                f,
+                "expected {} to span {:?}, but it spans {:?}",
+                kind, expected, found
+            ),
+        }
+    }
+}
+
+impl std::error::Error for LoadError {}
+
+/// A node or token as it's written out, with tokens having text and nodes having children.
+#[derive(Debug, Serialize)]
+struct Element {
+    kind: String,
+    range: [u32; 2],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    text: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    children: Option<Vec<Element>>,
+}
+
+#[derive(Debug, Serialize)]
+struct JsonParse {
+    tree: Element,
+    errors: Vec<JsonError>,
+}
+
+#[derive(Debug, Serialize)]
+struct JsonError {
+    range: [u32; 2],
+    message: String,
+}
+
+impl Element {
+    /// Writing a tree out recurses, which the parser's nesting limit keeps shallow enough.
+    fn new(node: &SyntaxNode) -> Self {
+        let children = node
+            .children_with_tokens()
+            .map(|element| match element {
+                NodeOrToken::Node(node) => Self::new(&node),
+                NodeOrToken::Token(token) => Self {
+                    kind: format!("{:?}", token.kind()),
+                    range: range_to_array(token.text_range()),
+                    text: Some(token.text().to_string()),
+                    children: None,
+                },
+            })
+            .collect();
+
+        Self {
+            kind: format!("{:?}", node.kind()),
+            range: range_to_array(node.text_range()),
+            text: None,
+            children: Some(children),
+        }
+    }
+
+    fn write_sexp(&self, out: &mut String, depth: usize) {
+        out.push_str(&"  ".repeat(depth));
+        out.push_str(&format!(
+            "({} {}..{}",
+            self.kind, self.range[0], self.range[1]
+        ));
+
+        if let Some(text) = &self.text {
+            out.push(' ');
+            write_string(out, text);
+        }
+
+        for child in self.children.iter().flatten() {
+            out.push('\n');
+            child.write_sexp(out, depth + 1);
+        }
+
+        out.push(')');
+    }
+}
+
+fn kind_from_name(name: &str) -> Result<SyntaxKind, LoadError> {
+    (0..)
+        .map_while(SyntaxKind::from_u16)
+        .find(|kind| format!("{:?}", kind) == name)
+        .ok_or_else(|| LoadError::UnknownKind(name.to_string()))
+}
+
+fn range_to_array(range: TextRange) -> [u32; 2] {
+    [range.start().into(), range.end().into()]
+}
+
+fn malformed(message: impl Into<String>) -> LoadError {
+    LoadError::Malformed(message.into())
+}
+
+impl Parse {
+    /// The tree and errors as JSON, with every node and token as an object with its `kind` and
+    /// `range`, and either the `text` of a token or the `children` of a node.
+    pub fn to_json(&self) -> String {
+        let json = JsonParse {
+            tree: Element::new(&self.syntax()),
+            errors: self
+                .errors
+                .iter()
+                .map(|error| JsonError {
+                    range: range_to_array(error.range()),
+                    message: error.message(),
+                })
+                .collect(),
+        };
+
+        serde_json::to_string_pretty(&json).unwrap()
+    }
+
+    /// The tree as an S-expression, with each node and token on its own line as
+    /// `(Kind start..end "text")` or `(Kind start..end children...)`, followed by an
+    /// `(error start..end "message")` line for each error.
+    pub fn to_sexp(&self) -> String {
+        let mut out = String::new();
+        Element::new(&self.syntax()).write_sexp(&mut out, 0);
+
+        for error in &self.errors {
+            let range = error.range();
+            out.push_str(&format!(
+                "\n(error {}..{} ",
+                u32::from(range.start()),
+                u32::from(range.end())
+            ));
+            write_string(&mut out, &error.message());
+            out.push(')');
+        }
+
+        out.push('\n');
+        out
+    }
+}
+
+/// How deeply a loaded tree can nest. Every tree the parser builds is well within this, and
+/// anything deeper would overflow the stack when it's walked or dropped.
+const MAX_TREE_DEPTH: usize = 4 * crate::parser::MAX_DEPTH;
+
+/// Builds a green node from the elements of a tree in the order they're read, keeping the
+/// elements that are still open on a stack of its own rather than by recursing.
+struct TreeBuilder {
+    builder: GreenNodeBuilder<'static>,
+    offset: TextSize,
+    /// Where each open element starts in the builder and in the text.
+    open: Vec<(Checkpoint, TextSize)>,
+    root_is_node: bool,
+}
+
+impl TreeBuilder {
+    fn new() -> Self {
+        Self {
+            builder: GreenNodeBuilder::new(),
+            offset: TextSize::default(),
+            open: Vec::new(),
+            root_is_node: false,
+        }
+    }
+
+    fn open(&mut self) -> Result<(), LoadError> {
+        if self.open.len() == MAX_TREE_DEPTH {
+            return Err(malformed(format!(
+                "the tree nests more than {} levels deep",
+                MAX_TREE_DEPTH
+            )));
+        }
+
+        self.open.push((self.builder.checkpoint(), self.offset));
+        Ok(())
+    }
+
+    /// Closes the innermost open element as a node holding everything added since it was
+    /// opened.
+    fn close_node(&mut self, kind: &str, range: [u32; 2]) -> Result<(), LoadError> {
+        let (checkpoint, start) = self.open.pop().unwrap();
+
+        self.builder.start_node_at(
+            checkpoint,
+            FelixFlowLanguage::kind_to_raw(kind_from_name(kind)?),
+        );
+        self.builder.finish_node();
+
+        self.root_is_node = self.open.is_empty();
+        self.check_range(kind, range, start)
+    }
+
+    /// Closes the innermost open element as a token, which mustn't have had anything added
+    /// since it was opened.
+    fn close_token(&mut self, kind: &str, range: [u32; 2], text: &str) -> Result<(), LoadError> {
+        let (_, start) = self.open.pop().unwrap();
+
+        self.builder
+            .token(FelixFlowLanguage::kind_to_raw(kind_from_name(kind)?), text);
+        self.offset += TextSize::of(text);
+
+        self.check_range(kind, range, start)
+    }
+
+    fn check_range(&self, kind: &str, range: [u32; 2], start: TextSize) -> Result<(), LoadError> {
+        let expected = TextRange::new(start, self.offset);
+        let found = TextRange::new(range[0].into(), range[1].into());
+
+        if found != expected {
+            return Err(LoadError::WrongRange {
+                kind: kind.to_string(),
+                expected,
+                found,
+            });
+        }
+
+        Ok(())
+    }
+
+    fn finish(self) -> Result<GreenNode, LoadError> {
+        if !self.root_is_node {
+            return Err(malformed("the root must be a node"));
+        }
+
+        Ok(self.builder.finish())
+    }
+}
+
+/// Rebuilds the tree written by [`Parse::to_json`], ignoring the errors after it.
+pub fn green_node_from_json(json: &str) -> Result<GreenNode, LoadError> {
+    let mut reader = JsonReader {
+        json: json.as_bytes(),
+        pos: 0,
+    };
+    let mut tree = None;
+
+    reader.expect(b'{')?;
+    let mut first = true;
+    while let Some(key) = reader.next_key(first)? {
+        first = false;
+
+        if key == "tree" {
+            let mut builder = TreeBuilder::new();
+            read_json_tree(&mut reader, &mut builder)?;
+            tree = Some(builder.finish()?);
+        } else {
+            reader.skip_value()?;
+        }
+    }
+
+    if reader.peek().is_some() {
+        return Err(reader.error("trailing characters"));
+    }
+
+    tree.ok_or_else(|| malformed("missing field `tree`"))
+}
+
+/// The fields of an element as they're read, since they can come in any order.
+#[derive(Default)]
+struct JsonElement {
+    kind: Option<String>,
+    range: Option<[u32; 2]>,
+    text: Option<String>,
+    has_children: bool,
+    /// Whether any fields have been read yet.
+    started: bool,
+}
+
+fn read_json_tree(reader: &mut JsonReader, tree: &mut TreeBuilder) -> Result<(), LoadError> {
+    let mut elements = Vec::new();
+
+    reader.expect(b'{')?;
+    tree.open()?;
+    elements.push(JsonElement::default());
+
+    while let Some(element) = elements.last_mut() {
+        let first = !std::mem::replace(&mut element.started, true);
+
+        match reader.next_key(first)?.as_deref() {
+            Some("kind") => element.kind = Some(reader.string()?),
+            Some("range") => element.range = Some(reader.range()?),
+            Some("text") => element.text = Some(reader.string()?),
+            Some("children") => {
+                element.has_children = true;
+                reader.expect(b'[')?;
+
+                if reader.next_item(true)? {
+                    reader.expect(b'{')?;
+                    tree.open()?;
+                    elements.push(JsonElement::default());
+                }
+            }
+            Some(_) => reader.skip_value()?,
+            None => {
+                let element = elements.pop().unwrap();
+                let missing = |field| malformed(format!("missing field `{}`", field));
+                let kind = element.kind.ok_or_else(|| missing("kind"))?;
+                let range = element.range.ok_or_else(|| missing("range"))?;
+
+                match (element.text, element.has_children) {
+                    (Some(text), false) => tree.close_token(&kind, range, &text)?,
+                    (None, true) => tree.close_node(&kind, range)?,
+                    _ => {
+                        return Err(malformed(format!(
+                            "{} must have either text or children",
+                            kind
+                        )))
+                    }
+                }
+
+                // Carry on with the siblings of what was just closed.
+                if !elements.is_empty() && reader.next_item(false)? {
+                    reader.expect(b'{')?;
+                    tree.open()?;
+                    elements.push(JsonElement::default());
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+struct JsonReader<'a> {
+    json: &'a [u8],
+    pos: usize,
+}
+
+impl JsonReader<'_> {
+    fn error(&self, message: &str) -> LoadError {
+        malformed(format!("{} at byte {}", message, self.pos))
+    }
+
+    /// Skips whitespace, returning the next byte if there is one.
+    fn peek(&mut self) -> Option<u8> {
+        while let Some(b) = self.json.get(self.pos) {
+            if !matches!(b, b' ' | b'\t' | b'\n' | b'\r') {
+                return Some(*b);
+            }
+            self.pos += 1;
+        }
+
+        None
+    }
+
+    fn expect(&mut self, expected: u8) -> Result<(), LoadError> {
+        if self.peek() != Some(expected) {
+            return Err(self.error(&format!("expected '{}'", expected as char)));
+        }
+
+        self.pos += 1;
+        Ok(())
+    }
+
+    /// Reads up to the value of the next field of an object, or past its end if there are no
+    /// more fields.
+    fn next_key(&mut self, first: bool) -> Result<Option<String>, LoadError> {
+        if self.peek() == Some(b'}') {
+            self.pos += 1;
+            return Ok(None);
+        }
+        if !first {
+            self.expect(b',')?;
+        }
+
+        let key = self.string()?;
+        self.expect(b':')?;
+
+        Ok(Some(key))
+    }
+
+    /// Reads up to the next item of an array, returning whether there is one.
+    fn next_item(&mut self, first: bool) -> Result<bool, LoadError> {
+        if self.peek() == Some(b']') {
+            self.pos += 1;
+            return Ok(false);
+        }
+        if !first {
+            self.expect(b',')?;
+        }
+
+        Ok(true)
+    }
+
+    fn string(&mut self) -> Result<String, LoadError> {
+        self.expect(b'"')?;
+        let mut bytes = Vec::new();
+
+        loop {
+            let Some(&b) = self.json.get(self.pos) else {
+                return Err(self.error("unclosed string"));
+            };
+            self.pos += 1;
+
+            match b {
+                b'"' => break,
+                b'\\' => {
+                    let escape = self.json.get(self.pos).copied();
+                    self.pos += 1;
+
+                    let c = match escape {
+                        Some(b'"') => '"',
+                        Some(b'\\') => '\\',
+                        Some(b'/') => '/',
+                        Some(b'b') => '\u{8}',
+                        Some(b'f') => '\u{c}',
+                        Some(b'n') => '\n',
+                        Some(b'r') => '\r',
+                        Some(b't') => '\t',
+                        Some(b'u') => self.unicode_escape()?,
+                        _ => return Err(self.error("invalid escape")),
+                    };
+                    bytes.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+                }
+                _ => bytes.push(b),
+            }
+        }
+
+        // Escapes are decoded into whole characters, so this can only fail if the input wasn't
+        // valid UTF-8 to begin with, and it came from a `&str`.
+        Ok(String::from_utf8(bytes).unwrap())
+    }
+
+    /// Decodes the rest of a `\u` escape, which takes two of them for characters outside the
+    /// Basic Multilingual Plane.
+    fn unicode_escape(&mut self) -> Result<char, LoadError> {
+        let high = self.hex4()?;
+
+        let code = if (0xd800..0xdc00).contains(&high) {
+            if self.json.get(self.pos..self.pos + 2) != Some(b"\\u") {
+                return Err(self.error("unpaired surrogate"));
+            }
+            self.pos += 2;
+
+            let low = self.hex4()?;
+            if !(0xdc00..0xe000).contains(&low) {
+                return Err(self.error("unpaired surrogate"));
+            }
+
+            0x10000 + ((high - 0xd800) << 10) + (low - 0xdc00)
+        } else {
+            high
+        };
+
+        char::from_u32(code).ok_or_else(|| self.error("unpaired surrogate"))
+    }
+
+    fn hex4(&mut self) -> Result<u32, LoadError> {
+        let digits = self
+            .json
+            .get(self.pos..self.pos + 4)
+            .and_then(|digits| std::str::from_utf8(digits).ok())
+            .filter(|digits| digits.bytes().all(|b| b.is_ascii_hexdigit()))
+            .ok_or_else(|| self.error("invalid escape"))?;
+        self.pos += 4;
+
+        Ok(u32::from_str_radix(digits, 16).unwrap())
+    }
+
+    fn range(&mut self) -> Result<[u32; 2], LoadError> {
+        self.expect(b'[')?;
+        let start = self.u32()?;
+        self.expect(b',')?;
+        let end = self.u32()?;
+        self.expect(b']')?;
+
+        Ok([start, end])
+    }
+
+    fn u32(&mut self) -> Result<u32, LoadError> {
+        self.peek();
+        let start = self.pos;
+        while self.json.get(self.pos).is_some_and(u8::is_ascii_digit) {
+            self.pos += 1;
+        }
+
+        std::str::from_utf8(&self.json[start..self.pos])
+            .unwrap()
+            .parse()
+            .map_err(|_| self.error("expected an offset"))
+    }
+
+    /// Skips over a value of any kind, keeping track of how deeply it nests rather than
+    /// recursing.
+    fn skip_value(&mut self) -> Result<(), LoadError> {
+        let mut closers = Vec::new();
+
+        loop {
+            match self.peek() {
+                None => return Err(self.error("unexpected end of input")),
+                Some(b'"') => {
+                    self.string()?;
+                }
+                Some(b @ (b'[' | b'{')) => {
+                    self.pos += 1;
+                    closers.push(if b == b'[' { b']' } else { b'}' });
+                    continue;
+                }
+                Some(b @ (b']' | b'}')) => {
+                    if closers.pop() != Some(b) {
+                        return Err(self.error(&format!("unexpected '{}'", b as char)));
+                    }
+                    self.pos += 1;
+                }
+                Some(_) => {
+                    // A number, `true`, `false` or `null`, or a separator between the items
+                    // of what's being skipped.
+                    let start = self.pos;
+                    while self
+                        .json
+                        .get(self.pos)
+                        .is_some_and(|b| !b" \t\n\r\",[]{}".contains(b))
+                    {
+                        self.pos += 1;
+                    }
+                    if self.pos == start {
+                        self.pos += 1;
+                        continue;
+                    }
+                }
+            }
+
+            if closers.is_empty() {
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// Rebuilds the tree written by [`Parse::to_sexp`], ignoring the errors after it.
+pub fn green_node_from_sexp(sexp: &str) -> Result<GreenNode, LoadError> {
+    let mut reader = SexpReader {
+        chars: sexp.chars().peekable(),
+    };
+
+    let mut tree = TreeBuilder::new();
+    match reader.next()? {
+        Some(SexpToken::Open) => read_sexp_tree(&mut reader, &mut tree)?,
+        Some(_) => return Err(malformed("expected a list")),
+        None => return Err(malformed("unexpected end of input")),
+    }
+
+    // The errors are skipped, but they still have to be well-formed.
+    let mut depth = 0_usize;
+    while let Some(token) = reader.next()? {
+        match token {
+            SexpToken::Open => depth += 1,
+            SexpToken::Close if depth == 0 => return Err(malformed("unexpected ')'")),
+            SexpToken::Close => depth -= 1,
+            SexpToken::Atom(_) | SexpToken::String(_) => {}
+        }
+    }
+    if depth > 0 {
+        return Err(malformed("unclosed '('"));
+    }
+
+    tree.finish()
+}
+
+/// Reads the elements of a tree once its opening parenthesis has been read.
+fn read_sexp_tree(reader: &mut SexpReader, tree: &mut TreeBuilder) -> Result<(), LoadError> {
+    // The kind and range of each open element, and whether it has any children yet.
+    let mut elements = Vec::new();
+    let mut open = true;
+
+    loop {
+        if open {
+            tree.open()?;
+            let (kind, range) = read_kind_and_range(reader)?;
+            elements.push((kind, range, false));
+        }
+        open = false;
+
+        let token = reader.next()?.ok_or_else(|| malformed("unclosed '('"))?;
+        let (_, _, has_children) = elements.last_mut().unwrap();
+
+        match token {
+            SexpToken::Open => {
+                *has_children = true;
+                open = true;
+            }
+            SexpToken::String(text) if !*has_children => {
+                if reader.next()? != Some(SexpToken::Close) {
+                    return Err(malformed("expected a list"));
+                }
+
+                let (kind, range, _) = elements.pop().unwrap();
+                tree.close_token(&kind, range, &text)?;
+            }
+            SexpToken::Close => {
+                let (kind, range, _) = elements.pop().unwrap();
+                tree.close_node(&kind, range)?;
+            }
+            SexpToken::Atom(_) | SexpToken::String(_) => return Err(malformed("expected a list")),
+        }
+
+        if elements.is_empty() {
+            return Ok(());
+        }
+    }
+}
+
+fn read_kind_and_range(reader: &mut SexpReader) -> Result<(String, [u32; 2]), LoadError> {
+    let (Some(SexpToken::Atom(kind)), Some(SexpToken::Atom(range))) =
+        (reader.next()?, reader.next()?)
+    else {
+        return Err(malformed("expected a kind and a range"));
+    };
+
+    let range = range
+        .split_once("..")
+        .and_then(|(start, end)| Some([start.parse().ok()?, end.parse().ok()?]))
+        .ok_or_else(|| LoadError::Malformed(format!("invalid range '{}'", range)))?;
+
+    Ok((kind, range))
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum SexpToken {
+    Open,
+    Close,
+    Atom(String),
+    String(String),
+}
+
+struct SexpReader<'a> {
+    chars: std::iter::Peekable<std::str::Chars<'a>>,
+}
+
+impl SexpReader<'_> {
+    /// Skips whitespace, returning the next character if there is one.
+    fn skip_whitespace(&mut self) -> Option<char> {
+        while let Some(c) = self.chars.peek() {
+            if !c.is_whitespace() {
+                return Some(*c);
+            }
+            self.chars.next();
+        }
+
+        None
+    }
+
+    fn next(&mut self) -> Result<Option<SexpToken>, LoadError> {
+        let token = match self.skip_whitespace() {
+            None => return Ok(None),
+            Some('(') => {
+                self.chars.next();
+                SexpToken::Open
+            }
+            Some(')') => {
+                self.chars.next();
+                SexpToken::Close
+            }
+            Some('"') => {
+                self.chars.next();
+                let mut text = String::new();
+
+                loop {
+                    match self.chars.next() {
+                        None => return Err(malformed("unclosed string")),
+                        Some('"') => break SexpToken::String(text),
+                        Some('\\') => match self.chars.next() {
+                            Some('n') => text.push('\n'),
+                            Some('r') => text.push('\r'),
+                            Some('t') => text.push('\t'),
+                            Some(c @ ('\\' | '"')) => text.push(c),
+                            _ => return Err(malformed("invalid escape")),
+                        },
+                        Some(c) => text.push(c),
+                    }
+                }
+            }
+            Some(_) => {
+                let mut atom = String::new();
+
+                while let Some(&c) = self.chars.peek() {
+                    if c.is_whitespace() || matches!(c, '(' | ')' | '"') {
+                        break;
+                    }
+                    atom.push(c);
+                    self.chars.next();
+                }
+
+                SexpToken::Atom(atom)
+            }
+        };
+
+        Ok(Some(token))
+    }
+}
+
+fn write_string(out: &mut String, text: &str) {
+    out.push('"');
+
+    for c in text.chars() {
+        match c {
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            '\\' | '"' => {
+                out.push('\\');
+                out.push(c);
+            }
+            c => out.push(c),
+        }
+    }
+
+    out.push('"');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+    use expect_test::expect;
+
+    fn check_round_trip(input: &str) {
+        let parse = parse(input);
+        let original = parse.syntax().green().into_owned();
+
+        assert_eq!(green_node_from_json(&parse.to_json()).unwrap(), original);
+        assert_eq!(green_node_from_sexp(&parse.to_sexp()).unwrap(), original);
+    }
+
+    #[test]
+    fn write_sexp() {
+        expect![[r##"
+            (Root 0..9
+              (VariableDef 0..9
+                (LetKw 0..3 "let")
+                (Whitespace 3..4 " ")
+                (Error 4..6
+                  (Equals 4..5 "=")
+                  (Whitespace 5..6 " "))
+                (Error 6..9
+                  (Number 6..7 "1")
+                  (Whitespace 7..8 " ")
+                  (Comment 8..9 "#"))))
+            (error 4..5 "expected identifier, but found '='")
+            (error 6..7 "expected '=', but found number")
+            (error 8..9 "expected number, identifier, '-', '(' or '{'")
+        "##]]
+        .assert_eq(&parse("let = 1 #").to_sexp());
+    }
+
+    #[test]
+    fn write_json() {
+        expect![[r#"
+            {
+              "tree": {
+                "kind": "Root",
+                "range": [
+                  0,
+                  1
+                ],
+                "children": [
+                  {
+                    "kind": "Literal",
+                    "range": [
+                      0,
+                      1
+                    ],
+                    "children": [
+                      {
+                        "kind": "Number",
+                        "range": [
+                          0,
+                          1
+                        ],
+                        "text": "1"
+                      }
+                    ]
+                  }
+                ]
+              },
+              "errors": []
+            }"#]]
+        .assert_eq(&parse("1").to_json());
+    }
+
+    #[test]
+    fn round_trip_trees() {
+        check_round_trip("");
+        check_round_trip("let a = 1\nfn f(x, y) { x * (y - a) }\nf(a, -2)");
+        check_round_trip("# \"quoted\" \\ comment\nlet = {");
+        check_round_trip("1 + ) 2");
+        check_round_trip(&format!("1{}", " + 1".repeat(200)));
+    }
+
+    #[test]
+    fn round_trip_trees_nested_to_the_limit() {
+        let depth = crate::parser::MAX_DEPTH;
+        check_round_trip(&format!("{}1{}", "f(".repeat(depth), ")".repeat(depth)));
+        check_round_trip(&format!(
+            "{}1{}",
+            "{ let a = ".repeat(depth),
+            " }".repeat(depth)
+        ));
+    }
+
+    #[test]
+    fn read_json_fields_in_any_order() {
+        let json = r#"{
+            "errors": [{ "range": [0, 1], "message": "unused", "extra": [[{}], null, -1.5e3] }],
+            "tree": {
+                "children": [{ "text": "\u0023 \ud83d\ude00", "range": [0, 6], "kind": "Comment" }],
+                "kind": "Root",
+                "range": [0, 6]
+            }
+        }"#;
+
+        assert_eq!(
+            green_node_from_json(json).unwrap(),
+            parse("# \u{1f600}").syntax().green().into_owned(),
+        );
+    }
+
+    #[test]
+    fn reject_trees_nested_too_deeply() {
+        let depth = MAX_TREE_DEPTH + 1;
+        let sexp = format!("{}{}", "(Root 0..0 ".repeat(depth), ")".repeat(depth));
+        let json = format!(
+            r#"{{"tree": {}{}}}"#,
+            r#"{"kind": "Root", "range": [0, 0], "children": ["#.repeat(depth),
+            "]}".repeat(depth)
+        );
+        let message = format!("the tree nests more than {} levels deep", MAX_TREE_DEPTH);
+
+        assert_eq!(
+            green_node_from_sexp(&sexp),
+            Err(LoadError::Malformed(message.clone()))
+        );
+        assert_eq!(
+            green_node_from_json(&json),
+            Err(LoadError::Malformed(message))
+        );
+
+        // Whatever is ignored is skipped without recursing, however deep it is.
+        let ignored = format!(
+            r#"{{"tree": {{"kind": "Root", "range": [0, 0], "children": []}}, "x": {}{}}}"#,
+            "[".repeat(100_000),
+            "]".repeat(100_000)
+        );
+        assert!(green_node_from_json(&ignored).is_ok());
+    }
+
+    #[test]
+    fn reject_inconsistent_trees() {
+        expect![[r#"
+            Err(
+                WrongRange {
+                    kind: "Root",
+                    expected: 0..1,
+                    found: 0..2,
+                },
+            )
+        "#]]
+        .assert_debug_eq(&green_node_from_sexp("(Root 0..2 (Number 0..1 \"1\"))"));
+
+        expect![[r#"
+            Err(
+                UnknownKind(
+                    "Number2",
+                ),
+            )
+        "#]]
+        .assert_debug_eq(&green_node_from_sexp("(Root 0..1 (Number2 0..1 \"1\"))"));
+
+        expect![[r#"
+            Err(
+                Malformed(
+                    "unclosed '('",
+                ),
+            )
+        "#]]
+        .assert_debug_eq(&green_node_from_sexp("(Root 0..0"));
+
+        expect![[r#"
+            Err(
+                Malformed(
+                    "Root must have either text or children",
+                ),
+            )
+        "#]]
+        .assert_debug_eq(&green_node_from_json(
+            r#"{"tree": {"kind": "Root", "range": [0, 0]}}"#,
+        ));
+
+        expect![[r#"
+            Err(
+                Malformed(
+                    "the root must be a node",
+                ),
+            )
+        "#]]
+        .assert_debug_eq(&green_node_from_json(
+            r#"{"tree": {"kind": "Number", "range": [0, 1], "text": "1"}}"#,
+        ));
+
+        expect![[r#"
+            Err(
+                Malformed(
+                    "expected ',' at byte 25",
+                ),
+            )
+        "#]]
+        .assert_debug_eq(&green_node_from_json(
+            r#"{"tree": {"kind": "Root" "range": [0, 0]}}"#,
+        ));
+    }
+}
@@ -16,6 +16,10 @@ pub(crate) fn root(p: &mut Parser) -> CompletedMarker {
     m.complete(p, SyntaxKind::Root)
 }
 
+pub(crate) fn block(p: &mut Parser) -> CompletedMarker {
+    expr::block(p)
+}
+
 #[cfg(test)]
 mod tests {
     use crate::check;
@@ -41,4 +45,28 @@ Root@0..11
     Ident@10..11 "a""#]],
         );
     }
+
+    #[test]
+    fn parse_unrecognized_character() {
+        check(
+            "let a = 1;\na",
+            expect![[r#"
+            Root@0..12
+              VariableDef@0..9
+                LetKw@0..3 "let"
+                Whitespace@3..4 " "
+                Ident@4..5 "a"
+                Whitespace@5..6 " "
+                Equals@6..7 "="
+                Whitespace@7..8 " "
+                Literal@8..9
+                  Number@8..9 "1"
+              Error@9..11
+                Error@9..10 ";"
+                Whitespace@10..11 "\n"
+              VariableRef@11..12
+                Ident@11..12 "a"
+            error at 9..10: expected '+', '-', '*', '/', 'let', 'fn', 'import', 'use', number, identifier, '(' or '{', but found unrecognized character"#]],
+        );
+    }
 }
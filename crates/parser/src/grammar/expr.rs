@@ -5,7 +5,8 @@ pub(super) fn expr(p: &mut Parser) -> Option<CompletedMarker> {
 }
 
 fn expr_binding_power(p: &mut Parser, minimum_binding_power: u8) -> Option<CompletedMarker> {
-    let mut lhs = lhs(p)?;
+    let (lhs, mut height) = p.measure(lhs);
+    let mut lhs = lhs?;
 
     loop {
         let op = if p.at(TokenKind::Plus) {
@@ -29,10 +30,16 @@ fn expr_binding_power(p: &mut Parser, minimum_binding_power: u8) -> Option<Compl
         p.bump();
 
         let m = lhs.precede(p);
-        let parsed_rhs = expr_binding_power(p, right_binding_power).is_some();
+        let (parsed_rhs, rhs_height) =
+            p.measure(|p| expr_binding_power(p, right_binding_power).is_some());
+
+        // The new node holds both operands, so it's one level above the deeper of the two.
+        height = height.max(rhs_height) + 1;
+        let too_deep = p.reach(height);
+
         lhs = m.complete(p, SyntaxKind::InfixExpr);
 
-        if !parsed_rhs {
+        if !parsed_rhs || too_deep {
             break;
         }
     }
@@ -44,11 +51,13 @@ fn lhs(p: &mut Parser) -> Option<CompletedMarker> {
     let cm = if p.at(TokenKind::Number) {
         literal(p)
     } else if p.at(TokenKind::Ident) {
-        variable_ref(p)
+        variable_ref_or_call(p)
     } else if p.at(TokenKind::Minus) {
         prefix_expr(p)
     } else if p.at(TokenKind::LParen) {
         paren_expr(p)
+    } else if p.at(TokenKind::LBrace) {
+        block(p)
     } else {
         p.error();
         return None;
@@ -65,12 +74,47 @@ fn literal(p: &mut Parser) -> CompletedMarker {
     m.complete(p, SyntaxKind::Literal)
 }
 
-fn variable_ref(p: &mut Parser) -> CompletedMarker {
+fn variable_ref_or_call(p: &mut Parser) -> CompletedMarker {
     assert!(p.at(TokenKind::Ident));
 
     let m = p.start();
     p.bump();
-    m.complete(p, SyntaxKind::VariableRef)
+
+    // A name from an imported module, like `m.value`.
+    if p.at(TokenKind::Dot) {
+        p.bump();
+        p.expect(TokenKind::Ident);
+    }
+
+    if p.at(TokenKind::LParen) {
+        arg_list(p);
+        m.complete(p, SyntaxKind::CallExpr)
+    } else {
+        m.complete(p, SyntaxKind::VariableRef)
+    }
+}
+
+fn arg_list(p: &mut Parser) -> CompletedMarker {
+    assert!(p.at(TokenKind::LParen));
+
+    let m = p.start();
+    p.bump();
+
+    if !p.at(TokenKind::RParen) {
+        p.nested(|p| loop {
+            expr(p);
+
+            if p.at(TokenKind::Comma) {
+                p.bump();
+            } else {
+                break;
+            }
+        });
+    }
+
+    p.expect(TokenKind::RParen);
+
+    m.complete(p, SyntaxKind::ArgList)
 }
 
 fn prefix_expr(p: &mut Parser) -> CompletedMarker {
@@ -83,7 +127,9 @@ fn prefix_expr(p: &mut Parser) -> CompletedMarker {
 
     p.bump();
 
-    expr_binding_power(p, right_binding_power);
+    p.nested(|p| {
+        expr_binding_power(p, right_binding_power);
+    });
 
     m.complete(p, SyntaxKind::PrefixExpr)
 }
@@ -94,12 +140,31 @@ fn paren_expr(p: &mut Parser) -> CompletedMarker {
     let m = p.start();
 
     p.bump();
-    expr_binding_power(p, 0);
+    p.nested(|p| {
+        expr_binding_power(p, 0);
+    });
     p.expect(TokenKind::RParen);
 
     m.complete(p, SyntaxKind::ParenExpr)
 }
 
+pub(super) fn block(p: &mut Parser) -> CompletedMarker {
+    assert!(p.at(TokenKind::LBrace));
+
+    let m = p.start();
+    p.bump();
+
+    p.nested(|p| {
+        while !p.at(TokenKind::RBrace) && !p.at_end() {
+            stmt::stmt(p);
+        }
+    });
+
+    p.expect(TokenKind::RBrace);
+
+    m.complete(p, SyntaxKind::Block)
+}
+
 enum BinaryOp {
     Add,
     Sub,
@@ -146,6 +211,32 @@ mod tests {
         )
     }
 
+    #[test]
+    fn parse_qualified_variable_ref_and_call() {
+        check(
+            "m.value + m.f(1)",
+            expect![[r#"
+            Root@0..16
+              InfixExpr@0..16
+                VariableRef@0..8
+                  Ident@0..1 "m"
+                  Dot@1..2 "."
+                  Ident@2..7 "value"
+                  Whitespace@7..8 " "
+                Plus@8..9 "+"
+                Whitespace@9..10 " "
+                CallExpr@10..16
+                  Ident@10..11 "m"
+                  Dot@11..12 "."
+                  Ident@12..13 "f"
+                  ArgList@13..16
+                    LParen@13..14 "("
+                    Literal@14..15
+                      Number@14..15 "1"
+                    RParen@15..16 ")""#]],
+        );
+    }
+
     #[test]
     fn parse_variable_ref() {
         check(
@@ -404,7 +495,23 @@ mod tests {
                     LParen@0..1 "("
                     VariableRef@1..4
                       Ident@1..4 "foo"
-                error at 1..4: expected '+', '-', '*', '/' or ')'"#]],
+                error at 1..4: expected '.', '(', '+', '-', '*', '/' or ')'"#]],
+        );
+    }
+
+    #[test]
+    fn parse_unrecognized_character_in_parentheses() {
+        check(
+            "(1;",
+            expect![[r#"
+            Root@0..3
+              ParenExpr@0..3
+                LParen@0..1 "("
+                Literal@1..2
+                  Number@1..2 "1"
+                Error@2..3
+                  Error@2..3 ";"
+            error at 2..3: expected '+', '-', '*', '/' or ')', but found unrecognized character"#]],
         );
     }
 
@@ -413,15 +520,136 @@ mod tests {
         check(
             "(1+",
             expect![[r#"
-Root@0..3
-  ParenExpr@0..3
-    LParen@0..1 "("
-    InfixExpr@1..3
-      Literal@1..2
-        Number@1..2 "1"
-      Plus@2..3 "+"
-error at 2..3: expected number, identifier, '-' or '('
-error at 2..3: expected ')'"#]],
+                Root@0..3
+                  ParenExpr@0..3
+                    LParen@0..1 "("
+                    InfixExpr@1..3
+                      Literal@1..2
+                        Number@1..2 "1"
+                      Plus@2..3 "+"
+                error at 2..3: expected number, identifier, '-', '(' or '{'
+                error at 2..3: expected ')'"#]],
+        );
+    }
+
+    #[test]
+    fn parse_block() {
+        check(
+            "{ let a = 1 a }",
+            expect![[r#"
+            Root@0..15
+              Block@0..15
+                LBrace@0..1 "{"
+                Whitespace@1..2 " "
+                VariableDef@2..12
+                  LetKw@2..5 "let"
+                  Whitespace@5..6 " "
+                  Ident@6..7 "a"
+                  Whitespace@7..8 " "
+                  Equals@8..9 "="
+                  Whitespace@9..10 " "
+                  Literal@10..12
+                    Number@10..11 "1"
+                    Whitespace@11..12 " "
+                VariableRef@12..14
+                  Ident@12..13 "a"
+                  Whitespace@13..14 " "
+                RBrace@14..15 "}""#]],
+        );
+    }
+
+    #[test]
+    fn parse_unclosed_block() {
+        check(
+            "{ 1",
+            expect![[r#"
+                Root@0..3
+                  Block@0..3
+                    LBrace@0..1 "{"
+                    Whitespace@1..2 " "
+                    Literal@2..3
+                      Number@2..3 "1"
+                error at 2..3: expected '+', '-', '*', '/' or '}'"#]],
+        );
+    }
+
+    #[test]
+    fn parse_call_without_arguments() {
+        check(
+            "foo()",
+            expect![[r#"
+            Root@0..5
+              CallExpr@0..5
+                Ident@0..3 "foo"
+                ArgList@3..5
+                  LParen@3..4 "("
+                  RParen@4..5 ")""#]],
+        );
+    }
+
+    #[test]
+    fn parse_call_with_arguments() {
+        check(
+            "add(1, x * 2)",
+            expect![[r#"
+            Root@0..13
+              CallExpr@0..13
+                Ident@0..3 "add"
+                ArgList@3..13
+                  LParen@3..4 "("
+                  Literal@4..5
+                    Number@4..5 "1"
+                  Comma@5..6 ","
+                  Whitespace@6..7 " "
+                  InfixExpr@7..12
+                    VariableRef@7..9
+                      Ident@7..8 "x"
+                      Whitespace@8..9 " "
+                    Star@9..10 "*"
+                    Whitespace@10..11 " "
+                    Literal@11..12
+                      Number@11..12 "2"
+                  RParen@12..13 ")""#]],
+        );
+    }
+
+    #[test]
+    fn parse_call_in_binary_expression() {
+        check(
+            "1 + f(2)",
+            expect![[r#"
+            Root@0..8
+              InfixExpr@0..8
+                Literal@0..2
+                  Number@0..1 "1"
+                  Whitespace@1..2 " "
+                Plus@2..3 "+"
+                Whitespace@3..4 " "
+                CallExpr@4..8
+                  Ident@4..5 "f"
+                  ArgList@5..8
+                    LParen@5..6 "("
+                    Literal@6..7
+                      Number@6..7 "2"
+                    RParen@7..8 ")""#]],
+        );
+    }
+
+    #[test]
+    fn parse_call_with_unclosed_argument_list() {
+        check(
+            "f(1,",
+            expect![[r#"
+            Root@0..4
+              CallExpr@0..4
+                Ident@0..1 "f"
+                ArgList@1..4
+                  LParen@1..2 "("
+                  Literal@2..3
+                    Number@2..3 "1"
+                  Comma@3..4 ","
+            error at 3..4: expected number, identifier, '-', '(' or '{'
+            error at 3..4: expected ',' or ')'"#]],
         );
     }
 }
@@ -3,6 +3,12 @@ use super::*;
 pub(super) fn stmt(p: &mut Parser) -> Option<CompletedMarker> {
     if p.at(TokenKind::LetKw) {
         Some(variable_def(p))
+    } else if p.at(TokenKind::FnKw) {
+        Some(fn_def(p))
+    } else if p.at(TokenKind::ImportKw) {
+        Some(import(p))
+    } else if p.at(TokenKind::UseKw) {
+        Some(use_stmt(p))
     } else {
         expr::expr(p)
     }
@@ -21,6 +27,81 @@ fn variable_def(p: &mut Parser) -> CompletedMarker {
     m.complete(p, SyntaxKind::VariableDef)
 }
 
+fn fn_def(p: &mut Parser) -> CompletedMarker {
+    assert!(p.at(TokenKind::FnKw));
+    let m = p.start();
+    p.bump();
+
+    p.expect(TokenKind::Ident);
+
+    if p.at(TokenKind::LParen) {
+        param_list(p);
+    } else {
+        p.error();
+    }
+
+    if p.at(TokenKind::LBrace) {
+        expr::block(p);
+    } else {
+        p.error();
+    }
+
+    m.complete(p, SyntaxKind::FnDef)
+}
+
+fn import(p: &mut Parser) -> CompletedMarker {
+    assert!(p.at(TokenKind::ImportKw));
+    let m = p.start();
+    p.bump();
+
+    p.expect(TokenKind::String);
+    p.expect(TokenKind::AsKw);
+    p.expect(TokenKind::Ident);
+
+    m.complete(p, SyntaxKind::Import)
+}
+
+fn use_stmt(p: &mut Parser) -> CompletedMarker {
+    assert!(p.at(TokenKind::UseKw));
+    let m = p.start();
+    p.bump();
+
+    p.expect(TokenKind::Ident);
+    p.expect(TokenKind::Dot);
+    p.expect(TokenKind::Ident);
+
+    m.complete(p, SyntaxKind::Use)
+}
+
+fn param_list(p: &mut Parser) -> CompletedMarker {
+    assert!(p.at(TokenKind::LParen));
+    let m = p.start();
+    p.bump();
+
+    if p.at(TokenKind::Ident) {
+        param(p);
+
+        while p.at(TokenKind::Comma) {
+            p.bump();
+            param(p);
+        }
+    }
+
+    p.expect(TokenKind::RParen);
+
+    m.complete(p, SyntaxKind::ParamList)
+}
+
+fn param(p: &mut Parser) {
+    if p.at(TokenKind::Ident) {
+        let m = p.start();
+        p.bump();
+        m.complete(p, SyntaxKind::Param);
+    } else {
+        p.error();
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use expect_test::expect;
@@ -69,7 +150,192 @@ Root@0..13
                     Whitespace@15..16 " "
                     VariableRef@16..17
                       Ident@16..17 "a"
-                error at 8..11: expected number, identifier, '-' or '(', but found 'let'"#]],
+                error at 8..11: expected number, identifier, '-', '(' or '{', but found 'let'"#]],
+        );
+    }
+
+    #[test]
+    fn parse_function_definition() {
+        check(
+            "fn add(x, y) { x + y }",
+            expect![[r#"
+            Root@0..22
+              FnDef@0..22
+                FnKw@0..2 "fn"
+                Whitespace@2..3 " "
+                Ident@3..6 "add"
+                ParamList@6..13
+                  LParen@6..7 "("
+                  Param@7..8
+                    Ident@7..8 "x"
+                  Comma@8..9 ","
+                  Whitespace@9..10 " "
+                  Param@10..11
+                    Ident@10..11 "y"
+                  RParen@11..12 ")"
+                  Whitespace@12..13 " "
+                Block@13..22
+                  LBrace@13..14 "{"
+                  Whitespace@14..15 " "
+                  InfixExpr@15..21
+                    VariableRef@15..17
+                      Ident@15..16 "x"
+                      Whitespace@16..17 " "
+                    Plus@17..18 "+"
+                    Whitespace@18..19 " "
+                    VariableRef@19..21
+                      Ident@19..20 "y"
+                      Whitespace@20..21 " "
+                  RBrace@21..22 "}""#]],
+        );
+    }
+
+    #[test]
+    fn parse_import_and_use() {
+        check(
+            "import \"lib/math.ff\" as math\nuse math.square",
+            expect![[r#"
+                Root@0..44
+                  Import@0..29
+                    ImportKw@0..6 "import"
+                    Whitespace@6..7 " "
+                    String@7..20 "\"lib/math.ff\""
+                    Whitespace@20..21 " "
+                    AsKw@21..23 "as"
+                    Whitespace@23..24 " "
+                    Ident@24..28 "math"
+                    Whitespace@28..29 "\n"
+                  Use@29..44
+                    UseKw@29..32 "use"
+                    Whitespace@32..33 " "
+                    Ident@33..37 "math"
+                    Dot@37..38 "."
+                    Ident@38..44 "square""#]],
+        );
+    }
+
+    #[test]
+    fn recover_from_incomplete_import() {
+        check(
+            "import as m\nuse m.\nlet a = 1",
+            expect![[r#"
+            Root@0..28
+              Import@0..12
+                ImportKw@0..6 "import"
+                Whitespace@6..7 " "
+                Error@7..10
+                  AsKw@7..9 "as"
+                  Whitespace@9..10 " "
+                Error@10..12
+                  Ident@10..11 "m"
+                  Whitespace@11..12 "\n"
+              Use@12..19
+                UseKw@12..15 "use"
+                Whitespace@15..16 " "
+                Ident@16..17 "m"
+                Dot@17..18 "."
+                Whitespace@18..19 "\n"
+              VariableDef@19..28
+                LetKw@19..22 "let"
+                Whitespace@22..23 " "
+                Ident@23..24 "a"
+                Whitespace@24..25 " "
+                Equals@25..26 "="
+                Whitespace@26..27 " "
+                Literal@27..28
+                  Number@27..28 "1"
+            error at 7..9: expected string, but found 'as'
+            error at 10..11: expected 'as', but found identifier
+            error at 12..15: expected identifier, but found 'use'
+            error at 19..22: expected identifier, but found 'let'"#]],
+        );
+    }
+
+    #[test]
+    fn parse_function_definition_without_parameters() {
+        check(
+            "fn one() { 1 }",
+            expect![[r#"
+            Root@0..14
+              FnDef@0..14
+                FnKw@0..2 "fn"
+                Whitespace@2..3 " "
+                Ident@3..6 "one"
+                ParamList@6..9
+                  LParen@6..7 "("
+                  RParen@7..8 ")"
+                  Whitespace@8..9 " "
+                Block@9..14
+                  LBrace@9..10 "{"
+                  Whitespace@10..11 " "
+                  Literal@11..13
+                    Number@11..12 "1"
+                    Whitespace@12..13 " "
+                  RBrace@13..14 "}""#]],
+        );
+    }
+
+    #[test]
+    fn parse_function_definition_missing_comma() {
+        check(
+            "fn add(x y) { x }",
+            expect![[r#"
+            Root@0..17
+              FnDef@0..12
+                FnKw@0..2 "fn"
+                Whitespace@2..3 " "
+                Ident@3..6 "add"
+                ParamList@6..10
+                  LParen@6..7 "("
+                  Param@7..9
+                    Ident@7..8 "x"
+                    Whitespace@8..9 " "
+                  Error@9..10
+                    Ident@9..10 "y"
+                Error@10..12
+                  RParen@10..11 ")"
+                  Whitespace@11..12 " "
+              Block@12..17
+                LBrace@12..13 "{"
+                Whitespace@13..14 " "
+                VariableRef@14..16
+                  Ident@14..15 "x"
+                  Whitespace@15..16 " "
+                RBrace@16..17 "}"
+            error at 9..10: expected ',' or ')', but found identifier
+            error at 10..11: expected '{', but found ')'"#]],
+        );
+    }
+
+    #[test]
+    fn recover_on_fn_token() {
+        check(
+            "let a =\nfn f() { a }",
+            expect![[r#"
+            Root@0..20
+              VariableDef@0..8
+                LetKw@0..3 "let"
+                Whitespace@3..4 " "
+                Ident@4..5 "a"
+                Whitespace@5..6 " "
+                Equals@6..7 "="
+                Whitespace@7..8 "\n"
+              FnDef@8..20
+                FnKw@8..10 "fn"
+                Whitespace@10..11 " "
+                Ident@11..12 "f"
+                ParamList@12..15
+                  LParen@12..13 "("
+                  RParen@13..14 ")"
+                  Whitespace@14..15 " "
+                Block@15..20
+                  LBrace@15..16 "{"
+                  Whitespace@16..17 " "
+                  VariableRef@17..19
+                    Ident@17..18 "a"
+                    Whitespace@18..19 " "
+                  RBrace@19..20 "}"
+            error at 8..10: expected number, identifier, '-', '(' or '{', but found 'fn'"#]],
         );
     }
 }
@@ -0,0 +1,37 @@
+//! Large synthetic programs for benchmarking the parser and everything after it. Each one
+//! parses without errors and runs without failing.
+
+/// Thousands of variables, each defined in terms of the one before.
+pub fn variable_defs() -> String {
+    let mut input = "let a0 = 1\n".to_string();
+    for i in 1..5000 {
+        input += &format!("let a{} = a{} / 2 + {}\n", i, i - 1, i);
+    }
+    input
+}
+
+/// Lines of parenthesized expressions and blocks nested close to the parser's limit.
+pub fn nested_exprs() -> String {
+    let line = format!(
+        "{}1{}\n{}{}\n",
+        "(-".repeat(80),
+        " + 2)".repeat(80),
+        "{ ".repeat(200),
+        " }".repeat(200)
+    );
+    line.repeat(50)
+}
+
+/// A long run of comments, with a little code at the end.
+pub fn comments() -> String {
+    "# a comment that goes on for a while, as some of them do\n".repeat(20_000) + "let a = 1\n"
+}
+
+/// A function that calls the one before it twice, so half a million calls in all.
+pub fn calls() -> String {
+    let mut input = "fn f0(n) { n }\n".to_string();
+    for i in 1..=18 {
+        input += &format!("fn f{}(n) {{ f{}(n + 1) - f{}(n) }}\n", i, i - 1, i - 1);
+    }
+    input + "f18(0)\n"
+}
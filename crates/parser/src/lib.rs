@@ -1,18 +1,28 @@
+mod dump;
 mod event;
 mod grammar;
+#[cfg(feature = "bench")]
+pub mod inputs;
 mod parser;
+mod reparse;
 mod sink;
 mod source;
+#[cfg(feature = "bench")]
+pub mod stages;
+mod tokens;
 
-use lexer::Lexer;
-use parser::{ParseError, Parser};
+use parser::Parser;
 use rowan::GreenNode;
 use sink::Sink;
 use source::Source;
-use syntax::SyntaxNode;
+use syntax::{SyntaxNode, TextEdit};
+use tokens::Tokens;
+
+pub use dump::{green_node_from_json, green_node_from_sexp, LoadError};
+pub use parser::ParseError;
 
 pub fn parse(input: &str) -> Parse {
-    let tokens: Vec<_> = Lexer::new(input).collect();
+    let tokens = Tokens::new(input);
     let source = Source::new(&tokens);
     let parser = Parser::new(source);
     let events = parser.parse();
@@ -21,17 +31,30 @@ pub fn parse(input: &str) -> Parse {
     sink.finish()
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Parse {
     green_node: GreenNode,
     errors: Vec<ParseError>,
 }
 
 impl Parse {
+    pub fn syntax(&self) -> SyntaxNode {
+        SyntaxNode::new_root(self.green_node.clone())
+    }
+
+    pub fn errors(&self) -> &[ParseError] {
+        &self.errors
+    }
+
+    /// Parses the text after `edit`, reusing as much of this parse as possible.
+    pub fn reparse(&self, edit: TextEdit) -> Parse {
+        reparse::reparse(self, &edit)
+    }
+
     pub fn debug_tree(&self) -> String {
         let mut s = String::new();
 
-        let syntax_node = SyntaxNode::new_root(self.green_node.clone());
-        let tree = format!("{:#?}", syntax_node);
+        let tree = format!("{:#?}", self.syntax());
 
         s.push_str(&tree[0..tree.len() - 1]);
 
@@ -48,3 +71,206 @@ fn check(input: &str, expected_tree: expect_test::Expect) {
     let parse = parse(input);
     expected_tree.assert_eq(&parse.debug_tree());
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use expect_test::expect_file;
+    use proptest::collection::vec;
+    use proptest::prelude::*;
+    use std::fs;
+    use std::path::Path;
+    use text_size::TextSize;
+
+    /// Parses `input`, checking that the tree holds exactly its text, with every byte of it in
+    /// exactly one token.
+    fn parse_losslessly(input: &str) -> Parse {
+        let parse = parse(input);
+        let root = parse.syntax();
+
+        assert_eq!(root.text(), input);
+
+        let mut end = TextSize::from(0);
+        for token in root
+            .descendants_with_tokens()
+            .filter_map(|e| e.into_token())
+        {
+            assert_eq!(
+                token.text_range().start(),
+                end,
+                "gap or overlap before {:?}",
+                token
+            );
+            end = token.text_range().end();
+        }
+        assert_eq!(end, TextSize::of(input));
+
+        parse
+    }
+
+    /// Parses every `.ff` file in `test_data/parser/ok` and `test_data/parser/err`, comparing
+    /// the tree to the `.rast` file next to it. Files in `ok` must parse without errors, and
+    /// files in `err` with at least one.
+    #[test]
+    fn parse_test_data() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("test_data/parser");
+
+        for (subdir, has_errors) in [("ok", false), ("err", true)] {
+            let mut paths: Vec<_> = fs::read_dir(dir.join(subdir))
+                .unwrap()
+                .map(|entry| entry.unwrap().path())
+                .filter(|path| path.extension().is_some_and(|extension| extension == "ff"))
+                .collect();
+            paths.sort();
+
+            for path in paths {
+                let input = fs::read_to_string(&path).unwrap();
+                let parse = parse_losslessly(&input);
+
+                assert_eq!(
+                    !parse.errors().is_empty(),
+                    has_errors,
+                    "{}:\n{}",
+                    path.display(),
+                    parse.debug_tree()
+                );
+                expect_file![path.with_extension("rast")].assert_eq(&parse.debug_tree());
+            }
+        }
+    }
+
+    const TOKENS: &[&str] = &[
+        "let", "fn", "import", "as", "use", "a", "b1", "12", "\"s\"", "+", "-", "*", "/", "=", "(",
+        ")", "{", "}", ",", ".", " ", "\n", "# c\n", "\t", ";", "é",
+    ];
+
+    const KEYWORDS: &[&str] = &["fn", "let", "import", "as", "use"];
+
+    type Tokens = Vec<String>;
+
+    fn name() -> impl Strategy<Value = String> {
+        "[a-z][a-z0-9]{0,4}".prop_filter("keywords aren't names", |name| {
+            !KEYWORDS.contains(&name.as_str())
+        })
+    }
+
+    fn join(parts: impl IntoIterator<Item = Tokens>) -> Tokens {
+        parts.into_iter().flatten().collect()
+    }
+
+    fn token(text: &str) -> Tokens {
+        vec![text.to_string()]
+    }
+
+    /// A comma-separated list in parentheses.
+    fn list(items: Vec<Tokens>) -> Tokens {
+        let mut tokens = token("(");
+        for (idx, item) in items.into_iter().enumerate() {
+            if idx > 0 {
+                tokens.push(",".to_string());
+            }
+            tokens.extend(item);
+        }
+        tokens.push(")".to_string());
+
+        tokens
+    }
+
+    fn block(stmts: Vec<Tokens>) -> Tokens {
+        join([token("{"), join(stmts), token("}")])
+    }
+
+    fn fn_def(name: String, params: Vec<String>, body: Vec<Tokens>) -> Tokens {
+        let params = params.into_iter().map(|param| vec![param]).collect();
+        join([token("fn"), vec![name], list(params), block(body)])
+    }
+
+    /// Statements that can be used inside `expr`, which is how deep they can nest.
+    fn stmt(expr: BoxedStrategy<Tokens>) -> BoxedStrategy<Tokens> {
+        prop_oneof![
+            expr.clone(),
+            (name(), expr.clone()).prop_map(|(name, value)| join([
+                token("let"),
+                vec![name],
+                token("="),
+                value
+            ])),
+            (name(), vec(name(), 0..3), vec(expr, 0..3))
+                .prop_map(|(name, params, body)| fn_def(name, params, body)),
+        ]
+        .boxed()
+    }
+
+    fn expr() -> BoxedStrategy<Tokens> {
+        let leaf = prop_oneof![
+            any::<u32>().prop_map(|n| vec![n.to_string()]),
+            name().prop_map(|name| vec![name]),
+            (name(), name()).prop_map(|(module, name)| vec![module, ".".to_string(), name]),
+        ];
+
+        leaf.prop_recursive(4, 32, 3, |expr| {
+            prop_oneof![
+                expr.clone().prop_map(|operand| join([token("-"), operand])),
+                (
+                    expr.clone(),
+                    prop::sample::select(&["+", "-", "*", "/"][..]),
+                    expr.clone()
+                )
+                    .prop_map(|(lhs, op, rhs)| join([lhs, token(op), rhs])),
+                expr.clone()
+                    .prop_map(|inner| join([token("("), inner, token(")")])),
+                (name(), vec(expr.clone(), 0..3))
+                    .prop_map(|(name, args)| join([vec![name], list(args)])),
+                vec(stmt(expr), 0..3).prop_map(block),
+            ]
+        })
+        .boxed()
+    }
+
+    fn program() -> impl Strategy<Value = String> {
+        let top_level = prop_oneof![
+            stmt(expr()),
+            (name(), name()).prop_map(|(path, alias)| {
+                join([
+                    token("import"),
+                    vec![format!("\"{}\"", path)],
+                    token("as"),
+                    vec![alias],
+                ])
+            }),
+            (name(), name()).prop_map(|(module, name)| join([
+                token("use"),
+                vec![module],
+                token("."),
+                vec![name]
+            ])),
+        ];
+
+        vec(top_level, 0..6)
+            .prop_map(join)
+            .prop_flat_map(|tokens| {
+                let trivia = prop::sample::select(&[" ", "\n", "  \n ", " # note\n"][..]);
+                (Just(tokens.clone()), vec(trivia, tokens.len()))
+            })
+            .prop_map(|(tokens, trivia)| {
+                tokens
+                    .iter()
+                    .zip(trivia)
+                    .map(|(token, trivia)| format!("{}{}", token, trivia))
+                    .collect()
+            })
+    }
+
+    proptest! {
+        #[test]
+        fn keep_every_byte_of_any_tokens(tokens in vec(prop::sample::select(TOKENS), 0..60)) {
+            parse_losslessly(&tokens.concat());
+        }
+
+        #[test]
+        fn parse_well_formed_programs_without_errors(input in program()) {
+            let parse = parse_losslessly(&input);
+            prop_assert!(parse.errors().is_empty(), "{}", parse.debug_tree());
+        }
+    }
+}
@@ -1,29 +1,56 @@
 pub(crate) mod marker;
 
 mod parse_error;
-pub(crate) use parse_error::ParseError;
+mod token_set;
+pub use parse_error::ParseError;
 
 use crate::event::Event;
 use crate::grammar;
 use crate::source::Source;
-use lexer::{Token, TokenKind};
+use lexer::TokenKind;
 use marker::Marker;
 use syntax::SyntaxKind;
+use text_size::TextRange;
+use token_set::{OrderedTokenSet, TokenSet};
 
-const RECOVERY_SET: [TokenKind; 1] = [TokenKind::LetKw];
+const RECOVERY_SET: TokenSet = TokenSet::new(&[
+    TokenKind::LetKw,
+    TokenKind::FnKw,
+    TokenKind::ImportKw,
+    TokenKind::UseKw,
+]);
+
+/// How deeply parenthesized expressions, prefix expressions, argument lists, blocks and binary
+/// operators can nest. Parsing them recurses, as does everything that walks the tree afterwards,
+/// so deeper input would overflow the stack. Operators are left-associative, so a chain like
+/// `1 + 1 + 1` nests one level for each of them.
+pub(crate) const MAX_DEPTH: usize = 256;
 
 pub(crate) struct Parser<'t, 'input> {
     source: Source<'t, 'input>,
     events: Vec<Event>,
-    expected_kinds: Vec<TokenKind>,
+    /// The kinds checked for since the last token, in the order errors list them in.
+    expected_kinds: OrderedTokenSet,
+    depth: usize,
+    /// The deepest level reached since the innermost call to `measure`.
+    reached: usize,
+    /// Whether the input nested too deeply, after which the rest of it was skipped.
+    bailed: bool,
 }
 
 impl<'t, 'input> Parser<'t, 'input> {
     pub fn new(source: Source<'t, 'input>) -> Self {
+        // Each token is added by one event, and most start and finish a node or so around
+        // them, so this is usually enough to never grow the events.
+        let events = Vec::with_capacity(source.significant_len() * 3);
+
         Self {
             source,
-            events: Vec::new(),
-            expected_kinds: Vec::new(),
+            events,
+            expected_kinds: OrderedTokenSet::default(),
+            depth: 0,
+            reached: 0,
+            bailed: false,
         }
     }
 
@@ -32,13 +59,21 @@ impl<'t, 'input> Parser<'t, 'input> {
         self.events
     }
 
+    /// Parses a lone block that is nested `depth` levels deep, for reparsing one that was
+    /// edited.
+    pub(crate) fn parse_block(mut self, depth: usize) -> Vec<Event> {
+        self.depth = depth;
+        grammar::block(&mut self);
+        self.events
+    }
+
     fn peek(&mut self) -> Option<TokenKind> {
         self.source.peek_kind()
     }
 
     pub(crate) fn bump(&mut self) {
         self.expected_kinds.clear();
-        self.source.next_token().unwrap();
+        self.source.bump();
         self.events.push(Event::AddToken);
     }
 
@@ -50,7 +85,7 @@ impl<'t, 'input> Parser<'t, 'input> {
     }
 
     pub(crate) fn at(&mut self, kind: TokenKind) -> bool {
-        self.expected_kinds.push(kind);
+        self.expected_kinds.insert(kind);
         self.peek() == Some(kind)
     }
 
@@ -62,30 +97,94 @@ impl<'t, 'input> Parser<'t, 'input> {
         }
     }
 
+    /// Runs `f` one level of nesting deeper. If that's too deep, the rest of the input is
+    /// skipped instead, since any error recovery would have to recurse just as deeply.
+    pub(crate) fn nested(&mut self, f: impl FnOnce(&mut Self)) {
+        if self.depth < MAX_DEPTH {
+            self.depth += 1;
+            self.reached = self.reached.max(self.depth);
+            f(self);
+            self.depth -= 1;
+        } else {
+            self.bail_out();
+        }
+    }
+
+    /// Runs `f`, also returning how many levels below the current one it nested.
+    pub(crate) fn measure<T>(&mut self, f: impl FnOnce(&mut Self) -> T) -> (T, usize) {
+        let outer = std::mem::replace(&mut self.reached, self.depth);
+        let result = f(self);
+        let height = self.reached - self.depth;
+        self.reached = self.reached.max(outer);
+
+        (result, height)
+    }
+
+    /// Records that what was just parsed nests `height` levels below the current one, skipping
+    /// the rest of the input like `nested` if that's too deep. Returns whether it was.
+    pub(crate) fn reach(&mut self, height: usize) -> bool {
+        self.reached = self.reached.max(self.depth + height);
+
+        let too_deep = self.depth + height > MAX_DEPTH;
+        if too_deep {
+            self.bail_out();
+        }
+
+        too_deep
+    }
+
+    fn bail_out(&mut self) {
+        let range = self.current_range();
+        self.events.push(Event::Error(ParseError {
+            expected: Vec::new(),
+            found: None,
+            range,
+            too_deep: true,
+        }));
+
+        let m = self.start();
+        while !self.at_end() {
+            self.bump();
+        }
+        m.complete(self, SyntaxKind::Error);
+
+        // Everything that's still open was cut off by skipping the input, which isn't worth
+        // reporting on its own.
+        self.bailed = true;
+    }
+
     pub(crate) fn error(&mut self) {
-        let current_token = self.source.peek_token();
+        if self.bailed {
+            return;
+        }
 
-        let (found, range) = if let Some(Token { kind, range, .. }) = current_token {
-            (Some(*kind), *range)
-        } else {
-            (None, self.source.last_token_range().unwrap())
-        };
+        let found = self.peek();
+        let range = self.current_range();
 
         self.events.push(Event::Error(ParseError {
-            expected: std::mem::take(&mut self.expected_kinds),
+            expected: std::mem::take(&mut self.expected_kinds).to_vec(),
             found,
             range,
+            too_deep: false,
         }));
 
-        if !self.at_set(&RECOVERY_SET) && !self.at_end() {
+        if !self.at_set(RECOVERY_SET) && !self.at_end() {
             let m = self.start();
             self.bump();
             m.complete(self, SyntaxKind::Error);
         }
     }
 
-    fn at_set(&mut self, set: &[TokenKind]) -> bool {
-        self.peek().map_or(false, |k| set.contains(&k))
+    /// The range of the current token, or of the last one at the end of the input.
+    fn current_range(&mut self) -> TextRange {
+        match self.source.peek_range() {
+            Some(range) => range,
+            None => self.source.last_token_range().unwrap(),
+        }
+    }
+
+    fn at_set(&mut self, set: TokenSet) -> bool {
+        self.peek().is_some_and(|k| set.contains(k))
     }
 
     pub(crate) fn at_end(&mut self) -> bool {
@@ -95,9 +194,17 @@ impl<'t, 'input> Parser<'t, 'input> {
 
 #[cfg(test)]
 mod tests {
+    use super::MAX_DEPTH;
     use crate::check;
     use expect_test::expect;
 
+    fn messages(input: &str) -> Vec<String> {
+        let parse = crate::parse(input);
+        assert_eq!(parse.syntax().to_string(), input);
+
+        parse.errors().iter().map(|error| error.message()).collect()
+    }
+
     #[test]
     fn parse_nothing() {
         check("", expect![r#"Root@0..0"#])
@@ -124,4 +231,43 @@ mod tests {
               Comment@0..8 "# hello!""##]],
         );
     }
+
+    #[test]
+    fn parse_nesting_up_to_the_limit() {
+        let nested = |depth| format!("{}1{}", "(".repeat(depth), ")".repeat(depth));
+
+        assert!(messages(&nested(MAX_DEPTH)).is_empty());
+        assert_eq!(messages(&nested(MAX_DEPTH + 1)), ["nesting too deep"]);
+    }
+
+    #[test]
+    fn bail_out_of_deep_nesting() {
+        for opener in ["(", "-", "{", "f(", "fn f() {", "{ let a = "] {
+            let input = opener.repeat(100_000);
+            assert_eq!(messages(&input), ["nesting too deep"], "{:?}", opener);
+        }
+    }
+
+    #[test]
+    fn count_operator_chains_as_nesting() {
+        let chain = |len| format!("1{}", " + 1".repeat(len));
+        assert!(messages(&chain(MAX_DEPTH)).is_empty());
+        assert_eq!(messages(&chain(MAX_DEPTH + 1)), ["nesting too deep"]);
+        assert_eq!(messages(&chain(100_000)), ["nesting too deep"]);
+
+        // Operands nested on either side count towards the chain's depth, and the chain towards
+        // whatever it's nested in.
+        let half = MAX_DEPTH / 2;
+        let nested = |depth| format!("{}1{}", "(".repeat(depth), ")".repeat(depth));
+        let deep_lhs = |len: usize| format!("{}{}", nested(half), " * 2".repeat(len));
+        let deep_rhs = |len: usize| format!("1 - {}", nested(half + len - 1));
+        let in_block =
+            |len: usize| format!("{}{}{}", "{ ".repeat(half), chain(len), " }".repeat(half));
+
+        let builds: [&dyn Fn(usize) -> String; 3] = [&deep_lhs, &deep_rhs, &in_block];
+        for build in builds {
+            assert!(messages(&build(MAX_DEPTH - half)).is_empty());
+            assert_eq!(messages(&build(MAX_DEPTH - half + 1)), ["nesting too deep"]);
+        }
+    }
 }
@@ -3,21 +3,27 @@ use std::fmt::Display;
 use lexer::TokenKind;
 use text_size::TextRange;
 
-#[derive(Debug, PartialEq)]
-pub(crate) struct ParseError {
-    pub(super) expected: Vec<TokenKind>,
-    pub(super) found: Option<TokenKind>,
-    pub(super) range: TextRange,
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub(crate) expected: Vec<TokenKind>,
+    pub(crate) found: Option<TokenKind>,
+    pub(crate) range: TextRange,
+    /// Whether the input nested too deeply here, rather than having an unexpected token.
+    pub(crate) too_deep: bool,
 }
 
-impl Display for ParseError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(
-            f,
-            "error at {}..{}: expected ",
-            u32::from(self.range.start()),
-            u32::from(self.range.end()),
-        )?;
+impl ParseError {
+    pub fn range(&self) -> TextRange {
+        self.range
+    }
+
+    /// The error message without the range, as shown by editors next to the source.
+    pub fn message(&self) -> String {
+        if self.too_deep {
+            return "nesting too deep".to_string();
+        }
+
+        let mut message = String::from("expected ");
 
         let num_expected = self.expected.len();
         let is_first = |idx| idx == 0;
@@ -25,18 +31,31 @@ impl Display for ParseError {
 
         for (idx, expected_kind) in self.expected.iter().enumerate() {
             if is_first(idx) {
-                write!(f, "{}", expected_kind)?;
+                message.push_str(&format!("{}", expected_kind));
             } else if is_last(idx) {
-                write!(f, " or {}", expected_kind)?;
+                message.push_str(&format!(" or {}", expected_kind));
             } else {
-                write!(f, ", {}", expected_kind)?;
+                message.push_str(&format!(", {}", expected_kind));
             }
         }
 
         if let Some(found) = self.found {
-            write!(f, ", but found {}", found)?;
+            message.push_str(&format!(", but found {}", found));
         }
-        Ok(())
+
+        message
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "error at {}..{}: {}",
+            u32::from(self.range.start()),
+            u32::from(self.range.end()),
+            self.message(),
+        )
     }
 }
 
@@ -59,6 +78,7 @@ mod tests {
                 let end = range.end.into();
                 TextRange::new(start, end)
             },
+            too_deep: false,
         };
 
         assert_eq!(format!("{}", error), output)
@@ -99,6 +119,18 @@ mod tests {
         )
     }
 
+    #[test]
+    fn nesting_too_deep() {
+        let error = ParseError {
+            expected: Vec::new(),
+            found: None,
+            range: TextRange::new(3.into(), 4.into()),
+            too_deep: true,
+        };
+
+        assert_eq!(format!("{}", error), "error at 3..4: nesting too deep");
+    }
+
     #[test]
     fn two_expected_did_find() {
         check(
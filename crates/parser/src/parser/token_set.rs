@@ -0,0 +1,110 @@
+use lexer::TokenKind;
+use num_traits::ToPrimitive;
+
+/// A set of token kinds, one bit for each.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct TokenSet(u32);
+
+impl TokenSet {
+    pub(crate) const fn new(kinds: &[TokenKind]) -> Self {
+        let mut bits = 0;
+        let mut idx = 0;
+
+        while idx < kinds.len() {
+            bits |= 1 << kinds[idx] as u32;
+            idx += 1;
+        }
+
+        Self(bits)
+    }
+
+    /// Adds `kind`, returning whether it wasn't in the set already.
+    pub(crate) fn insert(&mut self, kind: TokenKind) -> bool {
+        let added = !self.contains(kind);
+        self.0 |= Self::bit(kind);
+        added
+    }
+
+    pub(crate) fn contains(self, kind: TokenKind) -> bool {
+        self.0 & Self::bit(kind) != 0
+    }
+
+    fn bit(kind: TokenKind) -> u32 {
+        1 << kind.to_u32().unwrap()
+    }
+}
+
+/// One slot for each bit of a [`TokenSet`], so there's always room to write past the last kind.
+const SLOTS: usize = u32::BITS as usize;
+
+/// Token kinds in the order they were first added, without allocating.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct OrderedTokenSet {
+    set: TokenSet,
+    kinds: [TokenKind; SLOTS],
+    len: usize,
+}
+
+impl Default for OrderedTokenSet {
+    fn default() -> Self {
+        Self {
+            set: TokenSet::default(),
+            kinds: [TokenKind::Error; SLOTS],
+            len: 0,
+        }
+    }
+}
+
+impl OrderedTokenSet {
+    pub(crate) fn insert(&mut self, kind: TokenKind) {
+        // Writing the kind whether or not it's new avoids a branch, and wrapping the index avoids
+        // a bounds check, which matters since this is done for every kind the parser checks for.
+        self.kinds[self.len % SLOTS] = kind;
+        self.len += usize::from(self.set.insert(kind));
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.set = TokenSet::default();
+        self.len = 0;
+    }
+
+    pub(crate) fn to_vec(self) -> Vec<TokenKind> {
+        self.kinds[..self.len].to_vec()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_kind_fits() {
+        let last = TokenKind::Error;
+        assert!(last.to_u32().unwrap() < u32::BITS);
+        assert!(TokenSet::new(&[last]).contains(last));
+    }
+
+    #[test]
+    fn insert_each_kind_once() {
+        let mut set = TokenSet::default();
+        assert!(set.insert(TokenKind::RParen));
+        assert!(set.insert(TokenKind::Plus));
+        assert!(!set.insert(TokenKind::RParen));
+
+        assert!(set.contains(TokenKind::Plus));
+        assert!(!set.contains(TokenKind::Minus));
+    }
+
+    #[test]
+    fn list_kinds_in_the_order_they_were_added() {
+        let mut set = OrderedTokenSet::default();
+        set.insert(TokenKind::RParen);
+        set.insert(TokenKind::Plus);
+        set.insert(TokenKind::RParen);
+        assert_eq!(set.to_vec(), [TokenKind::RParen, TokenKind::Plus]);
+
+        set.clear();
+        set.insert(TokenKind::Minus);
+        assert_eq!(set.to_vec(), [TokenKind::Minus]);
+    }
+}
@@ -0,0 +1,411 @@
+use crate::parser::{ParseError, Parser};
+use crate::sink::Sink;
+use crate::source::Source;
+use crate::tokens::Tokens;
+use crate::Parse;
+use lexer::{Lexer, TokenKind};
+use rowan::{GreenNode, GreenToken, Language, TextRange, TextSize};
+use syntax::{FelixFlowLanguage, SyntaxKind, SyntaxNode, SyntaxToken, TextEdit};
+
+/// Applies `edit` to the text of `parse`, reparsing as little as possible.
+///
+/// An edit inside a single identifier, number, comment or whitespace token that leaves it as one
+/// token of the same kind only relexes that token. Otherwise the smallest block around the edit
+/// whose braces still match is relexed and reparsed on its own. Statements outside of blocks
+/// have no closing delimiter, so editing them falls back to parsing the whole text again.
+///
+/// Input that nests too deeply is skipped from that point on, and an edit anywhere before it
+/// can change how much is skipped, so a parse that went too deep is always redone in full.
+pub(crate) fn reparse(parse: &Parse, edit: &TextEdit) -> Parse {
+    let too_deep = parse.errors().iter().any(|error| error.too_deep);
+
+    if !too_deep {
+        if let Some(parse) = reparse_part(parse, edit) {
+            return parse;
+        }
+    }
+
+    let mut text = parse.syntax().text().to_string();
+    edit.apply(&mut text);
+
+    crate::parse(&text)
+}
+
+fn reparse_part(parse: &Parse, edit: &TextEdit) -> Option<Parse> {
+    let root = parse.syntax();
+
+    if let Some(parse) = reparse_token(&root, parse.errors(), edit) {
+        return Some(parse);
+    }
+
+    root.covering_element(edit.delete)
+        .ancestors()
+        .filter(|node| node.kind() == SyntaxKind::Block)
+        .find_map(|block| reparse_block(&block, parse.errors(), edit))
+}
+
+fn reparse_token(root: &SyntaxNode, errors: &[ParseError], edit: &TextEdit) -> Option<Parse> {
+    let token = root.covering_element(edit.delete).into_token()?;
+
+    if !matches!(
+        token.kind(),
+        SyntaxKind::Ident | SyntaxKind::Number | SyntaxKind::Comment | SyntaxKind::Whitespace
+    ) {
+        return None;
+    }
+
+    let old_range = token.text_range();
+    let mut text = token.text().to_string();
+    TextEdit::replace(edit.delete - old_range.start(), edit.insert.clone()).apply(&mut text);
+
+    if !relexes_alone(&token, &text) {
+        return None;
+    }
+
+    let green_token = GreenToken::new(FelixFlowLanguage::kind_to_raw(token.kind()), &text);
+    let new_range = TextRange::at(old_range.start(), TextSize::of(text.as_str()));
+
+    let errors = errors
+        .iter()
+        .map(|error| {
+            let range = if error.range == old_range {
+                new_range
+            } else {
+                shift(error.range, old_range.end(), edit)
+            };
+
+            ParseError {
+                range,
+                ..error.clone()
+            }
+        })
+        .collect();
+
+    Some(finish(token.replace_with(green_token), errors))
+}
+
+/// Whether `text` lexes as a single token of the same kind as `token`, without merging with the
+/// tokens on either side.
+fn relexes_alone(token: &SyntaxToken, text: &str) -> bool {
+    let prev = token.prev_token();
+    let next = token.next_token();
+
+    let mut expected = Vec::new();
+    let mut combined = String::new();
+
+    for (kind, text) in [
+        prev.as_ref().map(|prev| (prev.kind(), prev.text())),
+        Some((token.kind(), text)),
+        next.as_ref().map(|next| (next.kind(), next.text())),
+    ]
+    .into_iter()
+    .flatten()
+    {
+        expected.push((kind, text.len()));
+        combined.push_str(text);
+    }
+
+    let actual: Vec<_> = Lexer::new(&combined)
+        .map(|token| (SyntaxKind::from(token.kind), token.text.len()))
+        .collect();
+
+    actual == expected
+}
+
+fn reparse_block(block: &SyntaxNode, errors: &[ParseError], edit: &TextEdit) -> Option<Parse> {
+    let old_range = block.text_range();
+
+    // A block that was never closed ran into the end of the input, so the errors there came from
+    // parsing it and may not apply any more.
+    if !old_range.contains_range(edit.delete) || !is_closed(block) {
+        return None;
+    }
+
+    let mut text = block.text().to_string();
+    TextEdit::replace(edit.delete - old_range.start(), edit.insert.clone()).apply(&mut text);
+
+    let tokens = Tokens::new(&text);
+    if !is_one_block(tokens.kinds()) || merges_with_next(&tokens, block) {
+        return None;
+    }
+
+    // The nodes the parser goes a level deeper for, so that the block can't nest any deeper
+    // than it could when parsing the whole text.
+    let depth = block
+        .ancestors()
+        .skip(1)
+        .filter(|node| {
+            matches!(
+                node.kind(),
+                SyntaxKind::InfixExpr
+                    | SyntaxKind::ParenExpr
+                    | SyntaxKind::PrefixExpr
+                    | SyntaxKind::ArgList
+                    | SyntaxKind::Block
+            )
+        })
+        .count();
+    let events = Parser::new(Source::new(&tokens)).parse_block(depth);
+    let new_block = Sink::new(&tokens, events).finish();
+
+    if new_block.errors.iter().any(|error| error.too_deep) {
+        return None;
+    }
+
+    // Error recovery can swallow braces, so the block may close earlier or later than the
+    // braces suggest, in which case the rest of the file would be parsed differently too.
+    let new_node = SyntaxNode::new_root(new_block.green_node.clone());
+    if !is_closed(&new_node) || new_node.text_range().len() != TextSize::of(text.as_str()) {
+        return None;
+    }
+
+    let mut before = Vec::new();
+    let mut after = Vec::new();
+
+    for error in errors {
+        if error.found.is_some() && error.range.end() <= old_range.start() {
+            before.push(error.clone());
+        } else if error.found.is_some() && error.range.start() >= old_range.end() {
+            after.push(ParseError {
+                range: shift(error.range, old_range.end(), edit),
+                ..error.clone()
+            });
+        } else if error.found.is_none() {
+            after.push(error.clone());
+        }
+    }
+
+    let inside = new_block.errors.into_iter().map(|error| ParseError {
+        range: error.range + old_range.start(),
+        ..error
+    });
+
+    let errors = before.into_iter().chain(inside).chain(after).collect();
+
+    Some(finish(block.replace_with(new_block.green_node), errors))
+}
+
+/// Whether `tokens` start with an opening brace whose matching closing brace is the last token
+/// other than trivia.
+fn is_one_block(kinds: &[TokenKind]) -> bool {
+    let mut kinds = kinds.iter().filter(|kind| !kind.is_trivia());
+
+    if kinds.next() != Some(&TokenKind::LBrace) {
+        return false;
+    }
+
+    let mut depth = 1;
+
+    for kind in kinds {
+        if depth == 0 {
+            return false;
+        }
+
+        match kind {
+            TokenKind::LBrace => depth += 1,
+            TokenKind::RBrace => depth -= 1,
+            _ => {}
+        }
+    }
+
+    depth == 0
+}
+
+/// Whether the new last token of `block` would merge with the token after it, like a comment that
+/// lost its newline.
+fn merges_with_next(tokens: &Tokens<'_>, block: &SyntaxNode) -> bool {
+    let Some(next) = block.last_token().and_then(|token| token.next_token()) else {
+        return false;
+    };
+    let Some(last) = tokens.len().checked_sub(1) else {
+        return true;
+    };
+
+    let (kind, text) = (tokens.kind(last).unwrap(), tokens.text(last));
+    let combined = format!("{}{}", text, next.text());
+    let kinds: Vec<_> = Lexer::new(&combined)
+        .map(|token| (token.kind, token.text.len()))
+        .collect();
+
+    kinds.len() != 2 || kinds[0] != (kind, text.len())
+}
+
+fn is_closed(block: &SyntaxNode) -> bool {
+    block
+        .children_with_tokens()
+        .filter(|element| !element.kind().is_trivia())
+        .last()
+        .is_some_and(|element| element.kind() == SyntaxKind::RBrace)
+}
+
+/// Moves `range` along with the text after an edit that ended at or before `edit_end`.
+fn shift(range: TextRange, edit_end: TextSize, edit: &TextEdit) -> TextRange {
+    if range.start() < edit_end {
+        return range;
+    }
+
+    let start = i64::from(u32::from(range.start())) + edit.len_delta();
+    TextRange::at(TextSize::from(start as u32), range.len())
+}
+
+/// Builds the new parse, pointing errors at the end of the input to its new last token.
+fn finish(green_node: GreenNode, mut errors: Vec<ParseError>) -> Parse {
+    let root = SyntaxNode::new_root(green_node.clone());
+    let last_token_range = root.last_token().map(|token| token.text_range());
+
+    for error in &mut errors {
+        if error.found.is_none() {
+            error.range = last_token_range.unwrap();
+        }
+    }
+
+    Parse { green_node, errors }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{parse, Parse};
+    use proptest::prelude::*;
+    use syntax::{SyntaxNode, TextEdit};
+    use text_size::{TextRange, TextSize};
+
+    /// Checks that reparsing `text` after `edit` gives the same result as parsing it from
+    /// scratch, returning the old and new parses.
+    fn check(text: &str, edit: TextEdit) -> (Parse, Parse) {
+        let mut new_text = text.to_string();
+        edit.apply(&mut new_text);
+
+        let old = parse(text);
+        let new = old.reparse(edit);
+        assert_eq!(new.debug_tree(), parse(&new_text).debug_tree());
+
+        (old, new)
+    }
+
+    fn nth_stmt(root: &SyntaxNode, n: usize) -> SyntaxNode {
+        root.children().nth(n).unwrap()
+    }
+
+    fn same_green(a: &SyntaxNode, b: &SyntaxNode) -> bool {
+        std::ptr::eq(&*a.green(), &*b.green())
+    }
+
+    #[test]
+    fn relex_edited_identifier() {
+        let (old, new) = check("let abc = 1\nlet d = abc", TextEdit::insert(5.into(), "x"));
+        assert!(same_green(
+            &nth_stmt(&old.syntax(), 1),
+            &nth_stmt(&new.syntax(), 1)
+        ));
+    }
+
+    #[test]
+    fn reparse_edited_block() {
+        let (old, new) = check(
+            "let a = 1\nfn f(x) { x + 1 }\nlet b = { 2 }",
+            TextEdit::replace(TextRange::new(20.into(), 21.into()), "*"),
+        );
+
+        let (old, new) = (old.syntax(), new.syntax());
+        assert!(same_green(&nth_stmt(&old, 0), &nth_stmt(&new, 0)));
+        assert!(same_green(&nth_stmt(&old, 2), &nth_stmt(&new, 2)));
+    }
+
+    #[test]
+    fn keep_errors_outside_reparsed_block() {
+        check(
+            "let = 1\n{ let a = 1 }\nlet b =",
+            TextEdit::replace(TextRange::new(18.into(), 19.into()), "+"),
+        );
+    }
+
+    #[test]
+    fn fall_back_when_braces_change() {
+        check(
+            "{ 1 }\n{ 2 }",
+            TextEdit::delete(TextRange::new(4.into(), 5.into())),
+        );
+        check(
+            "{ let a = 1 }\n2",
+            TextEdit::delete(TextRange::new(10.into(), 11.into())),
+        );
+    }
+
+    #[test]
+    fn fall_back_when_token_merges() {
+        check(
+            "let a = b",
+            TextEdit::delete(TextRange::new(3.into(), 4.into())),
+        );
+        check("let a = 1", TextEdit::insert(1.into(), "x"));
+    }
+
+    #[test]
+    fn move_end_of_input_errors() {
+        check("f({ 1 }", TextEdit::insert(7.into(), "  "));
+        check("f({ 1 } ", TextEdit::insert(4.into(), "2"));
+    }
+
+    #[test]
+    fn keep_nesting_limit_when_reparsing_block() {
+        let depth = crate::parser::MAX_DEPTH - 2;
+        let text = format!("{}{{ 1 }}{}", "(".repeat(depth), ")".repeat(depth));
+        let block = TextSize::try_from(depth + 2).unwrap();
+
+        let (_, new) = check(
+            &text,
+            TextEdit::replace(TextRange::at(block, 1.into()), "((1))"),
+        );
+        assert_eq!(new.errors().len(), 1);
+
+        let deeper = format!("{}{{ ((1)) }}{}", "(".repeat(depth), ")".repeat(depth));
+        check(
+            &deeper,
+            TextEdit::replace(TextRange::at(block, 5.into()), "1"),
+        );
+
+        let chain = format!("{{ 1 }}{}", " + 1".repeat(depth));
+        let (_, new) = check(
+            &chain,
+            TextEdit::replace(TextRange::at(2.into(), 1.into()), "((1))"),
+        );
+        assert_eq!(new.errors().len(), 1);
+    }
+
+    const FRAGMENTS: &[&str] = &[
+        "let", "fn", "a", "b1", "12", "+", "-", "*", "/", "=", "(", ")", "{", "}", ",", " ", "\n",
+        "# c\n",
+    ];
+
+    fn source() -> impl Strategy<Value = String> {
+        prop::collection::vec(prop::sample::select(FRAGMENTS), 0..40)
+            .prop_map(|fragments| fragments.concat())
+    }
+
+    fn text_and_edit() -> impl Strategy<Value = (String, TextEdit)> {
+        (
+            source(),
+            source().prop_map(|s| s.chars().take(3).collect::<String>()),
+        )
+            .prop_flat_map(|(text, insert)| {
+                let len = text.len();
+                (Just(text), 0..=len, 0..=len, Just(insert)).prop_map(|(text, a, b, insert)| {
+                    let range = TextRange::new(
+                        TextSize::try_from(a.min(b)).unwrap(),
+                        TextSize::try_from(a.max(b)).unwrap(),
+                    );
+                    (text, TextEdit::replace(range, insert))
+                })
+            })
+    }
+
+    proptest! {
+        #[test]
+        fn incremental_and_full_parses_agree((text, edit) in text_and_edit()) {
+            let mut new_text = text.clone();
+            edit.apply(&mut new_text);
+
+            prop_assert_eq!(parse(&text).reparse(edit).debug_tree(), parse(&new_text).debug_tree());
+        }
+    }
+}
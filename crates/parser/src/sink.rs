@@ -1,26 +1,31 @@
 use super::event::Event;
+use crate::tokens::Tokens;
 use crate::{parser::ParseError, Parse};
-use lexer::{Token, TokenKind};
+use lexer::TokenKind;
 use rowan::{GreenNodeBuilder, Language};
 use std::mem;
-use syntax::FelixFlowLanguage;
+use syntax::{FelixFlowLanguage, SyntaxKind};
 
 pub(crate) struct Sink<'t, 'input> {
     builder: GreenNodeBuilder<'static>,
-    tokens: &'t [Token<'input>],
+    tokens: &'t Tokens<'input>,
     cursor: usize,
     events: Vec<Event>,
     errors: Vec<ParseError>,
+    /// The kinds of the nodes started at once by a chain of forward parents, kept between them
+    /// to reuse its allocation.
+    kinds: Vec<SyntaxKind>,
 }
 
 impl<'t, 'input> Sink<'t, 'input> {
-    pub(crate) fn new(tokens: &'t [Token<'input>], events: Vec<Event>) -> Self {
+    pub(crate) fn new(tokens: &'t Tokens<'input>, events: Vec<Event>) -> Self {
         Self {
             builder: GreenNodeBuilder::new(),
             tokens,
             cursor: 0,
             events,
             errors: Vec::new(),
+            kinds: Vec::new(),
         }
     }
 
@@ -31,7 +36,7 @@ impl<'t, 'input> Sink<'t, 'input> {
                     kind,
                     foward_parent,
                 } => {
-                    let mut kinds = vec![kind];
+                    self.kinds.push(kind);
 
                     let mut idx = idx;
                     let mut foward_parent = foward_parent;
@@ -45,14 +50,14 @@ impl<'t, 'input> Sink<'t, 'input> {
                         } =
                             mem::replace(&mut self.events[idx], Event::Placeholder)
                         {
-                            kinds.push(kind);
+                            self.kinds.push(kind);
                             foward_parent
                         } else {
                             unreachable!()
                         };
                     }
 
-                    for kind in kinds.into_iter().rev() {
+                    for kind in self.kinds.drain(..).rev() {
                         self.builder
                             .start_node(FelixFlowLanguage::kind_to_raw(kind));
                     }
@@ -73,18 +78,20 @@ impl<'t, 'input> Sink<'t, 'input> {
     }
 
     fn token(&mut self) {
-        let Token { kind, text, .. } = self.tokens[self.cursor];
-        self.builder
-            .token(FelixFlowLanguage::kind_to_raw(kind.into()), text.into());
+        let kind = self.tokens.kind(self.cursor).unwrap();
+        self.builder.token(
+            FelixFlowLanguage::kind_to_raw(kind.into()),
+            self.tokens.text(self.cursor),
+        );
         self.cursor += 1;
     }
 
     fn eat_trivia(&mut self) {
-        while let Some(token) = self.tokens.get(self.cursor) {
-            if !TokenKind::from(token.kind).is_trivia() {
-                break;
-            }
-
+        while self
+            .tokens
+            .kind(self.cursor)
+            .is_some_and(TokenKind::is_trivia)
+        {
             self.token()
         }
     }
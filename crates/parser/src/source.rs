@@ -1,22 +1,26 @@
-use lexer::{Token, TokenKind};
+use crate::tokens::Tokens;
+use lexer::TokenKind;
 use rowan::TextRange;
 
 pub(crate) struct Source<'t, 'input> {
-    tokens: &'t [Token<'input>],
+    tokens: &'t Tokens<'input>,
     cursor: usize,
 }
 
 impl<'t, 'input> Source<'t, 'input> {
-    pub(crate) fn new(tokens: &'t [Token<'input>]) -> Self {
+    pub(crate) fn new(tokens: &'t Tokens<'input>) -> Self {
         Self { tokens, cursor: 0 }
     }
 
-    pub(crate) fn next_token(&mut self) -> Option<&'t Token<'input>> {
+    /// How many tokens there are to parse, not counting trivia.
+    pub(crate) fn significant_len(&self) -> usize {
+        self.tokens.significant_len()
+    }
+
+    pub(crate) fn bump(&mut self) {
         self.eat_trivia();
-        let token = self.tokens.get(self.cursor)?;
+        assert!(self.cursor < self.tokens.len());
         self.cursor += 1;
-
-        Some(token)
     }
 
     pub(crate) fn peek_kind(&mut self) -> Option<TokenKind> {
@@ -24,13 +28,14 @@ impl<'t, 'input> Source<'t, 'input> {
         self.peek_raw()
     }
 
-    pub(crate) fn peek_token(&mut self) -> Option<&Token> {
+    pub(crate) fn peek_range(&mut self) -> Option<TextRange> {
         self.eat_trivia();
-        self.peek_token_raw()
+        (self.cursor < self.tokens.len()).then(|| self.tokens.range(self.cursor))
     }
 
     pub(crate) fn last_token_range(&self) -> Option<TextRange> {
-        self.tokens.last().map(|Token { range, .. }| *range)
+        let last = self.tokens.len().checked_sub(1)?;
+        Some(self.tokens.range(last))
     }
 
     fn eat_trivia(&mut self) {
@@ -40,16 +45,10 @@ impl<'t, 'input> Source<'t, 'input> {
     }
 
     fn at_trivia(&self) -> bool {
-        self.peek_raw().map_or(false, TokenKind::is_trivia)
+        self.peek_raw().is_some_and(TokenKind::is_trivia)
     }
 
     fn peek_raw(&self) -> Option<TokenKind> {
-        self.tokens
-            .get(self.cursor)
-            .map(|Token { kind, .. }| (*kind).into())
-    }
-
-    fn peek_token_raw(&self) -> Option<&Token> {
-        self.tokens.get(self.cursor)
+        self.tokens.kind(self.cursor)
     }
 }
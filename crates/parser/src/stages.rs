@@ -0,0 +1,24 @@
+//! The stages of [`crate::parse`], run one at a time so that they can be benchmarked separately.
+
+use crate::event::Event;
+use crate::parser::Parser;
+use crate::sink::Sink;
+use crate::source::Source;
+use crate::Parse;
+
+pub use crate::tokens::Tokens;
+
+/// What the parser found in some tokens, to be built into a tree.
+pub struct Events(Vec<Event>);
+
+pub fn lex(input: &str) -> Tokens<'_> {
+    Tokens::new(input)
+}
+
+pub fn parse(tokens: &Tokens<'_>) -> Events {
+    Events(Parser::new(Source::new(tokens)).parse())
+}
+
+pub fn build(tokens: &Tokens<'_>, events: Events) -> Parse {
+    Sink::new(tokens, events.0).finish()
+}
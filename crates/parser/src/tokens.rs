@@ -0,0 +1,59 @@
+use lexer::{Lexer, TokenKind};
+use text_size::{TextRange, TextSize};
+
+/// The tokens of some text, kept as their kinds and where each one starts. A token's text is
+/// sliced out of the input when it's needed, rather than being stored alongside it.
+pub struct Tokens<'input> {
+    input: &'input str,
+    kinds: Vec<TokenKind>,
+    /// Where each token starts, followed by where the input ends.
+    starts: Vec<TextSize>,
+    /// How many of the tokens aren't trivia.
+    significant: usize,
+}
+
+impl<'input> Tokens<'input> {
+    pub(crate) fn new(input: &'input str) -> Self {
+        let mut kinds = Vec::new();
+        let mut starts = Vec::new();
+        let mut significant = 0;
+
+        for token in Lexer::new(input) {
+            significant += usize::from(!token.kind.is_trivia());
+            kinds.push(token.kind);
+            starts.push(token.range.start());
+        }
+        starts.push(TextSize::of(input));
+
+        Self {
+            input,
+            kinds,
+            starts,
+            significant,
+        }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.kinds.len()
+    }
+
+    pub(crate) fn significant_len(&self) -> usize {
+        self.significant
+    }
+
+    pub(crate) fn kinds(&self) -> &[TokenKind] {
+        &self.kinds
+    }
+
+    pub(crate) fn kind(&self, idx: usize) -> Option<TokenKind> {
+        self.kinds.get(idx).copied()
+    }
+
+    pub(crate) fn range(&self, idx: usize) -> TextRange {
+        TextRange::new(self.starts[idx], self.starts[idx + 1])
+    }
+
+    pub(crate) fn text(&self, idx: usize) -> &'input str {
+        &self.input[self.range(idx)]
+    }
+}
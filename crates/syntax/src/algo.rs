@@ -0,0 +1,160 @@
+use crate::{AstNode, SyntaxElement, SyntaxKind, SyntaxNode, SyntaxToken};
+use rowan::{TextRange, TextSize};
+
+/// The token at `offset`. When `offset` is between two tokens, identifiers are preferred, and
+/// then anything that isn't trivia, so that a cursor right after a name still finds it.
+pub fn token_at_offset(root: &SyntaxNode, offset: TextSize) -> Option<SyntaxToken> {
+    if !root.text_range().contains_inclusive(offset) {
+        return None;
+    }
+
+    root.token_at_offset(offset)
+        .max_by_key(|token| match token.kind() {
+            SyntaxKind::Ident => 2,
+            kind if kind.is_trivia() => 0,
+            _ => 1,
+        })
+}
+
+/// The smallest node or token whose range contains `range`, or `None` if `range` isn't inside
+/// `root`.
+pub fn covering_element(root: &SyntaxNode, range: TextRange) -> Option<SyntaxElement> {
+    root.text_range()
+        .contains_range(range)
+        .then(|| root.covering_element(range))
+}
+
+/// The ancestors of the tokens on either side of `offset`, smallest first.
+pub fn ancestors_at_offset(
+    root: &SyntaxNode,
+    offset: TextSize,
+) -> impl Iterator<Item = SyntaxNode> {
+    let mut ancestors: Vec<_> = if root.text_range().contains_inclusive(offset) {
+        root.token_at_offset(offset)
+            .flat_map(|token| token.parent_ancestors())
+            .collect()
+    } else {
+        Vec::new()
+    };
+
+    // Sorting is stable, so of two nodes with the same length the inner one still comes first.
+    ancestors.sort_by_key(|node| node.text_range().len());
+    ancestors.dedup();
+
+    ancestors.into_iter()
+}
+
+/// The innermost node of type `N` at `offset`.
+pub fn find_node_at_offset<N: AstNode>(root: &SyntaxNode, offset: TextSize) -> Option<N> {
+    ancestors_at_offset(root, offset).find_map(N::cast)
+}
+
+/// `node` and its ancestors of the given kind, innermost first.
+pub fn ancestors_of_kind(node: &SyntaxNode, kind: SyntaxKind) -> impl Iterator<Item = SyntaxNode> {
+    node.ancestors().filter(move |node| node.kind() == kind)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FelixFlowLanguage;
+    use lexer::Lexer;
+    use rowan::{GreenNodeBuilder, Language};
+
+    /// Builds `let a = bc + 1` by hand, as the parser can't be used from here.
+    fn tree() -> SyntaxNode {
+        let mut builder = GreenNodeBuilder::new();
+        let mut tokens = Lexer::new("let a = bc + 1").map(|token| {
+            (
+                FelixFlowLanguage::kind_to_raw(token.kind.into()),
+                token.text,
+            )
+        });
+        let mut token = |builder: &mut GreenNodeBuilder, n| {
+            for _ in 0..n {
+                let (kind, text) = tokens.next().unwrap();
+                builder.token(kind, text);
+            }
+        };
+        let start = |builder: &mut GreenNodeBuilder, kind| {
+            builder.start_node(FelixFlowLanguage::kind_to_raw(kind));
+        };
+
+        start(&mut builder, SyntaxKind::Root);
+        start(&mut builder, SyntaxKind::VariableDef);
+        token(&mut builder, 6);
+        start(&mut builder, SyntaxKind::InfixExpr);
+        start(&mut builder, SyntaxKind::VariableRef);
+        token(&mut builder, 2);
+        builder.finish_node();
+        token(&mut builder, 2);
+        start(&mut builder, SyntaxKind::Literal);
+        token(&mut builder, 1);
+        builder.finish_node();
+        builder.finish_node();
+        builder.finish_node();
+        builder.finish_node();
+
+        SyntaxNode::new_root(builder.finish())
+    }
+
+    struct VariableRef(SyntaxNode);
+
+    impl AstNode for VariableRef {
+        fn cast(node: SyntaxNode) -> Option<Self> {
+            (node.kind() == SyntaxKind::VariableRef).then_some(Self(node))
+        }
+
+        fn syntax(&self) -> &SyntaxNode {
+            &self.0
+        }
+    }
+
+    fn kind_at(offset: u32) -> Option<SyntaxKind> {
+        token_at_offset(&tree(), offset.into()).map(|token| token.kind())
+    }
+
+    #[test]
+    fn prefer_identifiers_and_then_anything_but_trivia() {
+        assert_eq!(kind_at(4), Some(SyntaxKind::Ident));
+        assert_eq!(kind_at(5), Some(SyntaxKind::Ident));
+        assert_eq!(kind_at(7), Some(SyntaxKind::Equals));
+        assert_eq!(kind_at(11), Some(SyntaxKind::Plus));
+        assert_eq!(kind_at(14), Some(SyntaxKind::Number));
+        assert_eq!(kind_at(15), None);
+    }
+
+    #[test]
+    fn find_nodes_next_to_whitespace() {
+        let root = tree();
+        let at = |offset: u32| find_node_at_offset::<VariableRef>(&root, offset.into());
+
+        assert!(at(7).is_none());
+        assert_eq!(
+            at(8).unwrap().syntax().text_range(),
+            TextRange::new(8.into(), 11.into())
+        );
+        assert!(at(10).is_some());
+        assert!(at(11).is_some());
+        assert!(at(12).is_none());
+    }
+
+    #[test]
+    fn cover_ranges_and_walk_ancestors() {
+        let root = tree();
+        let covering = |start: u32, end: u32| {
+            covering_element(&root, TextRange::new(start.into(), end.into()))
+                .map(|element| element.kind())
+        };
+
+        assert_eq!(covering(8, 9), Some(SyntaxKind::Ident));
+        assert_eq!(covering(8, 13), Some(SyntaxKind::InfixExpr));
+        assert_eq!(covering(10, 20), None);
+
+        let variable_ref = find_node_at_offset::<VariableRef>(&root, 9.into()).unwrap();
+        let defs: Vec<_> =
+            ancestors_of_kind(variable_ref.syntax(), SyntaxKind::VariableDef).collect();
+        assert_eq!(defs.len(), 1);
+        assert_eq!(defs[0].text_range(), root.text_range());
+    }
+}
@@ -1,16 +1,30 @@
 #[macro_use]
 extern crate num_derive;
 
+mod algo;
+mod line_index;
+mod text_edit;
+
 use lexer::TokenKind;
 use num_traits::{FromPrimitive, ToPrimitive};
 
+pub use algo::{
+    ancestors_at_offset, ancestors_of_kind, covering_element, find_node_at_offset, token_at_offset,
+};
+pub use line_index::{LineCol, LineIndex};
+pub use text_edit::TextEdit;
+
 #[derive(Debug, Clone, Copy, PartialEq, FromPrimitive, ToPrimitive, Eq, PartialOrd, Ord, Hash)]
 pub enum SyntaxKind {
     Whitespace,
     FnKw,
     LetKw,
+    ImportKw,
+    AsKw,
+    UseKw,
     Ident,
     Number,
+    String,
     Plus,
     Minus,
     Star,
@@ -20,6 +34,8 @@ pub enum SyntaxKind {
     RBrace,
     LParen,
     RParen,
+    Comma,
+    Dot,
     Comment,
     Root,
     InfixExpr,
@@ -28,17 +44,35 @@ pub enum SyntaxKind {
     PrefixExpr,
     VariableDef,
     VariableRef,
+    FnDef,
+    ParamList,
+    Param,
+    Block,
+    CallExpr,
+    ArgList,
+    Import,
+    Use,
     Error,
 }
 
+impl SyntaxKind {
+    pub fn is_trivia(self) -> bool {
+        matches!(self, Self::Whitespace | Self::Comment)
+    }
+}
+
 impl From<TokenKind> for SyntaxKind {
     fn from(token_kind: TokenKind) -> Self {
         match token_kind {
             TokenKind::Whitespace => Self::Whitespace,
             TokenKind::FnKw => Self::FnKw,
             TokenKind::LetKw => Self::LetKw,
+            TokenKind::ImportKw => Self::ImportKw,
+            TokenKind::AsKw => Self::AsKw,
+            TokenKind::UseKw => Self::UseKw,
             TokenKind::Ident => Self::Ident,
             TokenKind::Number => Self::Number,
+            TokenKind::String => Self::String,
             TokenKind::Plus => Self::Plus,
             TokenKind::Minus => Self::Minus,
             TokenKind::Star => Self::Star,
@@ -46,9 +80,12 @@ impl From<TokenKind> for SyntaxKind {
             TokenKind::Equals => Self::Equals,
             TokenKind::LParen => Self::LParen,
             TokenKind::RParen => Self::RParen,
+            TokenKind::Comma => Self::Comma,
+            TokenKind::Dot => Self::Dot,
             TokenKind::LBrace => Self::LBrace,
             TokenKind::RBrace => Self::RBrace,
             TokenKind::Comment => Self::Comment,
+            TokenKind::Error => Self::Error,
         }
     }
 }
@@ -69,3 +106,12 @@ impl rowan::Language for FelixFlowLanguage {
 }
 
 pub type SyntaxNode = rowan::SyntaxNode<FelixFlowLanguage>;
+pub type SyntaxToken = rowan::SyntaxToken<FelixFlowLanguage>;
+pub type SyntaxElement = rowan::SyntaxElement<FelixFlowLanguage>;
+
+/// A typed view of a node in the tree.
+pub trait AstNode: Sized {
+    fn cast(node: SyntaxNode) -> Option<Self>;
+
+    fn syntax(&self) -> &SyntaxNode;
+}
@@ -0,0 +1,143 @@
+use rowan::TextSize;
+
+/// Maps byte offsets in a piece of text to zero-based line and column numbers.
+///
+/// More files can be appended, with their offsets following on from those before them, for
+/// code linked together from several files. Their lines are counted from the start of each file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineIndex {
+    line_starts: Vec<TextSize>,
+    /// The first line of each file.
+    file_starts: Vec<usize>,
+    len: TextSize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LineCol {
+    pub line: u32,
+    pub col: u32,
+}
+
+impl LineIndex {
+    pub fn new(text: &str) -> Self {
+        let line_starts = std::iter::once(TextSize::from(0))
+            .chain(
+                text.match_indices('\n')
+                    .map(|(idx, _)| TextSize::try_from(idx + 1).unwrap()),
+            )
+            .collect();
+
+        Self {
+            line_starts,
+            file_starts: vec![0],
+            len: TextSize::of(text),
+        }
+    }
+
+    /// Indexes `text` as another file after everything indexed so far, returning the offset
+    /// it starts at.
+    pub fn append(&mut self, text: &str) -> TextSize {
+        let start = self.len;
+
+        self.file_starts.push(self.line_starts.len());
+        self.line_starts.push(start);
+        self.line_starts.extend(
+            text.match_indices('\n')
+                .map(|(idx, _)| start + TextSize::try_from(idx + 1).unwrap()),
+        );
+        self.len += TextSize::of(text);
+
+        start
+    }
+
+    pub fn line_col(&self, offset: TextSize) -> LineCol {
+        let line = self.line_starts.partition_point(|&start| start <= offset) - 1;
+        let col = offset - self.line_starts[line];
+        let file_start =
+            self.file_starts[self.file_starts.partition_point(|&start| start <= line) - 1];
+
+        LineCol {
+            line: (line - file_start) as u32,
+            col: col.into(),
+        }
+    }
+
+    /// The inverse of [`LineIndex::line_col`] for the first file, or `None` if `line` is past
+    /// its last line. Columns past the end of a line are not checked.
+    pub fn offset(&self, line_col: LineCol) -> Option<TextSize> {
+        let lines = self
+            .file_starts
+            .get(1)
+            .copied()
+            .unwrap_or(self.line_starts.len());
+        let start = self.line_starts[..lines].get(line_col.line as usize)?;
+        Some(*start + TextSize::from(line_col.col))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(text: &str, offset: u32, line: u32, col: u32) {
+        let line_index = LineIndex::new(text);
+
+        assert_eq!(line_index.line_col(offset.into()), LineCol { line, col });
+        assert_eq!(
+            line_index.offset(LineCol { line, col }),
+            Some(offset.into())
+        );
+    }
+
+    #[test]
+    fn start_of_text() {
+        check("let a = 1", 0, 0, 0);
+    }
+
+    #[test]
+    fn middle_of_first_line() {
+        check("let a = 1", 4, 0, 4);
+    }
+
+    #[test]
+    fn newline_belongs_to_line_it_ends() {
+        check("a\nb", 1, 0, 1);
+    }
+
+    #[test]
+    fn start_of_later_line() {
+        check("a\n\nlet b = 2", 3, 2, 0);
+    }
+
+    #[test]
+    fn end_of_text() {
+        check("a\nbc", 4, 1, 2);
+    }
+
+    #[test]
+    fn count_lines_of_appended_files_from_their_start() {
+        let mut line_index = LineIndex::new("a\nb\n");
+        assert_eq!(line_index.append("cd\ne"), TextSize::from(4));
+        assert_eq!(line_index.append("f"), TextSize::from(8));
+
+        let line_col = |offset: u32| line_index.line_col(offset.into());
+        assert_eq!(line_col(2), LineCol { line: 1, col: 0 });
+        assert_eq!(line_col(5), LineCol { line: 0, col: 1 });
+        assert_eq!(line_col(7), LineCol { line: 1, col: 0 });
+        assert_eq!(line_col(8), LineCol { line: 0, col: 0 });
+
+        assert_eq!(
+            line_index.offset(LineCol { line: 2, col: 0 }),
+            Some(4.into())
+        );
+        assert_eq!(line_index.offset(LineCol { line: 3, col: 0 }), None);
+    }
+
+    #[test]
+    fn no_offset_past_last_line() {
+        assert_eq!(
+            LineIndex::new("a\nb").offset(LineCol { line: 2, col: 0 }),
+            None
+        );
+    }
+}
@@ -0,0 +1,67 @@
+use rowan::{TextRange, TextSize};
+
+/// Replaces the text in `delete` with `insert`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextEdit {
+    pub delete: TextRange,
+    pub insert: String,
+}
+
+impl TextEdit {
+    pub fn replace(delete: TextRange, insert: impl Into<String>) -> Self {
+        Self {
+            delete,
+            insert: insert.into(),
+        }
+    }
+
+    pub fn insert(offset: TextSize, insert: impl Into<String>) -> Self {
+        Self::replace(TextRange::empty(offset), insert)
+    }
+
+    pub fn delete(delete: TextRange) -> Self {
+        Self::replace(delete, "")
+    }
+
+    pub fn apply(&self, text: &mut String) {
+        text.replace_range(std::ops::Range::<usize>::from(self.delete), &self.insert);
+    }
+
+    /// How much longer the text is after the edit; negative if it's shorter.
+    pub fn len_delta(&self) -> i64 {
+        self.insert.len() as i64 - i64::from(u32::from(self.delete.len()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(edit: TextEdit, expected: &str) {
+        let mut text = String::from("let a = 1");
+        edit.apply(&mut text);
+
+        assert_eq!(text, expected);
+    }
+
+    #[test]
+    fn replace_range() {
+        check(
+            TextEdit::replace(TextRange::new(4.into(), 5.into()), "foo"),
+            "let foo = 1",
+        );
+    }
+
+    #[test]
+    fn insert_at_end() {
+        check(TextEdit::insert(9.into(), " + 2"), "let a = 1 + 2");
+    }
+
+    #[test]
+    fn delete_range() {
+        check(
+            TextEdit::delete(TextRange::new(5.into(), 9.into())),
+            "let a",
+        );
+    }
+}
@@ -0,0 +1,37 @@
+//! How fast both backends run large synthetic programs, not counting parsing and compiling.
+
+use ast::AstNode;
+use criterion::{criterion_group, criterion_main, Criterion};
+use eval::Interpreter;
+use parser::inputs;
+use syntax::LineIndex;
+use vm::{Module, Vm};
+
+fn bench(c: &mut Criterion) {
+    let inputs = [
+        ("variable defs", inputs::variable_defs()),
+        ("nested exprs", inputs::nested_exprs()),
+        ("calls", inputs::calls()),
+    ];
+
+    for (name, input) in &inputs {
+        let parse = parser::parse(input);
+        assert!(parse.errors().is_empty(), "{} has errors", name);
+        let program = hir::lower(ast::Root::cast(parse.syntax()).unwrap());
+
+        let mut module = Module::new();
+        let main = vm::compile(&mut module, &program, &LineIndex::new(input)).unwrap();
+
+        let mut group = c.benchmark_group(format!("eval {}", name));
+
+        group.bench_function("tree", |b| {
+            b.iter(|| Interpreter::new().run(&program).unwrap())
+        });
+        group.bench_function("vm", |b| b.iter(|| Vm::new().run(&module, &main).unwrap()));
+
+        group.finish();
+    }
+}
+
+criterion_group!(benches, bench);
+criterion_main!(benches);
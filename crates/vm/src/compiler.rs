@@ -0,0 +1,230 @@
+use crate::{Body, CompileError, LineEntry, Module, OpCode};
+use hir::{BinaryOp, Expr, Program, Stmt, StmtKind, UnaryOp};
+use syntax::LineIndex;
+use text_size::TextRange;
+
+/// Compiles a program into `module`, returning the body of its top-level code. `line_index`
+/// must cover the ranges in the program, and is used for the debug line table.
+pub fn compile(
+    module: &mut Module,
+    program: &Program,
+    line_index: &LineIndex,
+) -> Result<Body, CompileError> {
+    // Functions are declared up front so that calls can refer to functions defined later on.
+    for fn_def in &program.fns {
+        module
+            .function_index(&fn_def.name)
+            .ok_or(CompileError::TooManyFunctions)?;
+    }
+
+    for fn_def in &program.fns {
+        let mut compiler = FnCompiler::new(module, line_index);
+        compiler.mark_line(fn_def.range);
+
+        for param in &fn_def.params {
+            compiler.declare_local(param)?;
+        }
+
+        compiler.compile_expr(&fn_def.body)?;
+        let body = compiler.finish(fn_def.params.len())?;
+
+        let idx = module.function_index(&fn_def.name).unwrap();
+        module.functions[usize::from(idx)].body = Some(body);
+    }
+
+    let mut compiler = FnCompiler::new(module, line_index);
+    compiler.compile_stmts(&program.stmts, Scope::Global)?;
+    compiler.finish(0)
+}
+
+#[derive(Clone, Copy)]
+enum Scope {
+    Global,
+    Local,
+}
+
+struct FnCompiler<'m> {
+    module: &'m mut Module,
+    line_index: &'m LineIndex,
+    code: Vec<u8>,
+    lines: Vec<LineEntry>,
+    locals: Vec<String>,
+    num_locals: usize,
+}
+
+impl<'m> FnCompiler<'m> {
+    fn new(module: &'m mut Module, line_index: &'m LineIndex) -> Self {
+        Self {
+            module,
+            line_index,
+            code: Vec::new(),
+            lines: Vec::new(),
+            locals: Vec::new(),
+            num_locals: 0,
+        }
+    }
+
+    fn finish(mut self, arity: usize) -> Result<Body, CompileError> {
+        self.emit(OpCode::Return);
+
+        Ok(Body {
+            arity: u8::try_from(arity).map_err(|_| CompileError::TooManyArguments)?,
+            num_locals: u16::try_from(self.num_locals).map_err(|_| CompileError::TooManyLocals)?,
+            code: self.code,
+            lines: self.lines,
+        })
+    }
+
+    /// Compiles a statement list, leaving the value of its last statement on the stack.
+    fn compile_stmts(&mut self, stmts: &[Stmt], scope: Scope) -> Result<(), CompileError> {
+        let scope_start = self.locals.len();
+
+        if stmts.is_empty() {
+            self.emit(OpCode::Unit);
+        }
+
+        for (idx, stmt) in stmts.iter().enumerate() {
+            let is_last = idx == stmts.len() - 1;
+            self.mark_line(stmt.range);
+
+            match &stmt.kind {
+                StmtKind::VariableDef { name, value } => {
+                    self.compile_expr(value)?;
+
+                    match scope {
+                        Scope::Global => {
+                            let global = self
+                                .module
+                                .global_index(name)
+                                .ok_or(CompileError::TooManyGlobals)?;
+                            self.emit_with_u16(OpCode::SetGlobal, global);
+                        }
+                        Scope::Local => {
+                            let slot = self.declare_local(name)?;
+                            self.emit_with_u16(OpCode::SetLocal, slot);
+                        }
+                    }
+
+                    if is_last {
+                        self.emit(OpCode::Unit);
+                    }
+                }
+                StmtKind::Expr(expr) => {
+                    self.compile_expr(expr)?;
+
+                    if !is_last {
+                        self.emit(OpCode::Pop);
+                    }
+                }
+            }
+        }
+
+        self.locals.truncate(scope_start);
+
+        Ok(())
+    }
+
+    fn compile_expr(&mut self, expr: &Expr) -> Result<(), CompileError> {
+        match expr {
+            Expr::Missing => return Err(CompileError::MissingExpr),
+            Expr::Binary { op, lhs, rhs } => {
+                self.compile_expr(lhs)?;
+                self.compile_expr(rhs)?;
+
+                self.emit(match op {
+                    BinaryOp::Add => OpCode::Add,
+                    BinaryOp::Sub => OpCode::Sub,
+                    BinaryOp::Mul => OpCode::Mul,
+                    BinaryOp::Div => OpCode::Div,
+                });
+            }
+            Expr::Literal { n } => {
+                let n = i64::try_from(*n).map_err(|_| CompileError::LiteralOutOfRange(*n))?;
+
+                let idx = self
+                    .module
+                    .constant_index(n)
+                    .ok_or(CompileError::TooManyConstants)?;
+
+                self.emit_with_u16(OpCode::Constant, idx);
+            }
+            Expr::Unary { op, expr } => {
+                self.compile_expr(expr)?;
+
+                self.emit(match op {
+                    UnaryOp::Neg => OpCode::Neg,
+                });
+            }
+            Expr::VariableRef { var } => match self.resolve_local(var) {
+                Some(slot) => self.emit_with_u16(OpCode::GetLocal, slot),
+                None => {
+                    let global = self
+                        .module
+                        .global_index(var)
+                        .ok_or(CompileError::TooManyGlobals)?;
+                    self.emit_with_u16(OpCode::GetGlobal, global);
+                }
+            },
+            Expr::Block { stmts } => {
+                let line = self.lines.last().copied();
+                self.compile_stmts(stmts, Scope::Local)?;
+
+                if let Some(LineEntry { line, .. }) = line {
+                    self.set_line(line);
+                }
+            }
+            Expr::Call { callee, args } => {
+                for arg in args {
+                    self.compile_expr(arg)?;
+                }
+
+                let function = self
+                    .module
+                    .function_index(callee)
+                    .ok_or(CompileError::TooManyFunctions)?;
+                let argc = u8::try_from(args.len()).map_err(|_| CompileError::TooManyArguments)?;
+
+                self.emit_with_u16(OpCode::Call, function);
+                self.code.push(argc);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn mark_line(&mut self, range: TextRange) {
+        let line = self.line_index.line_col(range.start()).line + 1;
+        self.set_line(line);
+    }
+
+    fn set_line(&mut self, line: u32) {
+        let offset = self.code.len() as u32;
+
+        match self.lines.last_mut() {
+            Some(last) if last.line == line => {}
+            Some(last) if last.offset == offset => last.line = line,
+            _ => self.lines.push(LineEntry { offset, line }),
+        }
+    }
+
+    fn declare_local(&mut self, name: &str) -> Result<u16, CompileError> {
+        self.locals.push(name.to_string());
+        self.num_locals = self.num_locals.max(self.locals.len());
+
+        u16::try_from(self.locals.len() - 1).map_err(|_| CompileError::TooManyLocals)
+    }
+
+    fn resolve_local(&self, name: &str) -> Option<u16> {
+        let slot = self.locals.iter().rposition(|local| local == name)?;
+        Some(slot as u16)
+    }
+
+    fn emit(&mut self, op: OpCode) {
+        self.code.push(op as u8);
+    }
+
+    fn emit_with_u16(&mut self, op: OpCode, operand: u16) {
+        self.emit(op);
+        self.code.extend_from_slice(&operand.to_le_bytes());
+    }
+}
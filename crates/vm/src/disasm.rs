@@ -0,0 +1,94 @@
+use crate::{Body, Module, OpCode};
+use num_traits::FromPrimitive;
+use std::fmt::Write;
+
+/// Renders a human-readable listing of every function in `module` followed by `main`.
+pub fn disassemble(module: &Module, main: &Body) -> String {
+    let mut s = String::new();
+
+    write_table(&mut s, "constants", &module.constants);
+    write_table(&mut s, "globals", &module.globals);
+
+    for function in &module.functions {
+        match &function.body {
+            Some(body) => disassemble_body(&mut s, module, &function.name, body),
+            None => writeln!(s, "\nfn {} (undefined)", function.name).unwrap(),
+        }
+    }
+
+    disassemble_body(&mut s, module, "<main>", main);
+
+    s
+}
+
+fn write_table(s: &mut String, name: &str, entries: &[impl std::fmt::Display]) {
+    s.push_str(name);
+    s.push(':');
+
+    for (idx, entry) in entries.iter().enumerate() {
+        write!(s, " {}={}", idx, entry).unwrap();
+    }
+
+    s.push('\n');
+}
+
+fn disassemble_body(s: &mut String, module: &Module, name: &str, body: &Body) {
+    writeln!(
+        s,
+        "\nfn {}/{} ({} locals)",
+        name, body.arity, body.num_locals,
+    )
+    .unwrap();
+
+    let code = &body.code;
+    let mut offset = 0;
+    let mut last_line = None;
+
+    while offset < code.len() {
+        write!(s, "{:04} ", offset).unwrap();
+
+        let line = body.line_at(offset);
+        if line == last_line {
+            s.push_str("   | ");
+        } else if let Some(line) = line {
+            write!(s, "{:4} ", line).unwrap();
+        } else {
+            s.push_str("   ? ");
+        }
+        last_line = line;
+
+        let op = OpCode::from_u8(code[offset]).expect("invalid opcode");
+
+        let operand = |idx: usize| {
+            let bytes = [code[offset + 1 + idx], code[offset + 2 + idx]];
+            usize::from(u16::from_le_bytes(bytes))
+        };
+
+        match op {
+            OpCode::Constant => {
+                let idx = operand(0);
+                writeln!(s, "{:?} {} ({})", op, idx, module.constants[idx]).unwrap();
+            }
+            OpCode::GetGlobal | OpCode::SetGlobal => {
+                let idx = operand(0);
+                writeln!(s, "{:?} {} ({})", op, idx, module.globals[idx]).unwrap();
+            }
+            OpCode::GetLocal | OpCode::SetLocal => {
+                writeln!(s, "{:?} {}", op, operand(0)).unwrap();
+            }
+            OpCode::Call => {
+                let idx = operand(0);
+                let argc = code[offset + 3];
+                writeln!(
+                    s,
+                    "{:?} {} ({}) {}",
+                    op, idx, module.functions[idx].name, argc,
+                )
+                .unwrap();
+            }
+            _ => writeln!(s, "{:?}", op).unwrap(),
+        }
+
+        offset += 1 + op.operand_width();
+    }
+}
@@ -0,0 +1,28 @@
+use std::fmt::Display;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CompileError {
+    MissingExpr,
+    LiteralOutOfRange(u64),
+    TooManyConstants,
+    TooManyGlobals,
+    TooManyFunctions,
+    TooManyLocals,
+    TooManyArguments,
+}
+
+impl Display for CompileError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::MissingExpr => f.write_str("cannot compile a missing expression"),
+            Self::LiteralOutOfRange(n) => write!(f, "integer literal {} is out of range", n),
+            Self::TooManyConstants => f.write_str("too many constants in one module"),
+            Self::TooManyGlobals => f.write_str("too many globals in one module"),
+            Self::TooManyFunctions => f.write_str("too many functions in one module"),
+            Self::TooManyLocals => f.write_str("too many local variables in one function"),
+            Self::TooManyArguments => f.write_str("too many arguments in one call"),
+        }
+    }
+}
+
+impl std::error::Error for CompileError {}
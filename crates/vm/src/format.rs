@@ -0,0 +1,291 @@
+//! The binary module format written by `felix-flow compile`.
+//!
+//! All integers are little-endian. A file is the magic bytes and a `u16` format version,
+//! followed by the constant pool (`i64`s), the global table (names), the function table and
+//! finally the body of the top-level code. Counts and lengths are `u32`s, strings are a length
+//! followed by UTF-8 bytes, and each body is its arity (`u8`), local count (`u16`), code and
+//! debug line table.
+
+use crate::{Body, Function, LineEntry, Module, OpCode};
+use num_traits::FromPrimitive;
+use std::fmt::Display;
+
+pub const MAGIC: [u8; 4] = *b"FFBC";
+pub const VERSION: u16 = 1;
+
+/// A module together with the top-level code to run, as stored in a compiled file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Executable {
+    pub module: Module,
+    pub main: Body,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FormatError {
+    BadMagic,
+    UnsupportedVersion(u16),
+    UnexpectedEof,
+    InvalidUtf8,
+    TrailingBytes,
+    InvalidBytecode { function: String, offset: usize },
+}
+
+impl Display for FormatError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::BadMagic => f.write_str("not a felix-flow module"),
+            Self::UnsupportedVersion(version) => write!(
+                f,
+                "unsupported module version {} (expected {})",
+                version, VERSION,
+            ),
+            Self::UnexpectedEof => f.write_str("unexpected end of module"),
+            Self::InvalidUtf8 => f.write_str("invalid UTF-8 in module"),
+            Self::TrailingBytes => f.write_str("unexpected bytes after end of module"),
+            Self::InvalidBytecode { function, offset } => write!(
+                f,
+                "invalid bytecode in {} at offset {:04}",
+                function, offset,
+            ),
+        }
+    }
+}
+
+impl std::error::Error for FormatError {}
+
+impl Executable {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut writer = Writer::default();
+
+        writer.bytes.extend_from_slice(&MAGIC);
+        writer.u16(VERSION);
+
+        writer.len(self.module.constants.len());
+        for constant in &self.module.constants {
+            writer.bytes.extend_from_slice(&constant.to_le_bytes());
+        }
+
+        writer.len(self.module.globals.len());
+        for global in &self.module.globals {
+            writer.str(global);
+        }
+
+        writer.len(self.module.functions.len());
+        for function in &self.module.functions {
+            writer.str(&function.name);
+
+            match &function.body {
+                Some(body) => {
+                    writer.bytes.push(1);
+                    writer.body(body);
+                }
+                None => writer.bytes.push(0),
+            }
+        }
+
+        writer.body(&self.main);
+
+        writer.bytes
+    }
+
+    /// Reads an executable, checking that its bytecode is well-formed so that it can be run
+    /// without the VM ever reading out of bounds.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, FormatError> {
+        let mut reader = Reader { bytes, pos: 0 };
+
+        if reader.take(MAGIC.len())? != MAGIC {
+            return Err(FormatError::BadMagic);
+        }
+
+        let version = reader.u16()?;
+        if version != VERSION {
+            return Err(FormatError::UnsupportedVersion(version));
+        }
+
+        let mut module = Module::new();
+
+        for _ in 0..reader.u32()? {
+            let bytes = reader.take(8)?.try_into().unwrap();
+            module.constants.push(i64::from_le_bytes(bytes));
+        }
+
+        for _ in 0..reader.u32()? {
+            module.globals.push(reader.str()?);
+        }
+
+        for _ in 0..reader.u32()? {
+            let name = reader.str()?;
+            let body = match reader.u8()? {
+                0 => None,
+                _ => Some(reader.body()?),
+            };
+
+            module.functions.push(Function { name, body });
+        }
+
+        let main = reader.body()?;
+
+        if reader.pos != bytes.len() {
+            return Err(FormatError::TrailingBytes);
+        }
+
+        for function in &module.functions {
+            if let Some(body) = &function.body {
+                verify(&module, &function.name, body)?;
+            }
+        }
+        verify(&module, "<main>", &main)?;
+
+        Ok(Self { module, main })
+    }
+}
+
+/// Checks that every instruction in `body` is a valid opcode with in-bounds operands, that the
+/// stack never underflows, and that the code ends in a `Return`.
+fn verify(module: &Module, function: &str, body: &Body) -> Result<(), FormatError> {
+    let code = &body.code;
+    let mut offset = 0;
+    let mut depth: usize = 0;
+    let mut last_op = None;
+
+    while offset < code.len() {
+        let invalid = || FormatError::InvalidBytecode {
+            function: function.to_string(),
+            offset,
+        };
+
+        let op = OpCode::from_u8(code[offset]).ok_or_else(invalid)?;
+        let operands = code
+            .get(offset + 1..offset + 1 + op.operand_width())
+            .ok_or_else(invalid)?;
+        let operand = || usize::from(u16::from_le_bytes([operands[0], operands[1]]));
+
+        let (pops, pushes) = match op {
+            OpCode::Constant if operand() < module.constants.len() => (0, 1),
+            OpCode::GetLocal if operand() < usize::from(body.num_locals) => (0, 1),
+            OpCode::SetLocal if operand() < usize::from(body.num_locals) => (1, 0),
+            OpCode::GetGlobal if operand() < module.globals.len() => (0, 1),
+            OpCode::SetGlobal if operand() < module.globals.len() => (1, 0),
+            OpCode::Call if operand() < module.functions.len() => (usize::from(operands[2]), 1),
+            OpCode::Constant
+            | OpCode::GetLocal
+            | OpCode::SetLocal
+            | OpCode::GetGlobal
+            | OpCode::SetGlobal
+            | OpCode::Call => return Err(invalid()),
+            OpCode::Unit => (0, 1),
+            OpCode::Add | OpCode::Sub | OpCode::Mul | OpCode::Div => (2, 1),
+            OpCode::Neg => (1, 1),
+            OpCode::Pop | OpCode::Return => (1, 0),
+        };
+
+        depth = depth.checked_sub(pops).ok_or_else(invalid)? + pushes;
+        offset += 1 + op.operand_width();
+        last_op = Some(op);
+    }
+
+    if last_op == Some(OpCode::Return) {
+        Ok(())
+    } else {
+        Err(FormatError::InvalidBytecode {
+            function: function.to_string(),
+            offset,
+        })
+    }
+}
+
+#[derive(Default)]
+struct Writer {
+    bytes: Vec<u8>,
+}
+
+impl Writer {
+    fn u16(&mut self, n: u16) {
+        self.bytes.extend_from_slice(&n.to_le_bytes());
+    }
+
+    fn u32(&mut self, n: u32) {
+        self.bytes.extend_from_slice(&n.to_le_bytes());
+    }
+
+    fn len(&mut self, len: usize) {
+        self.u32(u32::try_from(len).expect("module table too large"));
+    }
+
+    fn str(&mut self, s: &str) {
+        self.len(s.len());
+        self.bytes.extend_from_slice(s.as_bytes());
+    }
+
+    fn body(&mut self, body: &Body) {
+        self.bytes.push(body.arity);
+        self.u16(body.num_locals);
+
+        self.len(body.code.len());
+        self.bytes.extend_from_slice(&body.code);
+
+        self.len(body.lines.len());
+        for LineEntry { offset, line } in &body.lines {
+            self.u32(*offset);
+            self.u32(*line);
+        }
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], FormatError> {
+        let bytes = self
+            .bytes
+            .get(self.pos..self.pos + len)
+            .ok_or(FormatError::UnexpectedEof)?;
+        self.pos += len;
+
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, FormatError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, FormatError> {
+        Ok(u16::from_le_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    fn u32(&mut self) -> Result<u32, FormatError> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn str(&mut self) -> Result<String, FormatError> {
+        let len = self.u32()? as usize;
+        let bytes = self.take(len)?;
+
+        String::from_utf8(bytes.to_vec()).map_err(|_| FormatError::InvalidUtf8)
+    }
+
+    fn body(&mut self) -> Result<Body, FormatError> {
+        let arity = self.u8()?;
+        let num_locals = self.u16()?;
+
+        let code_len = self.u32()? as usize;
+        let code = self.take(code_len)?.to_vec();
+
+        let mut lines = Vec::new();
+        for _ in 0..self.u32()? {
+            let offset = self.u32()?;
+            let line = self.u32()?;
+            lines.push(LineEntry { offset, line });
+        }
+
+        Ok(Body {
+            arity,
+            num_locals,
+            code,
+            lines,
+        })
+    }
+}
@@ -0,0 +1,423 @@
+#[macro_use]
+extern crate num_derive;
+
+mod compiler;
+mod disasm;
+mod error;
+pub mod format;
+mod module;
+mod opcode;
+mod vm;
+
+pub use compiler::compile;
+pub use disasm::disassemble;
+pub use error::CompileError;
+pub use format::{Executable, FormatError};
+pub use module::{Body, Function, LineEntry, Module};
+pub use opcode::OpCode;
+pub use vm::Vm;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ast::AstNode;
+    use eval::{EvalError, Interpreter, Limits, Value};
+    use expect_test::{expect, Expect};
+    use syntax::LineIndex;
+
+    /// Programs run through both backends by the differential tests.
+    const PROGRAMS: &[&str] = &[
+        "",
+        "42",
+        "1 + 2 * 3 - -4 / 2",
+        "((1 + 2) * (3 + 4)) / -(2 - 9)",
+        "let a = 1",
+        "let a = 10\nlet b = a * 2\nb - a",
+        "let a = 1\nlet a = a + 1\na",
+        "{ }",
+        "{ let x = 1 }",
+        "{ let x = 1 x }\nx",
+        "let x = 1\n{ let x = x + 1 x } + x",
+        "{ let a = 1 { let b = a + 1 { let c = b * 10 c + a } } }",
+        "fn add(x, y) { x + y }\nadd(1, 2) * 2",
+        "double(21)\nfn double(x) { x * 2 }",
+        "fn f() { 7 }\nfn g(x) { f() * x }\ng(g(2))",
+        "fn f(a, b, c) { let d = a * b { let e = d - c e * e } }\nf(3, 4, 2) + f(1, 1, 1)",
+        "let g = 1\nfn f() { g }\nf()",
+        "fn f() { g }\nf()",
+        "fn f() { x }\n{ let x = 1 f() }",
+        "fn f(x) { x }\nf(1, 2)",
+        "f()",
+        "fn f(x) { x }\nf(1 / 0, 2)",
+        "fn loop(n) { loop(n) }\nlet a = 0\na",
+        "1 / (2 - 2)",
+        "9223372036854775807 + 1",
+        "(-9223372036854775807 - 1) / -1",
+        "-(-9223372036854775807 - 1)",
+        "{ } + 1",
+        "{ } + 1 / 0",
+        "fn unit() { }\nunit() - missing()",
+        "-{ let a = 1 }",
+        "fn unit() { }\nunit() * 2",
+        "fn f(x) { --x }\nf(-9223372036854775807 - 1)",
+        "fn f(x) { x * 1 }\nf({ })",
+        "fn f(x) { 0 + x / 1 }\nf({ })",
+    ];
+
+    fn lower(input: &str) -> hir::Program {
+        let parse = parser::parse(input);
+        assert!(parse.errors().is_empty(), "{}", parse.debug_tree());
+
+        hir::lower(ast::Root::cast(parse.syntax()).unwrap())
+    }
+
+    fn compile_str(module: &mut Module, input: &str) -> Result<Body, CompileError> {
+        compile(module, &lower(input), &LineIndex::new(input))
+    }
+
+    fn run_vm(module: &mut Module, vm: &mut Vm, input: &str) -> Result<Value, EvalError> {
+        let main = compile_str(module, input).unwrap();
+        vm.run(module, &main)
+    }
+
+    #[test]
+    fn backends_agree() {
+        for input in PROGRAMS {
+            let tree = Interpreter::new().run(&lower(input));
+            let vm = run_vm(&mut Module::new(), &mut Vm::new(), input);
+
+            assert_eq!(tree, vm, "backends disagree on {:?}", input);
+        }
+    }
+
+    #[test]
+    fn optimized_programs_agree() {
+        for input in PROGRAMS {
+            let program = lower(input);
+            // Constant division by zero is reported by the optimizer rather than at runtime.
+            let Ok(optimized) = opt::optimize(&program) else {
+                continue;
+            };
+
+            let expected = Interpreter::new().run(&program);
+            let tree = Interpreter::new().run(&optimized);
+            let mut module = Module::new();
+            let main = compile(&mut module, &optimized, &LineIndex::new(input)).unwrap();
+            let vm = Vm::new().run(&module, &main);
+
+            assert_eq!(tree, expected, "optimizing changed {:?}", input);
+            assert_eq!(vm, expected, "optimizing changed {:?} in the VM", input);
+        }
+    }
+
+    #[test]
+    fn backends_agree_across_runs() {
+        let lines = [
+            "let a = 5",
+            "fn inc(x) { x + a }",
+            "inc(1)",
+            "let a = 10",
+            "inc(1)",
+            "fn inc(x) { x - a }",
+            "inc(1)",
+            "b",
+        ];
+
+        let mut interpreter = Interpreter::new();
+        let mut module = Module::new();
+        let mut vm = Vm::new();
+
+        for input in lines {
+            let tree = interpreter.run(&lower(input));
+            let vm = run_vm(&mut module, &mut vm, input);
+
+            assert_eq!(tree, vm, "backends disagree on {:?}", input);
+        }
+    }
+
+    #[test]
+    fn backends_stop_at_the_same_limits() {
+        let mut doubling = "fn f0() { 1 }".to_string();
+        for i in 1..=40 {
+            doubling += &format!("\nfn f{}() {{ f{}() + f{}() }}", i, i - 1, i - 1);
+        }
+        doubling += "\nf40()";
+
+        let cases = [
+            (
+                Limits {
+                    max_depth: Some(50),
+                    ..Limits::default()
+                },
+                "fn f(n) { f(n + 1) }\nf(0)",
+            ),
+            (
+                Limits {
+                    max_memory: Some(1024),
+                    ..Limits::default()
+                },
+                "fn f(n) { let a = n let b = n f(a + b) }\nf(1)",
+            ),
+            (
+                Limits {
+                    fuel: Some(10_000),
+                    ..Limits::default()
+                },
+                &doubling,
+            ),
+            (
+                Limits {
+                    timeout: Some(std::time::Duration::from_millis(10)),
+                    ..Limits::default()
+                },
+                &doubling,
+            ),
+        ];
+
+        for (limits, input) in cases {
+            let mut interpreter = Interpreter::new();
+            interpreter.set_limits(limits);
+            let tree = interpreter.run(&lower(input));
+
+            let mut vm = Vm::new();
+            vm.set_limits(limits);
+            let vm = run_vm(&mut Module::new(), &mut vm, input);
+
+            assert!(
+                matches!(tree, Err(EvalError::LimitExceeded(_))),
+                "{:?} on {:?}",
+                tree,
+                input,
+            );
+            assert_eq!(tree, vm, "backends disagree on {:?}", input);
+        }
+    }
+
+    #[test]
+    fn compile_arithmetic() {
+        let mut module = Module::new();
+        let main = compile_str(&mut module, "1 + 2 * 3").unwrap();
+
+        assert_eq!(module.constants, [1, 2, 3]);
+        assert_eq!(
+            main.code,
+            [
+                OpCode::Constant as u8,
+                0,
+                0,
+                OpCode::Constant as u8,
+                1,
+                0,
+                OpCode::Constant as u8,
+                2,
+                0,
+                OpCode::Mul as u8,
+                OpCode::Add as u8,
+                OpCode::Return as u8,
+            ],
+        );
+    }
+
+    #[test]
+    fn share_constants_across_compiles() {
+        let mut module = Module::new();
+        compile_str(&mut module, "1 + 2 * 1").unwrap();
+        let main = compile_str(&mut module, "2 - 3").unwrap();
+
+        assert_eq!(module.constants, [1, 2, 3]);
+        assert_eq!(
+            main.code,
+            [
+                OpCode::Constant as u8,
+                1,
+                0,
+                OpCode::Constant as u8,
+                2,
+                0,
+                OpCode::Sub as u8,
+                OpCode::Return as u8,
+            ],
+        );
+    }
+
+    #[test]
+    fn compile_too_many_constants() {
+        let mut module = Module::new();
+        module.constants = (0..=i64::from(u16::MAX)).collect();
+
+        assert_eq!(
+            compile_str(&mut module, "65536"),
+            Err(CompileError::TooManyConstants),
+        );
+        assert_eq!(module.constants.len(), 65536);
+        assert!(compile_str(&mut module, "65535").is_ok());
+    }
+
+    #[test]
+    fn reuse_local_slots_across_sibling_blocks() {
+        let mut module = Module::new();
+        let main = compile_str(&mut module, "{ let a = 1 let b = 2 } { let c = 3 }").unwrap();
+
+        assert_eq!(main.num_locals, 2);
+    }
+
+    #[test]
+    fn compile_literal_out_of_range() {
+        assert_eq!(
+            compile_str(&mut Module::new(), "9223372036854775808"),
+            Err(CompileError::LiteralOutOfRange(9223372036854775808)),
+        );
+    }
+
+    fn compile_executable(input: &str) -> Executable {
+        let mut module = Module::new();
+        let main = compile_str(&mut module, input).unwrap();
+
+        Executable { module, main }
+    }
+
+    fn check_disassembly(input: &str, expected: Expect) {
+        let executable = compile_executable(input);
+        expected.assert_eq(&disassemble(&executable.module, &executable.main));
+    }
+
+    #[test]
+    fn disassemble_functions_and_globals() {
+        check_disassembly(
+            "fn add(x, y) {\n  let sum = x + y\n  sum\n}\nlet a = add(1, 2)\nmissing(a)",
+            expect![[r#"
+                constants: 0=1 1=2
+                globals: 0=a
+
+                fn add/2 (3 locals)
+                0000    2 GetLocal 0
+                0003    | GetLocal 1
+                0006    | Add
+                0007    | SetLocal 2
+                0010    3 GetLocal 2
+                0013    1 Return
+
+                fn missing (undefined)
+
+                fn <main>/0 (0 locals)
+                0000    5 Constant 0 (1)
+                0003    | Constant 1 (2)
+                0006    | Call 0 (add) 2
+                0010    | SetGlobal 0 (a)
+                0013    6 GetGlobal 0 (a)
+                0016    | Call 1 (missing) 1
+                0020    | Return
+            "#]],
+        );
+    }
+
+    #[test]
+    fn disassemble_restores_line_after_block() {
+        check_disassembly(
+            "1 + {\n  2\n} + 3",
+            expect![[r#"
+            constants: 0=1 1=2 2=3
+            globals:
+
+            fn <main>/0 (0 locals)
+            0000    1 Constant 0 (1)
+            0003    2 Constant 1 (2)
+            0006    1 Add
+            0007    | Constant 2 (3)
+            0010    | Add
+            0011    | Return
+        "#]],
+        );
+    }
+
+    #[test]
+    fn executable_round_trips_through_bytes() {
+        for input in PROGRAMS {
+            let executable = compile_executable(input);
+            let bytes = executable.to_bytes();
+
+            assert_eq!(Executable::from_bytes(&bytes), Ok(executable));
+        }
+    }
+
+    #[test]
+    fn loaded_executable_runs() {
+        let executable = compile_executable("fn sq(x) { x * x }\nsq(7) - 1");
+        let loaded = Executable::from_bytes(&executable.to_bytes()).unwrap();
+
+        assert_eq!(
+            Vm::new().run(&loaded.module, &loaded.main),
+            Ok(Value::Int(48)),
+        );
+    }
+
+    #[test]
+    fn reject_bad_magic() {
+        assert_eq!(
+            Executable::from_bytes(b"ELF\0\x01\x00"),
+            Err(FormatError::BadMagic),
+        );
+    }
+
+    #[test]
+    fn reject_unsupported_version() {
+        let mut bytes = compile_executable("1").to_bytes();
+        bytes[4] = 99;
+
+        assert_eq!(
+            Executable::from_bytes(&bytes),
+            Err(FormatError::UnsupportedVersion(99)),
+        );
+    }
+
+    #[test]
+    fn reject_truncated_module() {
+        let bytes = compile_executable("let a = 1\na").to_bytes();
+
+        for len in 0..bytes.len() {
+            assert!(Executable::from_bytes(&bytes[..len]).is_err());
+        }
+    }
+
+    #[test]
+    fn reject_out_of_bounds_operand() {
+        let mut executable = compile_executable("1");
+        executable.main.code[1] = 5;
+
+        assert_eq!(
+            Executable::from_bytes(&executable.to_bytes()),
+            Err(FormatError::InvalidBytecode {
+                function: "<main>".to_string(),
+                offset: 0,
+            }),
+        );
+    }
+
+    #[test]
+    fn reject_stack_underflow() {
+        let mut executable = compile_executable("1");
+        executable.main.code = vec![OpCode::Add as u8, OpCode::Return as u8];
+
+        assert_eq!(
+            Executable::from_bytes(&executable.to_bytes()),
+            Err(FormatError::InvalidBytecode {
+                function: "<main>".to_string(),
+                offset: 0,
+            }),
+        );
+    }
+
+    #[test]
+    fn reject_code_without_return() {
+        let mut executable = compile_executable("1");
+        executable.main.code.pop();
+
+        assert_eq!(
+            Executable::from_bytes(&executable.to_bytes()),
+            Err(FormatError::InvalidBytecode {
+                function: "<main>".to_string(),
+                offset: 3,
+            }),
+        );
+    }
+}
@@ -0,0 +1,95 @@
+/// A compiled program: the constant pool, global table and function table shared by all the
+/// bytecode compiled into it. The REPL keeps compiling new input into the same module so that
+/// globals and functions from earlier lines stay reachable.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Module {
+    pub constants: Vec<i64>,
+    pub globals: Vec<String>,
+    pub functions: Vec<Function>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Function {
+    pub name: String,
+    /// `None` for functions that have been called but never defined.
+    pub body: Option<Body>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Body {
+    pub arity: u8,
+    pub num_locals: u16,
+    pub code: Vec<u8>,
+    /// Source lines for the code, sorted by offset. Each entry covers the code from its offset
+    /// up to the next entry.
+    pub lines: Vec<LineEntry>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LineEntry {
+    pub offset: u32,
+    /// One-based source line.
+    pub line: u32,
+}
+
+impl Body {
+    pub fn line_at(&self, offset: usize) -> Option<u32> {
+        let idx = self
+            .lines
+            .partition_point(|entry| entry.offset as usize <= offset);
+
+        Some(self.lines.get(idx.checked_sub(1)?)?.line)
+    }
+}
+
+impl Module {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The index of `n` in the constant pool, adding it if it isn't there yet, so that the pool
+    /// holds each value once however often it's used. There's no index if the pool is full.
+    pub(crate) fn constant_index(&mut self, n: i64) -> Option<u16> {
+        let idx = match self.constants.iter().position(|&constant| constant == n) {
+            Some(idx) => idx,
+            None if self.constants.len() <= usize::from(u16::MAX) => {
+                self.constants.push(n);
+                self.constants.len() - 1
+            }
+            None => return None,
+        };
+
+        u16::try_from(idx).ok()
+    }
+
+    pub(crate) fn global_index(&mut self, name: &str) -> Option<u16> {
+        let idx = match self.globals.iter().position(|global| global == name) {
+            Some(idx) => idx,
+            None => {
+                self.globals.push(name.to_string());
+                self.globals.len() - 1
+            }
+        };
+
+        u16::try_from(idx).ok()
+    }
+
+    pub(crate) fn function_index(&mut self, name: &str) -> Option<u16> {
+        let idx = match self
+            .functions
+            .iter()
+            .position(|function| function.name == name)
+        {
+            Some(idx) => idx,
+            None => {
+                self.functions.push(Function {
+                    name: name.to_string(),
+                    body: None,
+                });
+                self.functions.len() - 1
+            }
+        };
+
+        u16::try_from(idx).ok()
+    }
+}
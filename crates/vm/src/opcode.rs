@@ -0,0 +1,47 @@
+/// A single bytecode instruction. Operands follow the opcode byte inline in the code, with
+/// `u16` operands encoded little-endian.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, FromPrimitive, ToPrimitive)]
+pub enum OpCode {
+    /// `u16` index into the constant pool.
+    Constant,
+    Unit,
+    /// `u16` local slot.
+    GetLocal,
+    /// `u16` local slot.
+    SetLocal,
+    /// `u16` index into the global table.
+    GetGlobal,
+    /// `u16` index into the global table.
+    SetGlobal,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Neg,
+    Pop,
+    /// `u16` index into the function table, then a `u8` argument count.
+    Call,
+    Return,
+}
+
+impl OpCode {
+    /// The number of operand bytes that follow the opcode.
+    pub fn operand_width(self) -> usize {
+        match self {
+            Self::Constant
+            | Self::GetLocal
+            | Self::SetLocal
+            | Self::GetGlobal
+            | Self::SetGlobal => 2,
+            Self::Call => 3,
+            Self::Unit
+            | Self::Add
+            | Self::Sub
+            | Self::Mul
+            | Self::Div
+            | Self::Neg
+            | Self::Pop
+            | Self::Return => 0,
+        }
+    }
+}
@@ -0,0 +1,171 @@
+use crate::{Body, Module, OpCode};
+use eval::{Budget, EvalError, Limits, Value};
+use num_traits::FromPrimitive;
+use std::mem;
+
+/// A stack-based virtual machine executing bytecode from a [`Module`].
+///
+/// Global values live in the VM rather than the module, so that running more code compiled
+/// into the same module sees the globals defined by earlier runs.
+#[derive(Debug, Default)]
+pub struct Vm {
+    globals: Vec<Option<Value>>,
+    limits: Limits,
+}
+
+struct Frame<'m> {
+    code: &'m [u8],
+    ip: usize,
+    locals_base: usize,
+}
+
+impl Frame<'_> {
+    fn read_u8(&mut self) -> u8 {
+        let byte = self.code[self.ip];
+        self.ip += 1;
+        byte
+    }
+
+    fn read_u16(&mut self) -> u16 {
+        let bytes = [self.code[self.ip], self.code[self.ip + 1]];
+        self.ip += 2;
+        u16::from_le_bytes(bytes)
+    }
+}
+
+impl Vm {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Bounds every later run by `limits`.
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.limits = limits;
+    }
+
+    /// Runs `main`, which must have been compiled into `module`, returning the value it
+    /// leaves on the stack.
+    pub fn run(&mut self, module: &Module, main: &Body) -> Result<Value, EvalError> {
+        if self.globals.len() < module.globals.len() {
+            self.globals.resize(module.globals.len(), None);
+        }
+
+        let mut budget = Budget::new(self.limits);
+        let mut stack = Vec::new();
+        let mut locals = vec![Value::Unit; usize::from(main.num_locals)];
+        let mut frames = Vec::new();
+        let mut frame = Frame {
+            code: &main.code,
+            ip: 0,
+            locals_base: 0,
+        };
+        budget.hold(locals.len())?;
+
+        loop {
+            budget.step()?;
+
+            let op = OpCode::from_u8(frame.read_u8()).expect("invalid opcode");
+
+            match op {
+                OpCode::Constant => {
+                    let idx = frame.read_u16();
+                    stack.push(Value::Int(module.constants[usize::from(idx)]));
+                }
+                OpCode::Unit => stack.push(Value::Unit),
+                OpCode::GetLocal => {
+                    let slot = frame.locals_base + usize::from(frame.read_u16());
+                    stack.push(locals[slot]);
+                }
+                OpCode::SetLocal => {
+                    let slot = frame.locals_base + usize::from(frame.read_u16());
+                    locals[slot] = pop(&mut stack);
+                }
+                OpCode::GetGlobal => {
+                    let idx = usize::from(frame.read_u16());
+                    let value = self.globals[idx]
+                        .ok_or_else(|| EvalError::UndefinedVariable(module.globals[idx].clone()))?;
+                    stack.push(value);
+                }
+                OpCode::SetGlobal => {
+                    let idx = usize::from(frame.read_u16());
+                    self.globals[idx] = Some(pop(&mut stack));
+                }
+                OpCode::Add | OpCode::Sub | OpCode::Mul | OpCode::Div => {
+                    let rhs = pop_int(&mut stack)?;
+                    let lhs = pop_int(&mut stack)?;
+
+                    let op = match op {
+                        OpCode::Add => hir::BinaryOp::Add,
+                        OpCode::Sub => hir::BinaryOp::Sub,
+                        OpCode::Mul => hir::BinaryOp::Mul,
+                        _ => hir::BinaryOp::Div,
+                    };
+
+                    stack.push(Value::Int(eval::binary(op, lhs, rhs)?));
+                }
+                OpCode::Neg => {
+                    let value = pop_int(&mut stack)?;
+                    stack.push(Value::Int(eval::unary(hir::UnaryOp::Neg, value)?));
+                }
+                OpCode::Pop => {
+                    pop(&mut stack);
+                }
+                OpCode::Call => {
+                    let function = &module.functions[usize::from(frame.read_u16())];
+                    let argc = usize::from(frame.read_u8());
+
+                    let Some(body) = &function.body else {
+                        return Err(EvalError::UndefinedFunction(function.name.clone()));
+                    };
+
+                    if usize::from(body.arity) != argc {
+                        return Err(EvalError::ArityMismatch {
+                            name: function.name.clone(),
+                            expected: usize::from(body.arity),
+                            found: argc,
+                        });
+                    }
+
+                    budget.enter()?;
+                    budget
+                        .hold(stack.len() - argc + locals.len() + usize::from(body.num_locals))?;
+
+                    let locals_base = locals.len();
+                    locals.extend(stack.drain(stack.len() - argc..));
+                    locals.resize(locals_base + usize::from(body.num_locals), Value::Unit);
+
+                    let callee = Frame {
+                        code: &body.code,
+                        ip: 0,
+                        locals_base,
+                    };
+                    frames.push(mem::replace(&mut frame, callee));
+                }
+                OpCode::Return => {
+                    let value = pop(&mut stack);
+                    locals.truncate(frame.locals_base);
+
+                    match frames.pop() {
+                        Some(caller) => {
+                            budget.exit();
+                            frame = caller;
+                            stack.push(value);
+                        }
+                        None => return Ok(value),
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn pop(stack: &mut Vec<Value>) -> Value {
+    stack.pop().expect("stack underflow")
+}
+
+fn pop_int(stack: &mut Vec<Value>) -> Result<i64, EvalError> {
+    match pop(stack) {
+        Value::Int(n) => Ok(n),
+        value => Err(EvalError::ExpectedInt(value)),
+    }
+}
@@ -0,0 +1,563 @@
+//! Compiles programs to WebAssembly text format.
+//!
+//! Every value is an `i64`, so only programs whose statements all produce integers can be
+//! compiled. The module exports a single `main` function returning the value of the program's
+//! last statement. Runtime errors that the interpreter would report trap instead, each with its
+//! own trap code: overflow is an integer overflow, division by zero an integer division by zero,
+//! and reading a global before it is defined reaches `unreachable`.
+
+mod error;
+
+pub use error::CompileError;
+
+use hir::{BinaryOp, Expr, ModuleScope, Program, Stmt, StmtKind, UnaryOp};
+use std::fmt::Write;
+
+/// Checked arithmetic helpers included in every module. Division needs no helper, since
+/// `i64.div_s` already traps on both division by zero and overflow, and `$overflow` uses it to
+/// trap the same way when the other operations overflow.
+const PRELUDE: &str = "  (func $overflow
+    i64.const -9223372036854775808
+    i64.const -1
+    i64.div_s
+    drop)
+  (func $checked.add (param $a i64) (param $b i64) (result i64)
+    (local $r i64)
+    local.get $a
+    local.get $b
+    i64.add
+    local.set $r
+    local.get $r
+    local.get $a
+    i64.xor
+    local.get $r
+    local.get $b
+    i64.xor
+    i64.and
+    i64.const 0
+    i64.lt_s
+    if
+      call $overflow
+    end
+    local.get $r)
+  (func $checked.sub (param $a i64) (param $b i64) (result i64)
+    (local $r i64)
+    local.get $a
+    local.get $b
+    i64.sub
+    local.set $r
+    local.get $a
+    local.get $b
+    i64.xor
+    local.get $a
+    local.get $r
+    i64.xor
+    i64.and
+    i64.const 0
+    i64.lt_s
+    if
+      call $overflow
+    end
+    local.get $r)
+  (func $checked.mul (param $a i64) (param $b i64) (result i64)
+    (local $r i64)
+    local.get $a
+    local.get $b
+    i64.mul
+    local.set $r
+    local.get $a
+    i64.const 0
+    i64.ne
+    if
+      local.get $r
+      local.get $a
+      i64.div_s
+      local.get $b
+      i64.ne
+      if
+        call $overflow
+      end
+    end
+    local.get $r)
+  (func $checked.neg (param $a i64) (result i64)
+    local.get $a
+    i64.const -9223372036854775808
+    i64.eq
+    if
+      call $overflow
+    end
+    i64.const 0
+    local.get $a
+    i64.sub)
+";
+
+pub fn compile(program: &Program) -> Result<String, CompileError> {
+    let scope = ModuleScope::new(program);
+
+    let mut s = String::from("(module\n");
+
+    for global in &scope.globals {
+        writeln!(s, "  (global $g.{} (mut i64) (i64.const 0))", global).unwrap();
+        writeln!(
+            s,
+            "  (global $g.{}.defined (mut i32) (i32.const 0))",
+            global
+        )
+        .unwrap();
+    }
+
+    s.push_str(PRELUDE);
+
+    for fn_def in &scope.fns {
+        let mut codegen = FnCodegen::new(&scope);
+
+        for param in &fn_def.params {
+            codegen.declare_local(param);
+        }
+        codegen.compile_expr(&fn_def.body)?;
+
+        let signature = format!(
+            "$fn.{}{}",
+            fn_def.name,
+            " (param i64)".repeat(fn_def.params.len()),
+        );
+        codegen.finish(&mut s, &signature, fn_def.params.len());
+    }
+
+    let mut codegen = FnCodegen::new(&scope);
+    codegen.compile_stmts(&program.stmts, true)?;
+    codegen.finish(&mut s, "$main (export \"main\")", 0);
+
+    s.push_str(")\n");
+
+    Ok(s)
+}
+
+struct FnCodegen<'a> {
+    scope: &'a ModuleScope<'a>,
+    body: String,
+    depth: usize,
+    locals: Vec<String>,
+    num_locals: usize,
+}
+
+impl<'a> FnCodegen<'a> {
+    fn new(scope: &'a ModuleScope<'a>) -> Self {
+        Self {
+            scope,
+            body: String::new(),
+            depth: 2,
+            locals: Vec::new(),
+            num_locals: 0,
+        }
+    }
+
+    fn finish(self, s: &mut String, signature: &str, num_params: usize) {
+        writeln!(s, "  (func {} (result i64)", signature).unwrap();
+
+        let num_locals = self.num_locals - num_params;
+        if num_locals > 0 {
+            writeln!(s, "    (local{})", " i64".repeat(num_locals)).unwrap();
+        }
+
+        s.push_str(&self.body);
+        s.pop();
+        s.push_str(")\n");
+    }
+
+    /// Compiles a statement list, leaving the value of its last statement on the stack.
+    fn compile_stmts(&mut self, stmts: &[Stmt], is_top_level: bool) -> Result<(), CompileError> {
+        let scope_start = self.locals.len();
+
+        match stmts.last() {
+            Some(Stmt {
+                kind: StmtKind::Expr(_),
+                ..
+            }) => {}
+            _ => return Err(CompileError::UnitValue),
+        }
+
+        for (idx, stmt) in stmts.iter().enumerate() {
+            match &stmt.kind {
+                StmtKind::VariableDef { name, value } => {
+                    self.compile_expr(value)?;
+
+                    if is_top_level {
+                        self.emit(format!("global.set $g.{}", name));
+                        self.emit("i32.const 1");
+                        self.emit(format!("global.set $g.{}.defined", name));
+                    } else {
+                        let idx = self.declare_local(name);
+                        self.emit(format!("local.set {}", idx));
+                    }
+                }
+                StmtKind::Expr(expr) => {
+                    self.compile_expr(expr)?;
+
+                    if idx != stmts.len() - 1 {
+                        self.emit("drop");
+                    }
+                }
+            }
+        }
+
+        self.locals.truncate(scope_start);
+
+        Ok(())
+    }
+
+    fn compile_expr(&mut self, expr: &Expr) -> Result<(), CompileError> {
+        match expr {
+            Expr::Missing => return Err(CompileError::MissingExpr),
+            Expr::Binary { op, lhs, rhs } => {
+                self.compile_expr(lhs)?;
+                self.compile_expr(rhs)?;
+
+                self.emit(match op {
+                    BinaryOp::Add => "call $checked.add",
+                    BinaryOp::Sub => "call $checked.sub",
+                    BinaryOp::Mul => "call $checked.mul",
+                    BinaryOp::Div => "i64.div_s",
+                });
+            }
+            Expr::Literal { n } => {
+                let n = i64::try_from(*n).map_err(|_| CompileError::LiteralOutOfRange(*n))?;
+                self.emit(format!("i64.const {}", n));
+            }
+            Expr::Unary { op, expr } => {
+                self.compile_expr(expr)?;
+
+                self.emit(match op {
+                    UnaryOp::Neg => "call $checked.neg",
+                });
+            }
+            Expr::VariableRef { var } => {
+                if let Some(idx) = self.locals.iter().rposition(|local| local == var) {
+                    self.emit(format!("local.get {}", idx));
+                } else if self.scope.is_global(var) {
+                    self.emit(format!("global.get $g.{}.defined", var));
+                    self.emit("i32.eqz");
+                    self.emit("if");
+                    self.depth += 1;
+                    self.emit("unreachable");
+                    self.depth -= 1;
+                    self.emit("end");
+                    self.emit(format!("global.get $g.{}", var));
+                } else {
+                    return Err(CompileError::UndefinedVariable(var.clone()));
+                }
+            }
+            Expr::Block { stmts } => self.compile_stmts(stmts, false)?,
+            Expr::Call { callee, args } => {
+                let expected = *self
+                    .scope
+                    .arities
+                    .get(callee.as_str())
+                    .ok_or_else(|| CompileError::UndefinedFunction(callee.clone()))?;
+
+                if expected != args.len() {
+                    return Err(CompileError::ArityMismatch {
+                        name: callee.clone(),
+                        expected,
+                        found: args.len(),
+                    });
+                }
+
+                for arg in args {
+                    self.compile_expr(arg)?;
+                }
+
+                self.emit(format!("call $fn.{}", callee));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn declare_local(&mut self, name: &str) -> usize {
+        self.locals.push(name.to_string());
+        self.num_locals = self.num_locals.max(self.locals.len());
+
+        self.locals.len() - 1
+    }
+
+    fn emit(&mut self, instruction: impl AsRef<str>) {
+        writeln!(
+            self.body,
+            "{:indent$}{}",
+            "",
+            instruction.as_ref(),
+            indent = self.depth * 2,
+        )
+        .unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ast::AstNode;
+    use eval::{EvalError, Interpreter, Value};
+    use expect_test::{expect, Expect};
+    use wasmi::core::TrapCode;
+
+    /// The inputs of the expression tests in `parser`'s `grammar/expr.rs` that parse without
+    /// errors.
+    const EXPR_TESTS: &[&str] = &[
+        "123",
+        "abc",
+        "1+2",
+        "1+2+3+4",
+        "1+2*3-4",
+        "--10",
+        "-10+20",
+        "((((((10))))))",
+        "5*(2+1)",
+        "   9876",
+        "999   ",
+        " 123     ",
+        " 1 +   2* 3 ",
+        "\n1\n  + 1 # Add one\n  + 10 # Add ten",
+        "foo()",
+        "1 + f(2)",
+    ];
+
+    const PROGRAMS: &[&str] = &[
+        "let a = 10\nlet b = a * 2\nb - a",
+        "let a = 1\nlet a = a + 1\na",
+        "let x = 1\n{ let x = x + 1 x } + x",
+        "{ let a = 1 { let b = a + 1 { let c = b * 10 c + a } } }",
+        "fn add(x, y) { x + y }\nadd(1, 2) * 2",
+        "double(21)\nfn double(x) { x * 2 }",
+        "fn f() { 7 }\nfn g(x) { f() * x }\ng(g(2))",
+        "fn f(a, b, c) { let d = a * b { let e = d - c e * e } }\nf(3, 4, 2) + f(1, 1, 1)",
+        "fn f() { 1 }\nfn f() { 2 }\nf()",
+        "fn main() { 5 }\nmain() + 1",
+        "fn f() { g }\nf()\nlet g = 1\ng",
+        "fn f() { g }\nlet g = 1\nf()",
+        "1 / (2 - 2)",
+        "9223372036854775807 + 1",
+        "-9223372036854775807 - 2",
+        "4611686018427387904 * 2",
+        "-1 * (-9223372036854775807 - 1)",
+        "(-9223372036854775807 - 1) / -1",
+        "-(-9223372036854775807 - 1)",
+        "-9223372036854775807 - 1",
+        "3037000499 * 3037000499",
+    ];
+
+    fn lower(input: &str) -> Program {
+        let parse = parser::parse(input);
+        assert!(parse.errors().is_empty(), "{}", parse.debug_tree());
+
+        hir::lower(ast::Root::cast(parse.syntax()).unwrap())
+    }
+
+    fn run_wasm(wat: &str) -> Result<i64, wasmi::Error> {
+        let bytes = wat::parse_str(wat).expect("emitted invalid wat");
+
+        let engine = wasmi::Engine::default();
+        let module = wasmi::Module::new(&engine, &bytes[..]).expect("emitted invalid module");
+        let mut store = wasmi::Store::new(&engine, ());
+        let instance = wasmi::Linker::<()>::new(&engine)
+            .instantiate(&mut store, &module)?
+            .start(&mut store)?;
+
+        instance
+            .get_typed_func::<(), i64>(&store, "main")?
+            .call(&mut store, ())
+    }
+
+    fn check_against_interpreter(input: &str) {
+        let program = lower(input);
+
+        let wat = match compile(&program) {
+            Ok(wat) => wat,
+            Err(e) => panic!("failed to compile {:?}: {}", input, e),
+        };
+
+        match (Interpreter::new().run(&program), run_wasm(&wat)) {
+            (Ok(Value::Int(expected)), Ok(actual)) => {
+                assert_eq!(expected, actual, "wrong result for {:?}", input)
+            }
+            (Err(expected), Err(actual))
+                if trap_code(&expected).is_some()
+                    && trap_code(&expected) == actual.as_trap_code() => {}
+            (expected, actual) => panic!(
+                "interpreter gave {:?} but wasm gave {:?} for {:?}",
+                expected, actual, input,
+            ),
+        }
+    }
+
+    /// The trap the compiled program should stop with when the interpreter reports `error`.
+    fn trap_code(error: &EvalError) -> Option<TrapCode> {
+        match error {
+            EvalError::Overflow => Some(TrapCode::IntegerOverflow),
+            EvalError::DivisionByZero => Some(TrapCode::IntegerDivisionByZero),
+            EvalError::UndefinedVariable(_) => Some(TrapCode::UnreachableCodeReached),
+            _ => None,
+        }
+    }
+
+    fn check(input: &str, expected: Expect) {
+        expected.assert_eq(&compile(&lower(input)).unwrap());
+    }
+
+    #[test]
+    fn expr_tests_agree_with_interpreter() {
+        for input in EXPR_TESTS {
+            // Define the names they refer to, so that they compile.
+            check_against_interpreter(&format!(
+                "fn foo() {{ 3 }}\nfn f(x) {{ x * 2 }}\nlet abc = 7\nlet result = {}\nresult",
+                input,
+            ));
+        }
+    }
+
+    #[test]
+    fn programs_agree_with_interpreter() {
+        for input in PROGRAMS {
+            check_against_interpreter(input);
+        }
+    }
+
+    #[test]
+    fn emit_module() {
+        check(
+            "let a = 2\nfn f(x) { let y = x * a y }\nf(3)",
+            expect![[r#"
+                (module
+                  (global $g.a (mut i64) (i64.const 0))
+                  (global $g.a.defined (mut i32) (i32.const 0))
+                  (func $overflow
+                    i64.const -9223372036854775808
+                    i64.const -1
+                    i64.div_s
+                    drop)
+                  (func $checked.add (param $a i64) (param $b i64) (result i64)
+                    (local $r i64)
+                    local.get $a
+                    local.get $b
+                    i64.add
+                    local.set $r
+                    local.get $r
+                    local.get $a
+                    i64.xor
+                    local.get $r
+                    local.get $b
+                    i64.xor
+                    i64.and
+                    i64.const 0
+                    i64.lt_s
+                    if
+                      call $overflow
+                    end
+                    local.get $r)
+                  (func $checked.sub (param $a i64) (param $b i64) (result i64)
+                    (local $r i64)
+                    local.get $a
+                    local.get $b
+                    i64.sub
+                    local.set $r
+                    local.get $a
+                    local.get $b
+                    i64.xor
+                    local.get $a
+                    local.get $r
+                    i64.xor
+                    i64.and
+                    i64.const 0
+                    i64.lt_s
+                    if
+                      call $overflow
+                    end
+                    local.get $r)
+                  (func $checked.mul (param $a i64) (param $b i64) (result i64)
+                    (local $r i64)
+                    local.get $a
+                    local.get $b
+                    i64.mul
+                    local.set $r
+                    local.get $a
+                    i64.const 0
+                    i64.ne
+                    if
+                      local.get $r
+                      local.get $a
+                      i64.div_s
+                      local.get $b
+                      i64.ne
+                      if
+                        call $overflow
+                      end
+                    end
+                    local.get $r)
+                  (func $checked.neg (param $a i64) (result i64)
+                    local.get $a
+                    i64.const -9223372036854775808
+                    i64.eq
+                    if
+                      call $overflow
+                    end
+                    i64.const 0
+                    local.get $a
+                    i64.sub)
+                  (func $fn.f (param i64) (result i64)
+                    (local i64)
+                    local.get 0
+                    global.get $g.a.defined
+                    i32.eqz
+                    if
+                      unreachable
+                    end
+                    global.get $g.a
+                    call $checked.mul
+                    local.set 1
+                    local.get 1)
+                  (func $main (export "main") (result i64)
+                    i64.const 2
+                    global.set $g.a
+                    i32.const 1
+                    global.set $g.a.defined
+                    i64.const 3
+                    call $fn.f)
+                )
+            "#]],
+        );
+    }
+
+    #[test]
+    fn reject_unit_values() {
+        assert_eq!(compile(&lower("let a = 1")), Err(CompileError::UnitValue));
+        assert_eq!(compile(&lower("{ }")), Err(CompileError::UnitValue));
+        assert_eq!(
+            compile(&lower("fn f() { let a = 1 }\n1")),
+            Err(CompileError::UnitValue),
+        );
+    }
+
+    #[test]
+    fn reject_undefined_names() {
+        assert_eq!(
+            compile(&lower("abc")),
+            Err(CompileError::UndefinedVariable("abc".to_string())),
+        );
+        assert_eq!(
+            compile(&lower("foo()")),
+            Err(CompileError::UndefinedFunction("foo".to_string())),
+        );
+    }
+
+    #[test]
+    fn reject_arity_mismatch() {
+        assert_eq!(
+            compile(&lower("fn f(x) { x }\nf()")),
+            Err(CompileError::ArityMismatch {
+                name: "f".to_string(),
+                expected: 1,
+                found: 0,
+            }),
+        );
+    }
+}
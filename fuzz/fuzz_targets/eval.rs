@@ -0,0 +1,51 @@
+//! Runs arbitrary programs that parse through both backends, checking that they agree on the
+//! value or error of each one. Every program is run with limits, since nothing else stops it
+//! from recursing forever.
+
+#![no_main]
+
+use ast::AstNode;
+use eval::{EvalError, Interpreter, Limits};
+use libfuzzer_sys::fuzz_target;
+use syntax::LineIndex;
+use vm::{Module, Vm};
+
+const LIMITS: Limits = Limits {
+    fuel: Some(100_000),
+    max_depth: Some(64),
+    max_memory: Some(1 << 16),
+    timeout: None,
+};
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(input) = std::str::from_utf8(data) else {
+        return;
+    };
+
+    let parse = parser::parse(input);
+    if !parse.errors().is_empty() {
+        return;
+    }
+
+    let program = hir::lower(ast::Root::cast(parse.syntax()).unwrap());
+
+    let mut interpreter = Interpreter::new();
+    interpreter.set_limits(LIMITS);
+    let tree = interpreter.run(&program);
+
+    let mut module = Module::new();
+    let Ok(main) = vm::compile(&mut module, &program, &LineIndex::new(input)) else {
+        return;
+    };
+    let mut vm = Vm::new();
+    vm.set_limits(LIMITS);
+    let vm = vm.run(&module, &main);
+
+    // The backends count fuel and memory differently, so one can run out before the other.
+    let limited = |result: &Result<_, _>| matches!(result, Err(EvalError::LimitExceeded(_)));
+    if limited(&tree) || limited(&vm) {
+        return;
+    }
+
+    assert_eq!(tree, vm, "backends disagree on {:?}", input);
+});
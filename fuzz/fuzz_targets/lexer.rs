@@ -0,0 +1,26 @@
+//! Lexes arbitrary text, checking that the tokens cover all of it, in order and without gaps.
+
+#![no_main]
+
+use lexer::Lexer;
+use libfuzzer_sys::fuzz_target;
+use text_size::TextSize;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(input) = std::str::from_utf8(data) else {
+        return;
+    };
+
+    let mut text = String::new();
+    let mut end = TextSize::from(0);
+
+    for token in Lexer::new(input) {
+        assert_eq!(token.range.start(), end);
+        assert_eq!(token.range.len(), TextSize::of(token.text));
+
+        text.push_str(token.text);
+        end = token.range.end();
+    }
+
+    assert_eq!(text, input);
+});
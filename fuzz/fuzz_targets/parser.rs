@@ -0,0 +1,26 @@
+//! Parses arbitrary text, checking that the tree holds exactly the text it was parsed from and
+//! that every error points into it. Reaching the `unreachable!()` in the parser's sink would
+//! panic, so that's checked too.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use syntax::SyntaxKind;
+use text_size::{TextRange, TextSize};
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(input) = std::str::from_utf8(data) else {
+        return;
+    };
+
+    let parse = parser::parse(input);
+    let root = parse.syntax();
+
+    assert_eq!(root.kind(), SyntaxKind::Root);
+    assert_eq!(root.text_range(), TextRange::up_to(TextSize::of(input)));
+    assert_eq!(root.to_string(), input);
+
+    for error in parse.errors() {
+        assert!(root.text_range().contains_range(error.range()));
+    }
+});